@@ -0,0 +1,62 @@
+//! Non-fatal issues recorded while building a site.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::Error;
+
+/// How severe a [`Diagnostic`] is. Only warnings fail strict builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Expected but worth knowing, e.g. that a draft wasn't rendered.
+    Note,
+    /// Likely a mistake, e.g. content that has no template.
+    Warning,
+}
+
+/// A non-fatal issue found while building, about the file at `path`, relative
+/// to the site's root, if it's about any one file.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub path: Option<PathBuf>,
+    pub error: Error,
+}
+
+/// Diagnostics recorded while building. Recording is safe from any thread, so
+/// items rendered in parallel can record diagnostics as they go.
+#[derive(Debug, Default)]
+pub struct Diagnostics(Mutex<Vec<Diagnostic>>);
+
+/// The diagnostics of a build, each sorted by the path they're about. Those
+/// not about any one file come first.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub warnings: Vec<Error>,
+    pub notes: Vec<Error>,
+}
+
+impl Diagnostics {
+    pub fn push(&self, level: Level, path: Option<&Path>, error: Error) {
+        let path = path.map(|path| path.to_path_buf());
+        self.0.lock().unwrap().push(Diagnostic { level, path, error });
+    }
+
+    /// Takes all of the diagnostics recorded so far. Since the order in which
+    /// they're recorded varies between parallel builds, diagnostics about the
+    /// same path are sorted by message so that reports are reproducible.
+    pub fn take(&self) -> Report {
+        let mut diagnostics = std::mem::take(&mut *self.0.lock().unwrap());
+        diagnostics.sort_by_cached_key(|d| (d.path.clone(), d.error.to_string()));
+
+        let mut report = Report::default();
+        for diagnostic in diagnostics {
+            match diagnostic.level {
+                Level::Note => report.notes.push(diagnostic.error),
+                Level::Warning => report.warnings.push(diagnostic.error),
+            }
+        }
+
+        report
+    }
+}
@@ -9,7 +9,7 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub struct Error {
     detail: Vec<Box<dyn ErrorDetail>>,
     prev: Option<Box<Error>>,
-    _location: &'static Location<'static>,
+    location: &'static Location<'static>,
 }
 
 pub trait ErrorDetail: fmt::Display + fmt::Debug + Send + Sync {
@@ -41,6 +41,53 @@ impl Error {
         _chain(self, &mut other);
         other
     }
+
+    /// Iterates over the layers of the error, from the outermost, the one
+    /// added last, to the innermost, the [`Error::root_cause()`].
+    ///
+    /// ```rust
+    /// use harper::error;
+    /// use harper::error::Chainable;
+    ///
+    /// let result: harper::error::Result<()> = Err(error!("file not found", "path" => "a.md"));
+    /// let error = result.chain(error!("failed to render")).unwrap_err();
+    ///
+    /// let messages: Vec<_> = error.iter().map(|layer| layer.message()).collect();
+    /// assert_eq!(messages, ["failed to render", "file not found"]);
+    ///
+    /// let cause = error.root_cause();
+    /// assert_eq!(cause.context(), [(Some("path".to_string()), "a.md".to_string())]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = Layer<'_>> {
+        std::iter::successors(Some(self), |error| error.prev.as_deref()).map(Layer)
+    }
+
+    /// The innermost layer of the error: the one that caused all others.
+    pub fn root_cause(&self) -> Layer<'_> {
+        self.iter().last().expect("an error has at least one layer")
+    }
+}
+
+/// A single layer of an [`Error`], as yielded by [`Error::iter()`].
+#[derive(Debug, Copy, Clone)]
+pub struct Layer<'a>(&'a Error);
+
+impl Layer<'_> {
+    /// The layer's message, without context.
+    pub fn message(&self) -> String {
+        self.0.detail.iter().map(|detail| detail.to_string()).collect::<Vec<_>>().join("\n")
+    }
+
+    /// The layer's context as `(key, value)` pairs. Values without a key,
+    /// such as the sources of a standard library error, have a key of `None`.
+    pub fn context(&self) -> Vec<(Option<String>, String)> {
+        self.0.detail.iter().flat_map(|detail| detail.context()).collect()
+    }
+
+    /// Where in the source code the layer was created.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.0.location
+    }
 }
 
 impl ErrorDetail for &(dyn StdError + Send + Sync) {
@@ -84,6 +131,7 @@ macro_rules! impl_error_detail_with_std_error {
 impl_error_detail_with_std_error!(io::Error);
 impl_error_detail_with_std_error!(toml::de::Error);
 impl_error_detail_with_std_error!(serde_json::Error);
+impl_error_detail_with_std_error!(serde_yaml::Error);
 
 impl ErrorDetail for String { }
 impl ErrorDetail for &str { }
@@ -96,7 +144,7 @@ impl Clone for Error {
                 .map(|error| Box::new(error) as Box<dyn ErrorDetail>)
                 .collect(),
             prev: self.prev.clone(),
-            _location: self._location,
+            location: self.location,
         }
     }
 }
@@ -107,7 +155,7 @@ impl<T: ErrorDetail + 'static> From<T> for Error {
         Error {
             prev: None,
             detail: vec![Box::new(detail)],
-            _location: std::panic::Location::caller(),
+            location: std::panic::Location::caller(),
         }
     }
 }
@@ -147,7 +195,7 @@ impl fmt::Display for Error {
                     }
 
                     if std::env::var_os("RUST_BACKTRACE").is_some() {
-                        writeln!(f, "{indent}[{}]", e._location)?;
+                        writeln!(f, "{indent}[{}]", e.location)?;
                     }
                 }
 
@@ -0,0 +1,227 @@
+//! RSS and Atom feeds of a site's or a collection's items.
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::fstree::EntryId;
+use crate::url::{Url, UrlBuf};
+use crate::util::escape_html;
+
+/// The format a feed is written in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    /// RSS 2.0, written to `feed.xml`.
+    #[default]
+    Rss,
+    /// Atom 1.0, written to `atom.xml`.
+    Atom,
+}
+
+impl FeedFormat {
+    /// The name of the file a feed in this format is written to.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "feed.xml",
+            FeedFormat::Atom => "atom.xml",
+        }
+    }
+
+    /// The media type of the format, as used in `<link rel="alternate">`.
+    pub fn media_type(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml",
+            FeedFormat::Atom => "application/atom+xml",
+        }
+    }
+}
+
+/// A feed written for a site, registered in [`Site::feeds`] so that templates
+/// can link to it.
+///
+/// [`Site::feeds`]: crate::taxonomy::Site::feeds
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedLink {
+    /// The collection whose items are in the feed, or `None` if the feed is
+    /// site-wide.
+    pub collection: Option<EntryId>,
+    pub format: FeedFormat,
+    /// The URL the feed is served from.
+    pub url: UrlBuf,
+}
+
+/// A feed of entries, rendered by [`Feed::render()`].
+///
+/// Entries are added as they are: which items they're for and where their
+/// values come from is up to the caller. Undated entries are left out if
+/// [`Feed::omit_undated()`] is set.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use harper::feed::{Feed, FeedEntry, FeedFormat};
+/// use harper::url::Url;
+///
+/// let (link, url) = (Url::new("https://example.com/"), Url::new("https://example.com/atom.xml"));
+/// let entry = FeedEntry {
+///     title: "Hello".into(),
+///     link: Url::from(Arc::from("https://example.com/hello/")),
+///     date: Some(0),
+///     summary: None,
+/// };
+///
+/// let xml = Feed::new(FeedFormat::Atom, "Blog", link, url)
+///     .author("Ann")
+///     .entries([entry])
+///     .render();
+///
+/// assert!(xml.contains("<author>\n<name>Ann</name>\n</author>"));
+/// assert!(xml.contains("<id>https://example.com/hello/</id>"));
+/// ```
+#[derive(Debug)]
+pub struct Feed<'a> {
+    format: FeedFormat,
+    title: &'a str,
+    link: &'a Url,
+    url: &'a Url,
+    author: Option<&'a str>,
+    entries: Vec<FeedEntry>,
+    limit: Option<usize>,
+    omit_undated: bool,
+}
+
+/// An entry in a [`Feed`].
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    pub title: Arc<str>,
+    /// The URL of the entry's page, which also identifies the entry.
+    pub link: Arc<Url>,
+    /// When the entry was published, in seconds since the Unix epoch.
+    pub date: Option<i64>,
+    /// A summary of the entry, as HTML.
+    pub summary: Option<Arc<str>>,
+}
+
+impl<'a> Feed<'a> {
+    /// A feed titled `title` of the page at `link`, served from `url`.
+    pub fn new(format: FeedFormat, title: &'a str, link: &'a Url, url: &'a Url) -> Self {
+        Feed {
+            format, title, link, url,
+            author: None,
+            entries: vec![],
+            limit: None,
+            omit_undated: false,
+        }
+    }
+
+    /// Names `author` as the feed's author. Atom feeds require one, so they
+    /// name the feed's title if no author is set.
+    pub fn author(mut self, author: &'a str) -> Self {
+        self.author = Some(author);
+        self
+    }
+
+    /// Adds `entries`.
+    pub fn entries<I: IntoIterator<Item = FeedEntry>>(mut self, entries: I) -> Self {
+        self.entries.extend(entries);
+        self
+    }
+
+    /// Includes only the `limit` most recent entries.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Leaves out entries without a date when `omit` is `true`. Otherwise,
+    /// they're included after every dated entry.
+    pub fn omit_undated(mut self, omit: bool) -> Self {
+        self.omit_undated = omit;
+        self
+    }
+
+    /// Renders the feed with the most recent entries first. Undated entries
+    /// come last, in the order they were added.
+    pub fn render(&self) -> String {
+        let mut entries: Vec<&FeedEntry> = self.entries.iter()
+            .filter(|entry| !(self.omit_undated && entry.date.is_none()))
+            .collect();
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+        entries.truncate(self.limit.unwrap_or(usize::MAX));
+        match self.format {
+            FeedFormat::Rss => self.rss(&entries),
+            FeedFormat::Atom => self.atom(&entries),
+        }
+    }
+
+    fn rss(&self, entries: &[&FeedEntry]) -> String {
+        let (title, link) = (escape_html(self.title), escape_html(self.link.as_str()));
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+        let _ = writeln!(xml, "<title>{title}</title>\n<link>{link}</link>");
+        let _ = writeln!(xml, "<description>{title}</description>");
+        let _ = writeln!(xml, "<atom:link href=\"{}\" rel=\"self\" type=\"{}\"/>",
+            escape_html(self.url.as_str()), self.format.media_type());
+
+        if let Some(date) = entries.iter().find_map(|e| e.date).and_then(rfc2822) {
+            let _ = writeln!(xml, "<lastBuildDate>{date}</lastBuildDate>");
+        }
+
+        for entry in entries {
+            let link = escape_html(entry.link.as_str());
+            let _ = write!(xml, "<item>\n<title>{}</title>\n", escape_html(&entry.title));
+            let _ = write!(xml, "<link>{link}</link>\n<guid>{link}</guid>\n");
+            if let Some(date) = entry.date.and_then(rfc2822) {
+                let _ = writeln!(xml, "<pubDate>{date}</pubDate>");
+            }
+
+            if let Some(summary) = &entry.summary {
+                let _ = writeln!(xml, "<description>{}</description>", escape_html(summary));
+            }
+
+            xml.push_str("</item>\n");
+        }
+
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+
+    fn atom(&self, entries: &[&FeedEntry]) -> String {
+        let url = escape_html(self.url.as_str());
+        let updated = entries.iter().find_map(|e| e.date).unwrap_or(0);
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        let _ = writeln!(xml, "<title>{}</title>", escape_html(self.title));
+        let _ = writeln!(xml, "<link href=\"{}\"/>", escape_html(self.link.as_str()));
+        let _ = writeln!(xml, "<link href=\"{url}\" rel=\"self\" type=\"{}\"/>", self.format.media_type());
+        let _ = writeln!(xml, "<id>{url}</id>\n<updated>{}</updated>", rfc3339(updated));
+        let author = escape_html(self.author.unwrap_or(self.title));
+        let _ = writeln!(xml, "<author>\n<name>{author}</name>\n</author>");
+
+        for entry in entries {
+            let link = escape_html(entry.link.as_str());
+            let _ = write!(xml, "<entry>\n<title>{}</title>\n", escape_html(&entry.title));
+            let _ = write!(xml, "<link href=\"{link}\"/>\n<id>{link}</id>\n");
+            let _ = writeln!(xml, "<updated>{}</updated>", rfc3339(entry.date.unwrap_or(updated)));
+            if let Some(summary) = &entry.summary {
+                let _ = writeln!(xml, "<summary type=\"html\">{}</summary>", escape_html(summary));
+            }
+
+            xml.push_str("</entry>\n");
+        }
+
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
+
+fn rfc2822(timestamp: i64) -> Option<String> {
+    DateTime::from_timestamp(timestamp, 0).map(|date| date.to_rfc2822())
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0).unwrap_or_default().to_rfc3339()
+}
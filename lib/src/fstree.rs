@@ -1,7 +1,7 @@
 use std::{fs, fmt};
 use std::ops::Deref;
-use std::sync::Arc;
-use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::path::{Component, Path};
 use std::collections::VecDeque;
 
 use rustc_hash::FxHashMap;
@@ -27,17 +27,178 @@ pub struct OwnedEntry {
 pub struct Entry {
     pub id: EntryId,
     pub path: Arc<Path>,
+    /// The file system metadata. In-memory entries, and directories created
+    /// implicitly by [`FsTree::from_memory()`] and [`FsTree::from_entries()`],
+    /// have placeholder metadata instead. See [`Entry::fs_metadata()`].
     pub metadata: fs::Metadata,
     pub file_name: String,
     pub file_type: fs::FileType,
     pub parent: Option<EntryId>,
     pub children: Vec<EntryId>,
     pub depth: usize,
+    /// The contents of an in-memory file. Read in place of `path`.
+    pub contents: Option<Arc<str>>,
+    /// The hash of the contents, computed by [`Entry::content_hash()`].
+    hash: OnceLock<u128>,
+    /// Whether `metadata` is a placeholder.
+    placeholder: bool,
+}
+
+/// The kind of entry a traversal's [`Entries`] yields.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Dir,
 }
 
 #[derive(Default, Debug)]
 struct FsMetadata(Option<fs::Metadata>);
 
+/// The metadata of entries that aren't on the file system: that of the
+/// temporary directory for directories and of the running executable for
+/// files. Neither is read from, only stat'd.
+#[derive(Debug)]
+struct Placeholders {
+    dir: fs::Metadata,
+    file: fs::Metadata,
+}
+
+/// The name of the file, in the root of a tree, whose patterns [`FsTree::build()`]
+/// ignores.
+pub const IGNORE_FILE: &str = ".harperignore";
+
+/// Gitignore-style patterns matching paths to leave out of a tree.
+///
+/// Each pattern is a glob where `*` matches any run of characters but `/`,
+/// `?` any one character but `/`, and a `**` component any number of
+/// directories. A pattern with a `/` other than a trailing one matches paths
+/// relative to the root. Otherwise, it matches names at any depth. A trailing
+/// `/` matches only directories, and a leading `!` re-includes what earlier
+/// patterns ignore, except within ignored directories, which aren't walked.
+/// Blank lines and lines starting with `#` are skipped.
+///
+/// ```rust
+/// use std::path::Path;
+/// use harper::fstree::Ignore;
+///
+/// let ignore = Ignore::new(["*.tmp", "node_modules/", "/drafts/**/*.md", "!keep.tmp"]);
+/// assert!(ignore.is_ignored(Path::new("a/b.tmp"), false));
+/// assert!(!ignore.is_ignored(Path::new("a/keep.tmp"), false));
+/// assert!(ignore.is_ignored(Path::new("web/node_modules"), true));
+/// assert!(!ignore.is_ignored(Path::new("node_modules"), false));
+/// assert!(ignore.is_ignored(Path::new("drafts/x/y/post.md"), false));
+/// assert!(!ignore.is_ignored(Path::new("blog/drafts/post.md"), false));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Ignore {
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    components: Vec<String>,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl Ignore {
+    /// Parses each of `patterns`, each a line of a `.harperignore` file.
+    pub fn new<I, S>(patterns: I) -> Self
+        where I: IntoIterator<Item = S>, S: AsRef<str>
+    {
+        let rules = patterns.into_iter().filter_map(|pattern| {
+            let pattern = pattern.as_ref().trim_end();
+            if pattern.is_empty() || pattern.starts_with('#') {
+                return None;
+            }
+
+            let (negated, pattern) = match pattern.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern),
+            };
+
+            let (dir_only, pattern) = match pattern.strip_suffix('/') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern),
+            };
+
+            let anchored = pattern.contains('/');
+            let components: Vec<String> = pattern.split('/')
+                .filter(|c| !c.is_empty())
+                .map(String::from)
+                .collect();
+
+            (!components.is_empty()).then_some(IgnoreRule { components, negated, dir_only, anchored })
+        });
+
+        Ignore { rules: rules.collect() }
+    }
+
+    /// Reads patterns from the file at `path`, one per line. A missing file
+    /// has no patterns.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Ignore::new(contents.lines())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Ignore::default()),
+            Err(e) => err! {
+                "failed to read ignore file",
+                "path" => path.display(),
+                e,
+            },
+        }
+    }
+
+    /// Whether there are no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether `path`, relative to the root, is ignored. `is_dir` is whether
+    /// it's a directory. The last pattern matching `path` decides.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let components: Vec<_> = path.components()
+            .filter_map(|c| match c {
+                Component::Normal(c) => Some(c.to_string_lossy()),
+                _ => None,
+            })
+            .collect();
+
+        let Some(name) = components.last() else {
+            return false;
+        };
+
+        self.rules.iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only) && match rule.anchored {
+                    true => glob_components(&rule.components, &components),
+                    false => crate::util::glob_match(&rule.components[0], name),
+                }
+            })
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Matches path `components` against pattern `components`, where a `**`
+/// pattern component matches any number of path components.
+fn glob_components<P: AsRef<str>, C: AsRef<str>>(pattern: &[P], components: &[C]) -> bool {
+    match pattern.split_first() {
+        None => components.is_empty(),
+        Some((first, rest)) if first.as_ref() == "**" => {
+            (0..=components.len()).any(|i| glob_components(rest, &components[i..]))
+        }
+        Some((first, rest)) => match components.split_first() {
+            Some((component, remaining)) => {
+                crate::util::glob_match(first.as_ref(), component.as_ref())
+                    && glob_components(rest, remaining)
+            }
+            None => false,
+        },
+    }
+}
+
 impl FsTree {
     fn new() -> Self {
         Self {
@@ -46,25 +207,58 @@ impl FsTree {
         }
     }
 
+    /// Builds a tree of the directory `root`, leaving out the entries matched
+    /// by the patterns in `root/.harperignore`, if there is one. See
+    /// [`Ignore`] for the patterns' syntax.
     #[inline(always)]
     pub fn build<P: AsRef<Path>>(root: P) -> Result<Self> {
-        Self::build_with(root.as_ref(), |_, _| Ok(()))
+        let root = root.as_ref();
+        let ignore = Ignore::from_file(root.join(IGNORE_FILE))?;
+        Self::build_with_ignore(root, ignore, |_, _| Ok(()))
     }
 
     #[inline]
-    pub fn build_with<P, F>(root: P, mut callback: F) -> Result<Self>
+    pub fn build_with<P, F>(root: P, callback: F) -> Result<Self>
+        where P: AsRef<Path>,
+              F: FnMut(&Self, EntryId) -> Result<()>,
+    {
+        Self::build_with_ignore(root, Ignore::default(), callback)
+    }
+
+    /// Like [`FsTree::build_with()`], but leaves out entries that `ignore`
+    /// matches, by their path relative to `root`. Ignored directories aren't
+    /// walked at all. Building fails if every entry is ignored.
+    pub fn build_with_ignore<P, F>(root: P, ignore: Ignore, mut callback: F) -> Result<Self>
         where P: AsRef<Path>,
               F: FnMut(&Self, EntryId) -> Result<()>,
     {
+        use std::sync::atomic::{AtomicUsize, Ordering};
         use jwalk::WalkDirGeneric;
 
         let root = root.as_ref();
+        let ignored = Arc::new(AtomicUsize::new(0));
         let walker = WalkDirGeneric::<FsMetadata>::new(root)
             .follow_links(true)
-            .process_read_dir(|_, _, _, entries| {
-                entries.iter_mut()
-                    .filter_map(|e| e.as_mut().ok())
-                    .for_each(|e| e.client_state = FsMetadata(e.metadata().ok()))
+            .process_read_dir({
+                let (root, ignored) = (root.to_path_buf(), ignored.clone());
+                move |_, dir, _, entries| {
+                    if !ignore.is_empty() {
+                        let relative = dir.strip_prefix(&root).unwrap_or(dir);
+                        entries.retain(|e| match e {
+                            Ok(e) => {
+                                let path = relative.join(&e.file_name);
+                                let skip = ignore.is_ignored(&path, e.file_type.is_dir());
+                                ignored.fetch_add(skip as usize, Ordering::Relaxed);
+                                !skip
+                            }
+                            Err(_) => true,
+                        });
+                    }
+
+                    entries.iter_mut()
+                        .filter_map(|e| e.as_mut().ok())
+                        .for_each(|e| e.client_state = FsMetadata(e.metadata().ok()))
+                }
             });
 
         let mut tree: FsTree = FsTree::new();
@@ -73,11 +267,88 @@ impl FsTree {
             callback(&mut tree, id)?;
         }
 
-        if tree.len() == 0 {
-            return err! {
-                "file system tree discovery yielded zero files",
-                "search root" => root.display(),
-            }
+        // An empty but readable `root` yields itself, so this is never empty
+        // unless `root` is unreadable. It's left with only itself when every
+        // entry in it is ignored.
+        let ignored = ignored.load(Ordering::Relaxed);
+        if tree.len() == 0 || (tree.len() == 1 && ignored > 0) {
+            return match root.try_exists() {
+                Ok(false) => err! {
+                    "file system tree root does not exist",
+                    "search root" => root.display(),
+                },
+                _ if ignored > 0 => err! {
+                    "file system tree discovery yielded zero files",
+                    "search root" => root.display(),
+                    "ignored entries" => ignored,
+                    "help" => "every entry in the root matches an ignore pattern",
+                },
+                _ => err! {
+                    "file system tree discovery yielded zero files",
+                    "search root" => root.display(),
+                    "help" => "check that the root is readable",
+                },
+            };
+        }
+
+        Ok(tree)
+    }
+
+    /// Builds a tree rooted at the virtual directory `root` from in-memory
+    /// `files`, each a path relative to `root` and its contents. Nothing is
+    /// read from or written to the file system. Intermediate directories are
+    /// created as needed. Every entry has placeholder `metadata`, so
+    /// [`Entry::fs_metadata()`] returns `None`.
+    ///
+    /// ```rust
+    /// use harper::fstree::FsTree;
+    ///
+    /// let tree = FsTree::from_memory("/site", [
+    ///     ("content/index.md", "# Hello"),
+    ///     ("content/guide/intro.md", "# Intro"),
+    /// ]).unwrap();
+    ///
+    /// let intro = tree.get(None, "content/guide/intro.md").unwrap();
+    /// assert_eq!(intro.depth, 3);
+    /// assert!(tree.get(None, "content/guide").unwrap().file_type.is_dir());
+    /// assert!(intro.fs_metadata().is_none());
+    /// ```
+    pub fn from_memory<R, I, P, C>(root: R, files: I) -> Result<Self>
+        where R: AsRef<Path>,
+              I: IntoIterator<Item = (P, C)>,
+              P: AsRef<Path>,
+              C: Into<Arc<str>>,
+    {
+        let mut tree = FsTree::new();
+        let placeholders = Placeholders::new()?;
+        let root = tree.insert_virtual(None, root.as_ref(), placeholders.dir.clone(), true, None);
+        for (path, contents) in files {
+            let metadata = placeholders.file.clone();
+            tree.insert_relative(root, path.as_ref(), &placeholders, metadata, true, Some(contents.into()))?;
+        }
+
+        Ok(tree)
+    }
+
+    /// Builds a tree rooted at `root` from an explicit list of `entries`, each
+    /// a path relative to `root` and its file system metadata, instead of
+    /// walking the file system. Entries are inserted in the order given.
+    /// Intermediate directories not in `entries` are created with placeholder
+    /// metadata, as is `root` itself. A directory's metadata may be given after one of
+    /// its descendants.
+    ///
+    /// File contents are read from `root.join(path)` on demand, as with
+    /// [`FsTree::build()`].
+    pub fn from_entries<R, I, P>(root: R, entries: I) -> Result<Self>
+        where R: AsRef<Path>,
+              I: IntoIterator<Item = (P, fs::Metadata)>,
+              P: AsRef<Path>,
+    {
+        let mut tree = FsTree::new();
+        let placeholders = Placeholders::new()?;
+        let root = tree.insert_virtual(None, root.as_ref(), placeholders.dir.clone(), true, None);
+        for (path, metadata) in entries {
+            tree.insert_relative(root, path.as_ref(), &placeholders, metadata, false, None)?;
         }
 
         Ok(tree)
@@ -117,6 +388,57 @@ impl FsTree {
         self.map.get(&*full_path).cloned()
     }
 
+    /// Like [`FsTree::get_file_id()`], but see [`FsTree::get_id_ci()`].
+    pub fn get_file_id_ci<R, P>(&self, root: R, path: P) -> Option<EntryId>
+        where R: Into<Option<EntryId>>, P: AsRef<Path>
+    {
+        let id = self.get_id_ci(root.into(), path.as_ref())?;
+        self[id].file_type.is_file().then_some(id)
+    }
+
+    /// Like [`FsTree::get_id()`], but if there's no entry at exactly `path`,
+    /// matches each of its components against the names of the previous
+    /// one's children without regard to ASCII case. An exact match is
+    /// preferred at every step. Only the children along `path` are compared,
+    /// never the whole tree.
+    ///
+    /// ```rust
+    /// use harper::fstree::FsTree;
+    ///
+    /// let tree = FsTree::from_memory("/site", [("templates/page.html", "")]).unwrap();
+    /// assert!(tree.get_id(None, "Templates/Page.HTML").is_none());
+    /// assert_eq!(tree.get_id_ci(None, "Templates/Page.HTML"), tree.get_id(None, "templates/page.html"));
+    /// ```
+    pub fn get_id_ci<R, P>(&self, root: R, path: P) -> Option<EntryId>
+        where R: Into<Option<EntryId>>, P: AsRef<Path>
+    {
+        let root = root.into().unwrap_or(self.root_id());
+        if let Some(id) = self.get_id(root, path.as_ref()) {
+            return Some(id);
+        }
+
+        let mut id = root;
+        for component in path.as_ref().components() {
+            id = match component {
+                Component::CurDir => id,
+                Component::ParentDir => self[id].parent?,
+                Component::Normal(name) => {
+                    let children = &self[id].children;
+                    let name_str = name.to_str();
+                    children.iter()
+                        .find(|&&child| self[child].path.file_name() == Some(name))
+                        .or_else(|| children.iter().find(|&&child| {
+                            name_str.is_some_and(|n| self[child].file_name.eq_ignore_ascii_case(n))
+                        }))
+                        .copied()?
+                }
+                Component::RootDir | Component::Prefix(_) => return None,
+            };
+        }
+
+        Some(id)
+    }
+
     pub fn ancestors_of(&self, mut entry: EntryId) -> impl Iterator<Item = EntryId> + '_ {
         std::iter::from_fn(move || {
             let parent = self[entry].parent?;
@@ -134,7 +456,8 @@ impl FsTree {
             tree: self,
             root: Some(root),
             stack: VecDeque::new(),
-            progress: 0
+            progress: 0,
+            max_depth: usize::MAX,
         }
     }
 
@@ -142,6 +465,7 @@ impl FsTree {
         Dfs {
             tree: self,
             stack: { let mut q = VecDeque::new(); q.push_back(root); q },
+            max_depth: usize::MAX,
         }
     }
 
@@ -160,17 +484,19 @@ impl FsTree {
         _dfs(self, root, &mut progress)
     }
 
-    pub fn search<P: AsRef<Path>>(&self, path: P) -> Option<EntryId> {
-        let root_path = &self[self.root_id()].path;
-        for id in self.iter_breadth_first(self.root_id()) {
-            let entry = &self[id];
-            let suffix = entry.path.strip_prefix(&root_path).unwrap();
-            if path.as_ref() == suffix {
-                return Some(id);
-            }
-        }
+    /// Iterates breadth-first over `root` and its descendants, yielding each
+    /// entry's id along with its path relative to `root`. The relative path
+    /// of `root` itself is empty.
+    pub fn iter_relative(&self, root: EntryId) -> impl Iterator<Item = (EntryId, &Path)> + '_ {
+        let depth = self[root].depth;
+        self.iter_breadth_first(root)
+            .map(move |id| (id, self[id].path_suffix(self[id].depth - depth)))
+    }
 
-        None
+    pub fn search<P: AsRef<Path>>(&self, path: P) -> Option<EntryId> {
+        self.iter_relative(self.root_id())
+            .find(|(_, suffix)| path.as_ref() == *suffix)
+            .map(|(id, _)| id)
     }
 
     #[inline]
@@ -212,8 +538,95 @@ impl FsTree {
             parent: self.map.get(&entry.parent_path).cloned(),
             children: vec![],
             depth: entry.depth,
+            contents: None,
+            hash: OnceLock::new(),
+            placeholder: false,
         };
 
+        self.push(entry)
+    }
+
+    /// Inserts `path`, relative to `root`, creating intermediate directories.
+    fn insert_relative(
+        &mut self,
+        root: EntryId,
+        path: &Path,
+        placeholders: &Placeholders,
+        metadata: fs::Metadata,
+        placeholder: bool,
+        contents: Option<Arc<str>>,
+    ) -> Result<EntryId> {
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return err! {
+                "file tree entry paths must be relative and normalized",
+                "file path" => path.display(),
+            };
+        }
+
+        let mut parent = root;
+        let mut leaf = Some((metadata, contents));
+        let mut components = path.components().peekable();
+        while let Some(component) = components.next() {
+            let child_path = self[parent].path.join(component);
+            let existing = self.map.get(&*child_path).copied();
+            let is_leaf = components.peek().is_none();
+            parent = match (existing, is_leaf) {
+                (Some(id), false) if self[id].file_type.is_dir() => id,
+                (None, false) => {
+                    let metadata = placeholders.dir.clone();
+                    self.insert_virtual(Some(parent), &child_path, metadata, true, None)
+                }
+                (None, true) => {
+                    let (metadata, contents) = leaf.take().unwrap();
+                    self.insert_virtual(Some(parent), &child_path, metadata, placeholder, contents)
+                }
+                (Some(id), true) if !placeholder
+                    && self[id].placeholder
+                    && self[id].file_type.is_dir()
+                    && leaf.as_ref().is_some_and(|(m, _)| m.is_dir()) =>
+                {
+                    let entry = &mut self.entries[id.0];
+                    entry.metadata = leaf.take().unwrap().0;
+                    entry.file_type = entry.metadata.file_type();
+                    entry.placeholder = false;
+                    id
+                }
+                (Some(_), _) => return err! {
+                    "conflicting file tree entry path",
+                    "file path" => path.display(),
+                },
+            };
+        }
+
+        Ok(parent)
+    }
+
+    fn insert_virtual(
+        &mut self,
+        parent: Option<EntryId>,
+        path: &Path,
+        metadata: fs::Metadata,
+        placeholder: bool,
+        contents: Option<Arc<str>>,
+    ) -> EntryId {
+        let entry = Entry {
+            id: EntryId(self.entries.len()),
+            path: path.into(),
+            file_type: metadata.file_type(),
+            metadata,
+            file_name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            parent,
+            children: vec![],
+            depth: parent.map_or(0, |p| self[p].depth + 1),
+            contents,
+            hash: OnceLock::new(),
+            placeholder,
+        };
+
+        self.push(entry)
+    }
+
+    fn push(&mut self, entry: Entry) -> EntryId {
         self.map.insert(entry.path.clone(), entry.id);
         if let Some(parent) = entry.parent {
             self.entries[parent.0].children.push(entry.id);
@@ -258,6 +671,36 @@ impl PartialEq for OwnedEntry {
 impl Eq for OwnedEntry { }
 
 impl Entry {
+    /// A hash of the file's contents, read from disk or memory the first time
+    /// it's requested and cached thereafter. A directory has the hash of no
+    /// contents. The hash is a [`StableHasher`](crate::manifest::StableHasher)
+    /// hash, so it can be compared to one recorded by a previous build to
+    /// detect changes.
+    pub fn content_hash(&self) -> Result<u128> {
+        if let Some(hash) = self.hash.get() {
+            return Ok(*hash);
+        }
+
+        let mut hasher = crate::manifest::StableHasher::new();
+        match &self.contents {
+            Some(contents) => hasher.write(contents.as_bytes()),
+            None if self.file_type.is_file() => hasher.write(&fs::read(&self.path).map_err(|e| error! {
+                "failed to read file to hash",
+                "path" => self.path.display(),
+                e,
+            })?),
+            None => {},
+        }
+
+        Ok(*self.hash.get_or_init(|| hasher.finish()))
+    }
+
+    /// The file system metadata, or `None` if `metadata` is a placeholder, as
+    /// it is for in-memory entries.
+    pub fn fs_metadata(&self) -> Option<&fs::Metadata> {
+        (!self.placeholder).then_some(&self.metadata)
+    }
+
     /// File name without the extension.
     pub fn file_stem(&self) -> &str {
         match self.file_name.rsplit_once('.') {
@@ -266,6 +709,25 @@ impl Entry {
         }
     }
 
+    /// Whether the file name without the extension is exactly `stem`.
+    ///
+    /// Unlike comparing against [`Entry::file_stem()`], which is converted to
+    /// UTF-8 lossily, this compares the file name's raw bytes.
+    pub fn file_stem_eq(&self, stem: &str) -> bool {
+        let name = self.path.file_name().map_or(&[][..], |name| name.as_encoded_bytes());
+        let raw_stem = match name.iter().rposition(|&b| b == b'.') {
+            Some(i) => &name[..i],
+            None => name,
+        };
+
+        raw_stem == stem.as_bytes()
+    }
+
+    /// Whether the file name is valid UTF-8, and thus `file_name` is exact.
+    pub fn has_utf8_name(&self) -> bool {
+        self.path.file_name().is_none_or(|name| name.to_str().is_some())
+    }
+
     /// The complete extension, if any.
     pub fn file_ext(&self) -> Option<&str> {
         self.file_name.rsplit_once('.').map(|(_, right)| right)
@@ -273,12 +735,7 @@ impl Entry {
 
     /// Path relative to the root tree of `self`.
     pub fn relative_path(&self) -> &Path {
-        let mut components = self.path.components();
-        for _ in 0..(self.path.components().count() - self.depth) {
-            components.next();
-        }
-
-        components.as_path()
+        self.path_suffix(self.depth)
     }
 
     /// Path relative to `other`. `self` must be super-path of `other`.
@@ -287,13 +744,17 @@ impl Entry {
             return None;
         }
 
-        let n = self.depth - other.depth;
+        Some(self.path_suffix(self.depth - other.depth))
+    }
+
+    /// The last `n` components of `self.path`.
+    fn path_suffix(&self, n: usize) -> &Path {
         let mut components = self.path.components();
         for _ in 0..(self.path.components().count() - n) {
             components.next();
         }
 
-        Some(components.as_path())
+        components.as_path()
     }
 }
 
@@ -306,6 +767,8 @@ pub struct Bfs<'a> {
     root: Option<EntryId>,
     stack: VecDeque<EntryId>,
     progress: usize,
+    /// The absolute depth of the deepest entries to visit.
+    max_depth: usize,
 }
 
 impl Iterator for Bfs<'_> {
@@ -314,7 +777,10 @@ impl Iterator for Bfs<'_> {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             if let Some(root) = self.root.take() {
-                self.stack.push_front(root);
+                if self.tree[root].depth < self.max_depth {
+                    self.stack.push_front(root);
+                }
+
                 return Some(root);
             }
 
@@ -326,7 +792,8 @@ impl Iterator for Bfs<'_> {
                 let children = &self.tree[parent].children;
                 if self.progress < children.len() {
                     let node = children[self.progress];
-                    if !self.tree[node].children.is_empty() {
+                    let entry = &self.tree[node];
+                    if !entry.children.is_empty() && entry.depth < self.max_depth {
                         self.stack.push_back(node);
                     }
 
@@ -342,35 +809,63 @@ impl Iterator for Bfs<'_> {
 }
 
 impl<'a> Bfs<'a> {
+    /// Visits only entries at most `depth` levels below the root, which is at
+    /// depth `0`. Must be called before iterating.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        if let Some(root) = self.root {
+            self.max_depth = self.tree[root].depth.saturating_add(depth);
+        }
+
+        self
+    }
+
     #[inline]
-    pub fn entries(self) -> impl Iterator<Item = &'a Entry> {
-        let tree = self.tree;
-        self.into_iter().map(move |id| &tree[id])
+    pub fn entries(self) -> Entries<'a, Self> {
+        Entries::new(self.tree, self, None)
     }
 
     #[inline]
-    pub fn files(self) -> impl Iterator<Item = &'a Entry> {
-        let tree = self.tree;
-        self.into_iter().map(move |id| &tree[id]).filter(|e| e.metadata.is_file())
+    pub fn files(self) -> Entries<'a, Self> {
+        Entries::new(self.tree, self, Some(FileType::File))
+    }
+
+    #[inline]
+    pub fn dirs(self) -> Entries<'a, Self> {
+        Entries::new(self.tree, self, Some(FileType::Dir))
     }
 }
 
 pub struct Dfs<'a> {
     tree: &'a FsTree,
     stack: VecDeque<EntryId>,
+    /// The absolute depth of the deepest entries to visit.
+    max_depth: usize,
 }
 
 impl<'a> Dfs<'a> {
+    /// Visits only entries at most `depth` levels below the root, which is at
+    /// depth `0`. Must be called before iterating.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        if let Some(&root) = self.stack.front() {
+            self.max_depth = self.tree[root].depth.saturating_add(depth);
+        }
+
+        self
+    }
+
     #[inline]
-    pub fn entries(self) -> impl Iterator<Item = &'a Entry> {
-        let tree = self.tree;
-        self.into_iter().map(move |id| &tree[id])
+    pub fn entries(self) -> Entries<'a, Self> {
+        Entries::new(self.tree, self, None)
     }
 
     #[inline]
-    pub fn files(self) -> impl Iterator<Item = &'a Entry> {
-        let tree = self.tree;
-        self.into_iter().map(move |id| &tree[id]).filter(|e| e.metadata.is_file())
+    pub fn files(self) -> Entries<'a, Self> {
+        Entries::new(self.tree, self, Some(FileType::File))
+    }
+
+    #[inline]
+    pub fn dirs(self) -> Entries<'a, Self> {
+        Entries::new(self.tree, self, Some(FileType::Dir))
     }
 }
 
@@ -379,14 +874,72 @@ impl Iterator for Dfs<'_> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let node = self.stack.pop_front()?;
-        for &child in &self.tree[node].children {
-            self.stack.push_front(child);
+        let entry = &self.tree[node];
+        if entry.depth < self.max_depth {
+            for &child in &entry.children {
+                self.stack.push_front(child);
+            }
         }
 
         Some(node)
     }
 }
 
+/// An iterator over the entries visited by `I`, a traversal like [`Bfs`] or
+/// [`Dfs`], optionally only those of one [`FileType`]. Returned by `entries()`,
+/// `files()`, and `dirs()`.
+pub struct Entries<'a, I> {
+    tree: &'a FsTree,
+    ids: I,
+    file_type: Option<FileType>,
+}
+
+impl<'a, I> Entries<'a, I> {
+    fn new(tree: &'a FsTree, ids: I, file_type: Option<FileType>) -> Self {
+        Entries { tree, ids, file_type }
+    }
+}
+
+impl<'a, I: Iterator<Item = EntryId>> Iterator for Entries<'a, I> {
+    type Item = &'a Entry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.tree;
+        self.ids.by_ref()
+            .map(|id| &tree[id])
+            .find(|entry| match self.file_type {
+                Some(FileType::File) => entry.file_type.is_file(),
+                Some(FileType::Dir) => entry.file_type.is_dir(),
+                None => true,
+            })
+    }
+}
+
+impl FileType {
+    pub fn is_file(&self) -> bool {
+        *self == FileType::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        *self == FileType::Dir
+    }
+}
+
+impl Placeholders {
+    fn new() -> Result<Self> {
+        let metadata = |path: std::io::Result<std::path::PathBuf>| {
+            path.and_then(fs::metadata).map_err(|e| error! {
+                "failed to read placeholder metadata for in-memory entries",
+                e,
+            })
+        };
+
+        let dir = metadata(Ok(std::env::temp_dir()))?;
+        let file = metadata(std::env::current_exe())?;
+        Ok(Placeholders { dir, file })
+    }
+}
+
 impl jwalk::ClientState for FsMetadata {
     type ReadDirState = ();
     type DirEntryState = Self;
@@ -78,6 +78,10 @@ pub mod markdown;
 pub mod templating;
 pub mod path_str;
 pub mod url;
+pub mod feed;
+pub mod sitemap;
+pub mod manifest;
+pub mod diagnostics;
 
 pub use taxonomy::*;
 
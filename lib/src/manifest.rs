@@ -0,0 +1,150 @@
+//! Records of the outputs a build wrote, for incremental builds.
+//!
+//! A [`Manifest`] maps each source file, by its path relative to the site's
+//! root, to a [`Fingerprint`] of everything its output was rendered from. A
+//! build that finds an unchanged fingerprint in the previous build's manifest,
+//! and the output still in place, can skip rendering the file again.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Chainable, Result};
+
+/// The name of the manifest file in an output directory.
+pub const MANIFEST_FILE: &str = ".harper-manifest.json";
+
+/// What a source's output was rendered from: a `hash` of the source and its
+/// dependencies, and the `template` used, if any.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Fingerprint {
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+/// A map from source paths to the [`Fingerprint`] of their outputs, written
+/// as a JSON object sorted by path.
+///
+/// ```rust
+/// use std::path::Path;
+/// use harper::manifest::{Fingerprint, Manifest};
+///
+/// let mut manifest = Manifest::default();
+/// let fingerprint = Fingerprint::new(0xc0ffee, Some("page.html"));
+/// manifest.insert(Path::new("content/a.md"), fingerprint.clone());
+///
+/// assert!(manifest.is_fresh(Path::new("content/a.md"), &fingerprint));
+/// assert!(!manifest.is_fresh(Path::new("content/a.md"), &Fingerprint::new(1, Some("page.html"))));
+/// assert!(!manifest.is_fresh(Path::new("content/b.md"), &fingerprint));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Manifest {
+    entries: BTreeMap<String, Fingerprint>,
+}
+
+/// A hasher whose output is the same on every platform and with every build
+/// of Harper, so that hashes can be compared to those a previous build
+/// recorded. Unlike `std`'s and `rustc-hash`'s hashers, its algorithm, 128-bit
+/// SipHash-1-3 with fixed keys, never changes. It detects changes reliably but
+/// isn't cryptographic: it doesn't resist deliberately crafted collisions.
+///
+/// ```rust
+/// use harper::manifest::StableHasher;
+///
+/// let hash = |parts: &[&str]| {
+///     let mut hasher = StableHasher::new();
+///     parts.iter().for_each(|part| hasher.write(part.as_bytes()));
+///     hasher.finish()
+/// };
+///
+/// assert_eq!(hash(&["ab", "c"]), hash(&["ab", "c"]));
+/// assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct StableHasher(siphasher::sip128::SipHasher13);
+
+impl StableHasher {
+    pub fn new() -> Self {
+        StableHasher::default()
+    }
+
+    /// Hashes `bytes` and their length, so that a sequence of writes hashes
+    /// differently than the same bytes split another way.
+    pub fn write(&mut self, bytes: &[u8]) {
+        use std::hash::Hasher;
+
+        self.0.write(&(bytes.len() as u64).to_le_bytes());
+        self.0.write(bytes);
+    }
+
+    /// Hashes `hash`, as from another `StableHasher`.
+    pub fn write_hash(&mut self, hash: u128) {
+        self.write(&hash.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u128 {
+        use siphasher::sip128::Hasher128;
+
+        self.0.finish128().into()
+    }
+}
+
+impl Fingerprint {
+    pub fn new(hash: u128, template: Option<&str>) -> Self {
+        Fingerprint { hash: format!("{hash:032x}"), template: template.map(String::from) }
+    }
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`. A missing or malformed manifest, as from
+    /// a build that never finished, is empty, so everything is rendered.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        std::fs::read(path.as_ref()).ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the manifest to `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).chain_with(|| error! {
+            "failed to write build manifest",
+            "path" => path.display(),
+        })
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Fingerprint> {
+        self.entries.get(&*key(path))
+    }
+
+    /// Records `fingerprint` for the source at `path`, replacing any other.
+    pub fn insert(&mut self, path: &Path, fingerprint: Fingerprint) {
+        self.entries.insert(key(path), fingerprint);
+    }
+
+    /// Whether `fingerprint` is the one recorded for the source at `path`.
+    pub fn is_fresh(&self, path: &Path, fingerprint: &Fingerprint) -> bool {
+        self.get(path) == Some(fingerprint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The manifest key of `path`: its components joined with `/` on every
+/// platform, so manifests are portable.
+fn key(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
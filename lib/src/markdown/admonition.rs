@@ -1,14 +1,72 @@
 use std::borrow::Cow;
 use std::fmt::Write;
 
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
 use crate::error::Result;
 
 // TODO: Rename to "alert". Follow GitHub syntax?
 
 #[derive(Default, Clone)]
-pub struct Admonition;
+pub struct Admonition<'c> {
+    config: Cow<'c, AdmonitionConfig>,
+}
+
+/// How admonition names, as written by authors, map to markup.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdmonitionConfig {
+    /// Known admonitions by author-facing name, e.g. `tip`.
+    pub kinds: FxHashMap<String, AdmonitionKind>,
+    /// Whether admonitions with a name not in `kinds` are an error. When
+    /// `false`, they're emitted with their name as their class.
+    pub strict: bool,
+}
+
+/// The markup of an admonition with a given name.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdmonitionKind {
+    /// The CSS class, e.g. `note`. Defaults to the admonition's name.
+    pub class: Option<String>,
+    /// The title used when the author doesn't provide one, e.g. `Tip`.
+    pub title: Option<String>,
+}
 
-impl crate::markdown::Plugin for Admonition {
+impl<'c> Admonition<'c> {
+    pub fn with_config(config: &'c AdmonitionConfig) -> Self {
+        Admonition { config: Cow::Borrowed(config) }
+    }
+
+    /// Returns the class and title of the admonition `name` with the author's
+    /// `title`, which may be empty.
+    fn markup<'a>(&'a self, name: &'a str, title: &'a str) -> Result<(&'a str, &'a str)> {
+        let kind = match self.config.kinds.get(name) {
+            Some(kind) => kind,
+            None if self.config.strict => return err! {
+                "unknown admonition",
+                "name" => name,
+                "known names" => {
+                    let mut names: Vec<_> = self.config.kinds.keys().map(|k| &**k).collect();
+                    names.sort();
+                    names.join(", ")
+                },
+            },
+            None => return Ok((name, title)),
+        };
+
+        let class = kind.class.as_deref().unwrap_or(name);
+        let title = match title.is_empty() {
+            true => kind.title.as_deref().unwrap_or(title),
+            false => title,
+        };
+
+        Ok((class, title))
+    }
+}
+
+impl crate::markdown::Plugin for Admonition<'_> {
     fn preprocess<'a>(&self, input: &'a str) -> Result<Cow<'a, str>> {
         let mut output = Cow::Borrowed(input);
 
@@ -28,6 +86,7 @@ impl crate::markdown::Plugin for Admonition {
 
                 // Parse the admonition header and seek to the end of it.
                 let (name, title, h_end) = parse_admonition_header(&input[(k + 1)..]);
+                let (name, title) = self.markup(name, title)?;
                 bytes = &bytes[(h_end + i + 1)..];
 
                 // Find the end of the admonition and capture the whole thing.
@@ -119,3 +178,39 @@ fn find_admonition_end(mut bytes: &[u8]) -> usize {
 
     start.len() - bytes.len()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{Admonition, AdmonitionConfig, AdmonitionKind, Plugin};
+
+    fn config(strict: bool) -> AdmonitionConfig {
+        let tip = AdmonitionKind { class: Some("note".into()), title: Some("Tip".into()) };
+        AdmonitionConfig { kinds: [("tip".to_string(), tip)].into_iter().collect(), strict }
+    }
+
+    #[test]
+    fn mapped_class_and_default_title() {
+        let config = config(false);
+        let admonition = Admonition::with_config(&config);
+
+        let output = admonition.preprocess("!tip\n  Hi.\n").unwrap();
+        assert!(output.contains(r#"<div class="admonition note">"#), "{output}");
+        assert!(output.contains("\n\nTip\n\n"), "{output}");
+
+        let output = admonition.preprocess("!tip: Mine\n\n  Hi.\n").unwrap();
+        assert!(output.contains("\n\nMine\n\n") && !output.contains("Tip"), "{output}");
+
+        let output = admonition.preprocess("!warning\n  Hi.\n").unwrap();
+        assert!(output.contains(r#"<div class="admonition warning">"#), "{output}");
+    }
+
+    #[test]
+    fn strict_rejects_unknown_names() {
+        let config = config(true);
+        let admonition = Admonition::with_config(&config);
+        assert!(admonition.preprocess("!tip\n  Hi.\n").is_ok());
+
+        let error = admonition.preprocess("!warning\n  Hi.\n").unwrap_err().to_string();
+        assert!(error.contains("unknown admonition") && error.contains("tip"), "{error}");
+    }
+}
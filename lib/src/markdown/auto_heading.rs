@@ -4,12 +4,18 @@ use std::fmt::Write;
 use pulldown_cmark::{Event, Tag, CowStr, TagEnd};
 use rustc_hash::FxHashMap;
 
+use crate::util::SlugStyle;
 use super::Plugin;
 
+/// Assigns an id, slugified from its text, to every heading without one.
+/// Repeated ids are suffixed with `-1`, `-2`, and so on.
 #[derive(Default)]
-pub struct AutoHeading;
+pub struct AutoHeading {
+    style: SlugStyle,
+}
 
 struct HeadingIterator<'a, I: Iterator<Item = Event<'a>>> {
+    style: SlugStyle,
     stack: VecDeque<Event<'a>>,
     seen: FxHashMap<String, usize>,
     inner: I,
@@ -37,9 +43,10 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for HeadingIterator<'a, I> {
                     self.stack.push_back(event);
                 }
 
-                let mut id = crate::util::slugify(&text);
-                if let Some(n) = self.seen.get(&id) {
+                let mut id = self.style.slugify(&text);
+                if let Some(n) = self.seen.get_mut(&id) {
                     let _ = write!(&mut id, "-{}", n);
+                    *n += 1;
                 } else {
                     self.seen.insert(id.clone(), 1);
                 }
@@ -53,11 +60,18 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for HeadingIterator<'a, I> {
     }
 }
 
+impl AutoHeading {
+    pub fn with_style(style: SlugStyle) -> Self {
+        AutoHeading { style }
+    }
+}
+
 impl Plugin for AutoHeading {
     fn remap<'a, I>(&'a mut self, events: I) -> impl Iterator<Item = Event<'a>> + 'a
         where I: Iterator<Item = Event<'a>> + 'a
     {
         HeadingIterator {
+            style: self.style,
             seen: FxHashMap::default(),
             inner: events,
             stack: VecDeque::with_capacity(4),
@@ -101,3 +115,60 @@ impl Plugin for HeadingAnchor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{AutoHeading, Markdown, Renderer};
+    use crate::taxonomy::Metadata;
+    use crate::util::SlugStyle;
+
+    fn ids(style: SlugStyle, headings: &[&str]) -> Vec<String> {
+        let markdown: String = headings.iter().map(|h| format!("# {h}\n\n")).collect();
+        let metadata = Metadata::new();
+        Markdown::from(markdown)
+            .plugin(AutoHeading::with_style(style))
+            .plugin(Renderer::new(metadata.key("content")))
+            .run()
+            .unwrap();
+
+        let html = metadata.get_raw("content").unwrap();
+        html.as_str().unwrap()
+            .split("id=\"")
+            .skip(1)
+            .map(|rest| rest.split('"').next().unwrap().to_string())
+            .collect()
+    }
+
+    // Harper transliterates and collapses separators. GitHub keeps non-ASCII
+    // letters, drops punctuation and emoji, and maps each space to a `-`.
+    #[test]
+    fn harper_and_github_slugs() {
+        let headings = [
+            "What's new in v1.2?",
+            "Hello,  World!",
+            "🎉 Party time",
+            "日本語の見出し",
+            "Über cool_names - here",
+        ];
+
+        let harper = ids(SlugStyle::Harper, &headings);
+        assert_eq!(harper[..2], ["what-s-new-in-v1-2", "hello-world"]);
+        assert!(harper[2].ends_with("party-time"));
+        assert!(harper[3].is_ascii() && !harper[3].is_empty());
+        assert_eq!(harper[4], "uber-cool_names-here");
+
+        assert_eq!(ids(SlugStyle::GitHub, &headings), [
+            "whats-new-in-v12",
+            "hello--world",
+            "-party-time",
+            "日本語の見出し",
+            "über-cool_names---here",
+        ]);
+    }
+
+    #[test]
+    fn repeated_ids_are_numbered() {
+        let ids = ids(SlugStyle::GitHub, &["Setup", "Setup", "Setup"]);
+        assert_eq!(ids, ["setup", "setup-1", "setup-2"]);
+    }
+}
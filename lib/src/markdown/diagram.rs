@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+
+use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+use crate::util::escape_html;
+use super::Plugin;
+
+/// Passes fenced code blocks in diagram languages, like `mermaid`, through as
+/// `<pre class="mermaid">` with their source escaped, for a client-side script
+/// to render. The blocks are consumed, so plugins after this one, like
+/// [`SyntaxHighlight`](super::SyntaxHighlight), never see them.
+#[derive(Default, Clone)]
+pub struct DiagramPassthrough<'c> {
+    config: Cow<'c, DiagramConfig>,
+}
+
+/// Which code blocks are diagrams.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DiagramConfig {
+    /// The languages of diagram code blocks, e.g. `graphviz`, matched without
+    /// regard to case. The language is the block's class. Defaults to
+    /// `mermaid` alone.
+    pub languages: Vec<String>,
+}
+
+impl Default for DiagramConfig {
+    fn default() -> Self {
+        DiagramConfig { languages: vec!["mermaid".into()] }
+    }
+}
+
+impl<'c> DiagramPassthrough<'c> {
+    pub fn with_config(config: &'c DiagramConfig) -> Self {
+        DiagramPassthrough { config: Cow::Borrowed(config) }
+    }
+
+    /// The configured language the fence `label` names, if any. As with
+    /// highlighting, the language is everything before the first `,`.
+    fn language(&self, label: &str) -> Option<&str> {
+        let lang = label.split(',').next().unwrap_or_default().trim();
+        self.config.languages.iter()
+            .find(|l| l.eq_ignore_ascii_case(lang))
+            .map(|l| l.as_str())
+    }
+}
+
+impl Plugin for DiagramPassthrough<'_> {
+    fn remap<'a, I>(&'a mut self, mut events: I) -> impl Iterator<Item = Event<'a>> + 'a
+        where I: Iterator<Item = Event<'a>> + 'a
+    {
+        std::iter::from_fn(move || {
+            let event = events.next()?;
+            let lang = match &event {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(label))) => self.language(label),
+                _ => None,
+            };
+
+            let Some(lang) = lang else {
+                return Some(event);
+            };
+
+            let mut html = format!("<pre class=\"{}\">", escape_html(lang));
+            for event in events.by_ref() {
+                match event {
+                    Event::Text(text) => html.push_str(&escape_html(&text)),
+                    Event::End(TagEnd::CodeBlock) => break,
+                    _ => continue,
+                }
+            }
+
+            html.push_str("</pre>\n");
+            Some(Event::Html(html.into()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{CodeBlockStyle, DiagramConfig, DiagramPassthrough, Markdown, Renderer, SyntaxHighlight};
+    use crate::taxonomy::Metadata;
+
+    fn render(config: &DiagramConfig, markdown: &str) -> String {
+        let metadata = Metadata::new();
+        let style = CodeBlockStyle::default();
+        Markdown::from(markdown.to_string())
+            .plugin(DiagramPassthrough::with_config(config))
+            .plugin(SyntaxHighlight::with_style(&style))
+            .plugin(Renderer::new(metadata.key("content")))
+            .run()
+            .unwrap();
+
+        let content = metadata.get_raw("content").unwrap();
+        content.as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn passes_diagrams_through_unhighlighted() {
+        let config = DiagramConfig::default();
+        let html = render(&config, "Before.\n\n```mermaid\ngraph TD\n  A --> B & C\n```\n\nAfter.\n");
+        assert_eq!(html, concat!(
+            "<p>Before.</p>\n",
+            "<pre class=\"mermaid\">graph TD\n  A --&gt; B &amp; C\n</pre>\n",
+            "<p>After.</p>\n",
+        ));
+
+        let html = render(&config, "```Mermaid, nolines\npie\n```\n");
+        assert_eq!(html, "<pre class=\"mermaid\">pie\n</pre>\n");
+    }
+
+    #[test]
+    fn other_languages_are_highlighted() {
+        let config = DiagramConfig { languages: vec!["graphviz".into()] };
+        let html = render(&config, "```graphviz\ndigraph { a -> b }\n```\n\n```mermaid\npie\n```\n");
+        assert!(html.starts_with("<pre class=\"graphviz\">digraph { a -&gt; b }\n</pre>\n"), "{html}");
+        assert!(!html.contains("<pre class=\"mermaid\">"), "{html}");
+        assert!(html.contains("pie"), "{html}");
+    }
+}
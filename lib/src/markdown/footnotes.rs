@@ -0,0 +1,184 @@
+use std::fmt::Write;
+
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use rustc_hash::FxHashMap;
+
+use super::Plugin;
+
+/// Renders footnotes as a numbered list at the end of the document.
+///
+/// Definitions are moved out of the flow of the document and numbered in the
+/// order they're first referenced. Each reference becomes a superscript link
+/// to its footnote, `<li id="fn-N">` in an `<ol class="footnotes">`, and each
+/// footnote links back to every reference to it, so a footnote referenced
+/// twice has two backlinks. Definitions that are never referenced are
+/// dropped, and references without a definition render as a dangling `[?]`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Footnotes;
+
+/// The state of a single run: definitions by label, and the labels of
+/// referenced definitions, in footnote order, with their reference counts.
+#[derive(Default)]
+struct Collector<'a> {
+    definitions: FxHashMap<CowStr<'a>, Vec<Event<'a>>>,
+    numbered: Vec<(CowStr<'a>, usize)>,
+}
+
+impl<'a> Collector<'a> {
+    /// Replaces each reference in `events` with its link, numbering the
+    /// footnotes it refers to.
+    fn link(&mut self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        events.into_iter().map(|event| match event {
+            Event::FootnoteReference(label) => self.reference(label),
+            event => event,
+        }).collect()
+    }
+
+    fn reference(&mut self, label: CowStr<'a>) -> Event<'a> {
+        if !self.definitions.contains_key(&label) {
+            return Event::InlineHtml("<sup class=\"footnote-ref dangling\">[?]</sup>".into());
+        }
+
+        let i = match self.numbered.iter().position(|(l, _)| *l == label) {
+            Some(i) => i,
+            None => {
+                self.numbered.push((label, 0));
+                self.numbered.len() - 1
+            }
+        };
+
+        self.numbered[i].1 += 1;
+        let (n, k) = (i + 1, self.numbered[i].1);
+        let html = format!("<sup class=\"footnote-ref\" id=\"{}\"><a href=\"#fn-{n}\">{n}</a></sup>", ref_id(n, k));
+        Event::InlineHtml(html.into())
+    }
+
+    /// Renders the footnotes section. References within footnotes number
+    /// their footnotes, too, appending them to the section.
+    fn footnotes(mut self) -> Vec<Event<'a>> {
+        let mut events = vec![];
+        let mut i = 0;
+        while i < self.numbered.len() {
+            let label = self.numbered[i].0.clone();
+            let definition = self.definitions.get_mut(&label).map(std::mem::take).unwrap_or_default();
+            let mut definition = self.link(definition);
+
+            let n = i + 1;
+            let mut backlinks = String::new();
+            for k in 1..=self.numbered[i].1 {
+                let _ = write!(backlinks, " <a href=\"#{}\" class=\"footnote-backref\">↩", ref_id(n, k));
+                if k > 1 {
+                    let _ = write!(backlinks, "<sup>{k}</sup>");
+                }
+
+                backlinks.push_str("</a>");
+            }
+
+            // Keep the backlinks in the footnote's last paragraph, if it ends
+            // in one, so that they don't render on a line of their own.
+            let backlinks = Event::InlineHtml(backlinks.into());
+            match definition.last() {
+                Some(Event::End(TagEnd::Paragraph)) => definition.insert(definition.len() - 1, backlinks),
+                _ => definition.push(backlinks),
+            }
+
+            events.push(Event::Html(format!("<li id=\"fn-{n}\">\n").into()));
+            events.extend(definition);
+            events.push(Event::Html("</li>\n".into()));
+            i += 1;
+        }
+
+        if !events.is_empty() {
+            events.insert(0, Event::Html("<ol class=\"footnotes\">\n".into()));
+            events.push(Event::Html("</ol>\n".into()));
+        }
+
+        events
+    }
+}
+
+fn ref_id(n: usize, k: usize) -> String {
+    match k {
+        1 => format!("fnref-{n}"),
+        k => format!("fnref-{n}-{k}"),
+    }
+}
+
+impl Plugin for Footnotes {
+    fn remap<'a, I>(&'a mut self, events: I) -> impl Iterator<Item = Event<'a>> + 'a
+        where I: Iterator<Item = Event<'a>> + 'a
+    {
+        let mut collector = Collector::default();
+        let mut body = vec![];
+        let mut definition: Option<(CowStr<'a>, Vec<Event<'a>>)> = None;
+        for event in events {
+            match (event, &mut definition) {
+                (Event::Start(Tag::FootnoteDefinition(label)), None) => definition = Some((label, vec![])),
+                (Event::End(TagEnd::FootnoteDefinition), Some(_)) => {
+                    let (label, events) = definition.take().unwrap();
+                    collector.definitions.entry(label).or_insert(events);
+                }
+                (event, Some((_, events))) => events.push(event),
+                (event, None) => body.push(event),
+            }
+        }
+
+        let mut events = collector.link(body);
+        events.extend(collector.footnotes());
+        events.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{Footnotes, Markdown, Renderer};
+    use crate::taxonomy::Metadata;
+
+    fn render(markdown: &str) -> String {
+        let metadata = Metadata::new();
+        Markdown::from(markdown.to_string())
+            .plugin(Footnotes)
+            .plugin(Renderer::new(metadata.key("content")))
+            .run()
+            .unwrap();
+
+        let content = metadata.get_raw("content").unwrap();
+        content.as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn numbers_references_in_document_order() {
+        let html = render("A[^z] and B[^a] and A again[^z].\n\n[^a]: First *def*.\n\n[^z]: Second def.\n");
+        assert!(html.starts_with(concat!(
+            "<p>A<sup class=\"footnote-ref\" id=\"fnref-1\"><a href=\"#fn-1\">1</a></sup> ",
+            "and B<sup class=\"footnote-ref\" id=\"fnref-2\"><a href=\"#fn-2\">2</a></sup> ",
+            "and A again<sup class=\"footnote-ref\" id=\"fnref-1-2\"><a href=\"#fn-1\">1</a></sup>.</p>\n",
+        )), "{html}");
+
+        assert!(html.ends_with(concat!(
+            "<ol class=\"footnotes\">\n",
+            "<li id=\"fn-1\">\n<p>Second def. ",
+            "<a href=\"#fnref-1\" class=\"footnote-backref\">↩</a> ",
+            "<a href=\"#fnref-1-2\" class=\"footnote-backref\">↩<sup>2</sup></a></p>\n</li>\n",
+            "<li id=\"fn-2\">\n<p>First <em>def</em>. ",
+            "<a href=\"#fnref-2\" class=\"footnote-backref\">↩</a></p>\n</li>\n",
+            "</ol>\n",
+        )), "{html}");
+    }
+
+    #[test]
+    fn dangling_and_unreferenced() {
+        let html = render("Missing[^nope].\n\n[^unused]: Never referenced.\n");
+        assert_eq!(html, "<p>Missing<sup class=\"footnote-ref dangling\">[?]</sup>.</p>\n");
+
+        let html = render("No footnotes here.\n");
+        assert_eq!(html, "<p>No footnotes here.</p>\n");
+    }
+
+    #[test]
+    fn references_within_footnotes() {
+        let html = render("A[^a].\n\n[^a]: See[^b].\n\n[^b]: Nested.\n");
+        assert!(html.contains("<li id=\"fn-1\">\n<p>See<sup class=\"footnote-ref\" id=\"fnref-2\">"), "{html}");
+        assert!(html.contains("<li id=\"fn-2\">\n<p>Nested. <a href=\"#fnref-2\""), "{html}");
+    }
+}
@@ -1,33 +1,79 @@
 use std::borrow::Cow;
 
-use crate::error::Result;
+use crate::error::{Result, Chainable};
 use crate::value::{Mapper, Sink};
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct FrontMatter<M: Mapper, O: Sink> {
     mapper: M,
-    output: O
+    output: O,
+    fence: Cow<'static, str>,
+    required: bool,
 }
 
 impl<M: Mapper, O: Sink> FrontMatter<M, O> {
-    pub fn new(mapper: M, output: O) -> Self { Self { mapper, output } }
-}
+    pub const DEFAULT_FENCE: &'static str = "+++";
 
-impl<M: Mapper, O: Sink> crate::markdown::Plugin for FrontMatter<M, O> {
-    fn preprocess<'a>(&self, input: &'a str) -> Result<Cow<'a, str>> {
-        const PREFIX: &str = "+++\n";
-        const SUFFIX: &str = "\n+++\n";
+    pub fn new(mapper: M, output: O) -> Self {
+        Self { mapper, output, fence: Self::DEFAULT_FENCE.into(), required: false }
+    }
+
+    /// Sets the delimiter, e.g. `+++` or `---`, that opens and closes the
+    /// front matter, each on a line of its own. Defaults to `+++`.
+    pub fn fenced_by<F: Into<Cow<'static, str>>>(mut self, fence: F) -> Self {
+        self.fence = fence.into();
+        self
+    }
+
+    /// Sets whether a document without front matter is an error. Otherwise,
+    /// a document without front matter is treated as having no metadata.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Splits `input` into its front matter and content, if it has any front
+    /// matter, or errors if the front matter is unterminated.
+    fn split<'a>(&self, input: &'a str) -> Result<Option<(&'a str, &'a str)>> {
+        let fence = &*self.fence;
+        let Some(body) = input.strip_prefix(fence).filter(|body| body.starts_with('\n')) else {
+            return Ok(None);
+        };
 
-        if !input.starts_with(PREFIX) {
-            return Ok(Cow::Borrowed(input));
+        let closing = format!("\n{fence}");
+        for (i, _) in body.match_indices(&closing) {
+            let rest = &body[(i + closing.len())..];
+            if rest.is_empty() || rest.starts_with('\n') {
+                let front_matter = body.get(1..i).unwrap_or("");
+                let content = rest.strip_prefix('\n').unwrap_or(rest);
+                return Ok(Some((front_matter, content)));
+            }
         }
 
-        let (front_matter, content) = match input.split_once(SUFFIX) {
-            Some((prefix, content)) => (&prefix[PREFIX.len()..], content),
-            None => return Ok(Cow::Borrowed(input))
+        err!("unterminated front matter", "fence" => fence)
+    }
+}
+
+impl<M: Mapper + Default, O: Sink + Default> Default for FrontMatter<M, O> {
+    fn default() -> Self {
+        Self::new(M::default(), O::default())
+    }
+}
+
+impl<M: Mapper, O: Sink> crate::markdown::Plugin for FrontMatter<M, O> {
+    fn preprocess<'a>(&self, input: &'a str) -> Result<Cow<'a, str>> {
+        let (front_matter, content) = match self.split(input)? {
+            Some(split) => split,
+            None if self.required => return err! {
+                "missing required front matter",
+                "expected fence" => &*self.fence,
+            },
+            None => return Ok(Cow::Borrowed(input)),
         };
 
-        self.mapper.try_map_copy(front_matter, &self.output)?;
+        self.mapper.try_map_copy(front_matter, &self.output)
+            .chain(error!("malformed front matter"))?;
+
         Ok(Cow::Borrowed(content))
     }
 }
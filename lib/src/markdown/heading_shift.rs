@@ -0,0 +1,95 @@
+use pulldown_cmark::{Event, HeadingLevel, Tag, TagEnd};
+
+use super::Plugin;
+
+/// Offsets the level of every heading by a fixed amount, clamped at `h6`, so
+/// that, e.g., `#` headings render as `<h2>` in a page that already has an
+/// `<h1>`. Must run before [`AutoHeading`] and [`TableOfContents`] for ids and
+/// the table of contents to reflect the shifted levels.
+///
+/// [`AutoHeading`]: crate::markdown::AutoHeading
+/// [`TableOfContents`]: crate::markdown::TableOfContents
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HeadingShift {
+    offset: u8,
+}
+
+impl HeadingShift {
+    /// Shifts headings down by `offset` levels. An offset of `0` does nothing.
+    pub fn by(offset: u8) -> Self {
+        HeadingShift { offset }
+    }
+
+    fn shift(&self, level: HeadingLevel) -> HeadingLevel {
+        let level = (level as usize).saturating_add(self.offset.into()).min(6);
+        HeadingLevel::try_from(level).unwrap_or(HeadingLevel::H6)
+    }
+}
+
+impl Plugin for HeadingShift {
+    fn remap<'a, I>(&'a mut self, events: I) -> impl Iterator<Item = Event<'a>> + 'a
+        where I: Iterator<Item = Event<'a>> + 'a
+    {
+        let this = *self;
+        events.map(move |event| match event {
+            Event::Start(Tag::Heading { level, id, classes, attrs }) => {
+                Event::Start(Tag::Heading { level: this.shift(level), id, classes, attrs })
+            }
+            Event::End(TagEnd::Heading(level)) => Event::End(TagEnd::Heading(this.shift(level))),
+            event => event,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{AutoHeading, HeadingShift, Markdown, Renderer, TableOfContents};
+    use crate::taxonomy::Metadata;
+    use crate::value::Value;
+
+    fn render(offset: u8, markdown: &str) -> (String, String) {
+        let metadata = Metadata::new();
+        Markdown::from(markdown.to_string())
+            .plugin(HeadingShift::by(offset))
+            .plugin(AutoHeading::default())
+            .plugin(TableOfContents::new(metadata.key("toc")).render_html(metadata.key("toc_html")))
+            .plugin(Renderer::new(metadata.key("content")))
+            .run()
+            .unwrap();
+
+        let string = |key| metadata.get_raw(key).and_then(|v| v.as_str().map(String::from));
+        (string("content").unwrap(), string("toc_html").unwrap())
+    }
+
+    #[test]
+    fn shifts_levels_before_ids_and_toc() {
+        let markdown = "# A\n\n## B\n\n### C\n\n#### D\n\n##### E\n\n###### F\n\n# G\n";
+        let (html, toc) = render(1, markdown);
+        assert!(html.starts_with("<h2 id=\"a\">A</h2>\n<h3 id=\"b\">B</h3>\n"), "{html}");
+        assert!(html.contains("<h6 id=\"e\">E</h6>\n<h6 id=\"f\">F</h6>\n"), "{html}");
+        assert!(html.ends_with("<h2 id=\"g\">G</h2>\n"), "{html}");
+        assert!(!html.contains("<h1"), "{html}");
+        assert!(toc.starts_with("<ul><li><a href=\"#a\">A</a><ul><li><a href=\"#b\">B</a>"), "{toc}");
+        assert!(toc.ends_with("<li><a href=\"#g\">G</a></li></ul>"), "{toc}");
+
+        let metadata = Metadata::new();
+        Markdown::from("# A\n\n## B\n".to_string())
+            .plugin(HeadingShift::by(1))
+            .plugin(TableOfContents::new(metadata.key("toc")))
+            .run()
+            .unwrap();
+
+        let Some(Value::Array(toc)) = metadata.get_raw("toc") else { panic!("no toc") };
+        let Value::Dict(a) = &toc[0] else { panic!("not a dict") };
+        assert_eq!(a["level"], Value::from(2usize));
+    }
+
+    #[test]
+    fn clamps_at_h6_and_zero_is_identity() {
+        let (html, _) = render(10, "# A\n\n###### B\n");
+        assert_eq!(html, "<h6 id=\"a\">A</h6>\n<h6 id=\"b\">B</h6>\n");
+
+        let (html, _) = render(0, "# A\n\n## B\n");
+        assert_eq!(html, "<h1 id=\"a\">A</h1>\n<h2 id=\"b\">B</h2>\n");
+    }
+}
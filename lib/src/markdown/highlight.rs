@@ -1,7 +1,12 @@
+use std::borrow::Cow;
+
 use pulldown_cmark::{Event, Tag, CodeBlockKind, TagEnd};
+use pulldown_cmark_escape::escape_html;
+use serde::{Deserialize, Serialize};
 use syntect::html::{ClassedHTMLGenerator, ClassStyle};
 use syntect::parsing::{SyntaxSet, SyntaxReference};
 use once_cell::sync::Lazy;
+use rustc_hash::FxHashMap;
 
 use super::Plugin;
 
@@ -14,15 +19,82 @@ static DEFAULT_SYNTAX: Lazy<&'static SyntaxReference>
     = Lazy::new(|| SYNTAX_SET.find_syntax_plain_text());
 
 #[derive(Default, Clone)]
-pub struct SyntaxHighlight;
+pub struct SyntaxHighlight<'s> {
+    style: Cow<'s, CodeBlockStyle>,
+}
+
+/// The markup of the container emitted for a highlighted code block.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CodeBlockStyle {
+    /// The class of the `<div>` wrapping the code block.
+    pub container_class: String,
+    /// The class of the `<pre>` containing line numbers.
+    pub line_nums_class: String,
+    /// The class of the `<pre>` containing the highlighted code.
+    pub code_class: String,
+    /// Whether to emit an inline `display: flex` style on the container.
+    pub inline_style: bool,
+    /// Whether to emit line numbers.
+    pub line_numbers: bool,
+    /// A fence label directive, e.g. `nolines` in `rust,nolines`, which
+    /// suppresses line numbers for that block. `linenos=false` is also
+    /// always recognized.
+    pub nolines_directive: String,
+    /// Where fenced code blocks are highlighted.
+    pub mode: HighlightMode,
+    /// Canonical language names by fence label, e.g. `javascript` for `js`,
+    /// consulted before the highlighters' own lookups.
+    pub aliases: FxHashMap<String, String>,
+    /// A prefix, e.g. `hl-`, prepended to each class of the highlighted
+    /// `<span>`s, which are otherwise the atoms of the scope names, e.g.
+    /// `keyword` and `control` for `keyword.control`.
+    pub class_prefix: String,
+    /// Classes of highlighted `<span>`s to emit as others instead, e.g.
+    /// `keyword = "kw"`, to match a theme's CSS. A class mapped to `""` is
+    /// dropped. Renamed classes aren't prefixed with `class_prefix`.
+    pub classes: FxHashMap<String, String>,
+}
+
+/// Selects between server-side and client-side highlighting markup.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightMode {
+    /// Highlight every block, treating unknown languages as plain text.
+    #[default]
+    Server,
+    /// Highlight blocks in known languages. Emit the rest as plain
+    /// `<pre><code class="language-x">` for a client-side highlighter.
+    Fallback,
+    /// Emit every block as plain `<pre><code class="language-x">` for a
+    /// client-side highlighter.
+    Client,
+}
+
+/// The language and directives of a fenced code block's label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fence<'a> {
+    /// The language, i.e, everything before the first `,`, resolved through
+    /// [`CodeBlockStyle::aliases`].
+    pub lang: &'a str,
+    /// Whether the block should be rendered with line numbers.
+    pub line_numbers: bool,
+}
 
-pub struct Highlighter<I> {
+pub struct Highlighter<'s, I> {
+    style: &'s CodeBlockStyle,
     generator: Option<ClassedHTMLGenerator<'static>>,
-    lines: usize,
+    plain: Option<(String, String)>,
+    source: String,
+    line_numbers: bool,
     inner: I,
 }
 
-impl SyntaxHighlight {
+impl<'s> SyntaxHighlight<'s> {
+    pub fn with_style(style: &'s CodeBlockStyle) -> Self {
+        SyntaxHighlight { style: Cow::Borrowed(style) }
+    }
+
     #[inline]
     pub fn warm_up() {
         rayon::spawn(|| { Lazy::force(&SYNTAX_SET); });
@@ -30,63 +102,181 @@ impl SyntaxHighlight {
     }
 }
 
-impl Plugin for SyntaxHighlight {
+impl Plugin for SyntaxHighlight<'_> {
     fn remap<'a, I>(&'a mut self, events: I) -> impl Iterator<Item = Event<'a>> + 'a
         where I: Iterator<Item = Event<'a>> + 'a
     {
-        Highlighter { generator: None, lines: 0, inner: events }
+        Highlighter {
+            style: &self.style,
+            generator: None,
+            plain: None,
+            source: String::new(),
+            line_numbers: false,
+            inner: events
+        }
     }
 }
 
-fn html_generator(syntax: &SyntaxReference) -> ClassedHTMLGenerator<'_> {
-    ClassedHTMLGenerator::new_with_class_style(syntax, &*SYNTAX_SET, ClassStyle::Spaced)
+impl Default for CodeBlockStyle {
+    fn default() -> Self {
+        CodeBlockStyle {
+            container_class: "code".into(),
+            line_nums_class: "line-nums".into(),
+            code_class: "code".into(),
+            inline_style: true,
+            line_numbers: true,
+            nolines_directive: "nolines".into(),
+            mode: HighlightMode::Server,
+            aliases: FxHashMap::default(),
+            class_prefix: String::new(),
+            classes: FxHashMap::default(),
+        }
+    }
 }
 
-#[allow(unused_must_use)]
-fn code_div(lines: usize, code: String) -> String {
-    use std::fmt::Write;
+impl CodeBlockStyle {
+    /// Parses a fence label of the form `lang,directive,...`.
+    pub fn fence<'a>(&'a self, label: &'a str) -> Fence<'a> {
+        let mut parts = label.split(',').map(|part| part.trim());
+        let lang = parts.next().unwrap_or_default();
+        let lang = self.aliases.get(lang).map_or(lang, |canonical| canonical.as_str());
+        let line_numbers = self.line_numbers && !parts
+            .any(|d| d == self.nolines_directive || d.replace(' ', "") == "linenos=false");
+
+        Fence { lang, line_numbers }
+    }
+
+    /// Applies `class_prefix` and `classes` to the `<span>`s of the
+    /// highlighted `html`.
+    fn restyle(&self, html: String) -> String {
+        const OPEN: &str = "<span class=\"";
+
+        if self.class_prefix.is_empty() && self.classes.is_empty() {
+            return html;
+        }
+
+        let mut restyled = String::with_capacity(html.len());
+        let mut rest = html.as_str();
+        while let Some(i) = rest.find(OPEN) {
+            let (before, after) = rest.split_at(i + OPEN.len());
+            let Some(end) = after.find('"') else { break };
+            restyled.push_str(before);
+
+            let classes = after[..end].split_whitespace()
+                .filter_map(|class| match self.classes.get(class) {
+                    Some(renamed) if renamed.is_empty() => None,
+                    Some(renamed) => Some(Cow::Borrowed(renamed.as_str())),
+                    None => Some(Cow::Owned(format!("{}{class}", self.class_prefix))),
+                })
+                .collect::<Vec<_>>();
+
+            restyled.push_str(&classes.join(" "));
+            rest = &after[end..];
+        }
+
+        restyled.push_str(rest);
+        restyled
+    }
+
+    /// Wraps the highlighted `code` in the container. If `lines` is set, a
+    /// gutter with that many line numbers is emitted.
+    #[allow(unused_must_use)]
+    pub fn code_div(&self, lines: Option<usize>, code: &str) -> String {
+        use std::fmt::Write;
+
+        let mut div = String::new();
+        write!(&mut div, "<div class=\"{}\"", self.container_class);
+        if self.inline_style {
+            write!(&mut div, " style=\"display: flex;\"");
+        }
+
+        write!(&mut div, ">");
+        if let Some(lines) = lines {
+            write!(&mut div, "<pre class=\"{}\">", self.line_nums_class);
+            for i in 1..=lines {
+                if i < lines { writeln!(&mut div, "{}", i); }
+                else { write!(&mut div, "{}", i); }
+            }
+
+            write!(&mut div, "</pre>");
+        }
+
+        write!(&mut div, "<pre class=\"{}\">{}</pre>", self.code_class, code);
+        write!(&mut div, "</div>");
+
+        div
+    }
+}
 
-    let mut div = String::new();
-    write!(&mut div, "<div class=\"code\" style=\"display: flex;\">");
+fn html_generator(syntax: &SyntaxReference) -> ClassedHTMLGenerator<'_> {
+    ClassedHTMLGenerator::new_with_class_style(syntax, &*SYNTAX_SET, ClassStyle::Spaced)
+}
 
-    write!(&mut div, "<pre class=\"line-nums\">");
-    for i in 1..=lines {
-        if i < lines { write!(&mut div, "{}\n", i); }
-        else { write!(&mut div, "{}", i); }
+/// Renders `code` unhighlighted, classed with its language, if any, for use by
+/// a client-side highlighter.
+fn plain_code_block(lang: &str, code: &str) -> String {
+    let mut html = String::from("<pre><code");
+    if !lang.is_empty() {
+        html.push_str(" class=\"language-");
+        let _ = escape_html(&mut html, lang);
+        html.push('"');
     }
-    write!(&mut div, "</pre>");
 
-    write!(&mut div, "<pre class=\"code\">{}</pre>", code);
-    write!(&mut div, "</div>");
+    html.push('>');
+    let _ = escape_html(&mut html, code);
+    html.push_str("</code></pre>");
+    html
+}
 
-    div
+/// Returns the number of lines in `code`, including a final line that isn't
+/// terminated by a newline.
+pub(crate) fn line_count(code: &str) -> usize {
+    let newlines = memchr::memchr_iter(b'\n', code.as_bytes()).count();
+    newlines + (!code.is_empty() && !code.ends_with('\n')) as usize
 }
 
-impl<'a, I: Iterator<Item = Event<'a>>> Iterator for Highlighter<I> {
+impl<'a, I: Iterator<Item = Event<'a>>> Iterator for Highlighter<'_, I> {
     type Item = Event<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             match self.inner.next()? {
                 Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(label))) => {
-                    let lang = label.split_once(',')
-                        .map(|(prefix, _)| prefix)
-                        .unwrap_or(&*label);
-
-                    let syntax = SYNTAX_SET.find_syntax_by_token(lang)
-                        .unwrap_or_else(|| &*DEFAULT_SYNTAX);
+                    let fence = self.style.fence(&label);
+                    let syntax = SYNTAX_SET.find_syntax_by_token(fence.lang);
+                    match (self.style.mode, syntax) {
+                        (HighlightMode::Client, _) | (HighlightMode::Fallback, None) => {
+                            self.plain = Some((fence.lang.to_string(), String::new()));
+                            continue;
+                        }
+                        (_, syntax) => {
+                            let syntax = syntax.unwrap_or_else(|| *DEFAULT_SYNTAX);
+                            self.generator = Some(html_generator(syntax));
+                        }
+                    }
 
-                    self.generator = Some(html_generator(syntax));
-                    self.lines = 0;
+                    self.source.clear();
+                    self.line_numbers = fence.line_numbers;
+                }
+                Event::Text(text) if self.plain.is_some() => {
+                    self.plain.as_mut().unwrap().1.push_str(&text);
+                }
+                Event::End(TagEnd::CodeBlock) if self.plain.is_some() => {
+                    let (lang, code) = self.plain.take().unwrap();
+                    return Some(Event::Html(plain_code_block(&lang, &code).into()));
                 }
                 Event::Text(text) if self.generator.is_some() => {
+                    // A line may be split across several text events, so
+                    // lines are counted once the whole source is known.
                     let generator = self.generator.as_mut().unwrap();
-                    self.lines += memchr::memrchr_iter(b'\n', text.as_bytes()).count();
+                    self.source.push_str(&text);
                     let _ = generator.parse_html_for_line_which_includes_newline(&text);
                 }
                 Event::End(TagEnd::CodeBlock) if self.generator.is_some() => {
                     let generator = self.generator.take().unwrap();
-                    let code_html = code_div(self.lines, generator.finalize());
+                    let lines = self.line_numbers.then(|| line_count(&self.source));
+                    let code = self.style.restyle(generator.finalize());
+                    let code_html = self.style.code_div(lines, &code);
                     return Some(Event::Html(code_html.into()));
                 },
                 ev => return Some(ev),
@@ -94,3 +284,148 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for Highlighter<I> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pulldown_cmark::{Event, Tag, TagEnd, CodeBlockKind};
+
+    use super::{CodeBlockStyle, Fence, Highlighter, HighlightMode, line_count};
+
+    fn highlight(style: &CodeBlockStyle, texts: &[&str]) -> String {
+        highlight_fenced(style, "rust", texts)
+    }
+
+    fn highlight_fenced(style: &CodeBlockStyle, label: &str, texts: &[&str]) -> String {
+        let label = label.to_string();
+        let events = std::iter::once(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(label.into()))))
+            .chain(texts.iter().map(|text| Event::Text((*text).into())))
+            .chain(std::iter::once(Event::End(TagEnd::CodeBlock)));
+
+        let mut highlighter = Highlighter {
+            style,
+            generator: None,
+            plain: None,
+            source: String::new(),
+            line_numbers: false,
+            inner: events
+        };
+
+        let Some(Event::Html(html)) = highlighter.next() else { panic!("expected html") };
+        html.to_string()
+    }
+
+    fn gutter(texts: &[&str]) -> String {
+        let html = highlight(&CodeBlockStyle::default(), texts);
+        let start = html.find("<pre class=\"line-nums\">").unwrap() + "<pre class=\"line-nums\">".len();
+        let end = start + html[start..].find("</pre>").unwrap();
+        html[start..end].to_string()
+    }
+
+    #[test]
+    fn test_line_count() {
+        assert_eq!(line_count(""), 0);
+        assert_eq!(line_count("a"), 1);
+        assert_eq!(line_count("a\n"), 1);
+        assert_eq!(line_count("a\nb"), 2);
+        assert_eq!(line_count("a\nb\n"), 2);
+        assert_eq!(line_count("\n\n"), 2);
+    }
+
+    #[test]
+    fn test_gutter_trailing_newline() {
+        assert_eq!(gutter(&["let a = 1;\n"]), "1");
+        assert_eq!(gutter(&["let a = 1;\n", "let b = 2;\n"]), "1\n2");
+    }
+
+    #[test]
+    fn test_gutter_no_trailing_newline() {
+        assert_eq!(gutter(&["let a = 1;"]), "1");
+        assert_eq!(gutter(&["let a = 1;\n", "let b = 2;"]), "1\n2");
+        assert_eq!(gutter(&["let a = 1;\nlet b", " = 2;"]), "1\n2");
+    }
+
+    #[test]
+    fn test_code_block_style() {
+        let html = highlight(&CodeBlockStyle::default(), &["a"]);
+        assert!(html.starts_with("<div class=\"code\" style=\"display: flex;\"><pre class=\"line-nums\">1</pre><pre class=\"code\">"));
+
+        let style = CodeBlockStyle {
+            container_class: "highlight".into(),
+            code_class: "source".into(),
+            inline_style: false,
+            line_numbers: false,
+            ..Default::default()
+        };
+
+        let html = highlight(&style, &["a"]);
+        assert!(html.starts_with("<div class=\"highlight\"><pre class=\"source\">"), "{html}");
+        assert!(!html.contains("line-nums"), "{html}");
+    }
+
+    #[test]
+    fn test_class_prefix_and_renames() {
+        let html = highlight(&CodeBlockStyle::default(), &["fn main() {}"]);
+        assert!(html.contains("<span class=\"source rust\">"), "{html}");
+
+        let style = CodeBlockStyle {
+            class_prefix: "hl-".into(),
+            classes: [("source", ""), ("storage", "kw")].into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+
+        let html = highlight(&style, &["fn main() {}"]);
+        assert!(html.contains("<span class=\"hl-rust\">"), "{html}");
+        assert!(html.contains("<span class=\"kw hl-type hl-function hl-rust\">fn</span>"), "{html}");
+        assert!(!html.contains("\"source") && !html.contains(" source"), "{html}");
+    }
+
+    #[test]
+    fn test_fence_directives() {
+        let style = CodeBlockStyle::default();
+        assert_eq!(style.fence("rust"), Fence { lang: "rust", line_numbers: true });
+        assert_eq!(style.fence("rust,nolines"), Fence { lang: "rust", line_numbers: false });
+        assert_eq!(style.fence("rust, linenos = false"), Fence { lang: "rust", line_numbers: false });
+        assert_eq!(style.fence("rust,linenos=true"), Fence { lang: "rust", line_numbers: true });
+        assert_eq!(style.fence(""), Fence { lang: "", line_numbers: true });
+
+        let style = CodeBlockStyle { nolines_directive: "plain".into(), ..Default::default() };
+        assert_eq!(style.fence("rust,plain"), Fence { lang: "rust", line_numbers: false });
+        assert_eq!(style.fence("rust,nolines"), Fence { lang: "rust", line_numbers: true });
+
+        let html = highlight_fenced(&CodeBlockStyle::default(), "rust,nolines", &["a\n"]);
+        assert!(!html.contains("line-nums"), "{html}");
+    }
+
+    #[test]
+    fn test_fence_aliases() {
+        let aliases = [("ferris", "rust"), ("js", "javascript")].into_iter()
+            .map(|(label, lang)| (label.to_string(), lang.to_string()))
+            .collect();
+
+        let style = CodeBlockStyle { mode: HighlightMode::Fallback, aliases, ..Default::default() };
+        assert_eq!(style.fence("ferris,nolines"), Fence { lang: "rust", line_numbers: false });
+        assert_eq!(style.fence("rust"), Fence { lang: "rust", line_numbers: true });
+        assert!(highlight_fenced(&style, "ferris", &["a"]).starts_with("<div"));
+        assert_eq!(highlight_fenced(&style, "mystery", &["a"]),
+            "<pre><code class=\"language-mystery\">a</code></pre>");
+    }
+
+    #[test]
+    fn test_highlight_modes() {
+        let server = CodeBlockStyle::default();
+        assert!(highlight_fenced(&server, "rust", &["a"]).starts_with("<div"));
+        assert!(highlight_fenced(&server, "nosuchlang", &["a"]).starts_with("<div"));
+
+        let fallback = CodeBlockStyle { mode: HighlightMode::Fallback, ..Default::default() };
+        assert!(highlight_fenced(&fallback, "rust", &["a"]).starts_with("<div"));
+        assert_eq!(highlight_fenced(&fallback, "nosuchlang,nolines", &["a <", "b"]),
+            "<pre><code class=\"language-nosuchlang\">a &lt;b</code></pre>");
+
+        let client = CodeBlockStyle { mode: HighlightMode::Client, ..Default::default() };
+        assert_eq!(highlight_fenced(&client, "rust", &["a\n"]),
+            "<pre><code class=\"language-rust\">a\n</code></pre>");
+        assert_eq!(highlight_fenced(&client, "", &["a"]), "<pre><code>a</code></pre>");
+    }
+}
@@ -1,4 +1,5 @@
-use pulldown_cmark::{Event, Tag, HeadingLevel};
+use pulldown_cmark::{Event, Tag, TagEnd, HeadingLevel};
+use serde::{Deserialize, Serialize};
 
 use crate::markdown::Plugin;
 
@@ -14,9 +15,10 @@ struct Heading {
     name: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LunrDocument {
     id: String,
+    url: String,
     title: String,
     breadcrumb: String,
     body: String,
@@ -24,7 +26,7 @@ pub struct LunrDocument {
 
 #[derive(Copy, Clone, PartialEq)]
 enum State {
-    InHeading,
+    InHeading(HeadingLevel),
     InBody,
 }
 
@@ -50,48 +52,51 @@ impl<'a, I: Iterator<Item = Event<'a>>> IndexerIterator<'a, I> {
 
         string
     }
+
+    fn current_doc(&mut self) -> &mut LunrDocument {
+        if self.docs.is_empty() {
+            self.docs.push(LunrDocument::new(String::new()));
+        }
+
+        self.docs.last_mut().unwrap()
+    }
 }
 
 impl<'a, I: Iterator<Item = Event<'a>>> Iterator for IndexerIterator<'a, I> {
     type Item = Event<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // TODO: Check if we have a doc here before sending `None`.
         let event = self.inner.next()?;
         match event {
-            Event::Start(Tag::Heading(level, Some(ref id), _)) => {
+            Event::Start(Tag::Heading { level, id: Some(ref id), .. }) => {
                 while self.breadcrumb_stack.last().map_or(false, |h| h.level >= level) {
                     self.breadcrumb_stack.pop();
                 }
 
-                self.state = State::InHeading;
-                self.docs.push(LunrDocument {
-                    id: id.to_string(),
-                    title: String::new(),
-                    breadcrumb: String::new(),
-                    body: String::new()
-                })
+                self.state = State::InHeading(level);
+                self.docs.push(LunrDocument::new(id.to_string()));
             }
-            Event::Text(ref s) | Event::Code(ref s) if self.state == State::InHeading => {
-                if let Some(doc) = self.docs.last_mut() {
-                    doc.title.push_str(s);
-                }
-            },
-            Event::Text(ref s) | Event::Code(ref s) => {
-                if let Some(doc) = self.docs.last_mut() {
+            Event::Text(ref s) | Event::Code(ref s) => match self.state {
+                State::InHeading(_) => self.current_doc().title.push_str(s),
+                State::InBody => {
+                    let doc = self.current_doc();
+                    if !doc.body.is_empty() {
+                        doc.body.push(' ');
+                    }
+
                     doc.body.push_str(s);
                 }
             },
-            Event::End(Tag::Heading(level, Some(_), _)) => {
-                self.state = State::InBody;
-                if let Some(doc) = self.docs.last_mut() {
-                    self.breadcrumb_stack.push(Heading { level, name: doc.title.clone() });
-                }
+            Event::End(TagEnd::Heading(_)) => {
+                if let State::InHeading(level) = self.state {
+                    let name = self.current_doc().title.clone();
+                    self.breadcrumb_stack.push(Heading { level, name });
 
-                let breadcrumb_string = self.breadcrumb_string();
-                if let Some(doc) = self.docs.last_mut() {
-                    doc.breadcrumb = breadcrumb_string;
+                    let breadcrumb_string = self.breadcrumb_string();
+                    self.current_doc().breadcrumb = breadcrumb_string;
                 }
+
+                self.state = State::InBody;
             },
             _ => { /* skip */ }
         }
@@ -101,26 +106,64 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for IndexerIterator<'a, I> {
 }
 
 impl Plugin for &mut LunrIndexer {
-    fn remap<'a, I>(&'a mut self, events: I) -> Box<dyn Iterator<Item = Event<'a>> + 'a>
+    fn remap<'a, I>(&'a mut self, events: I) -> impl Iterator<Item = Event<'a>> + 'a
         where I: Iterator<Item = Event<'a>> + 'a
     {
-        Box::new(IndexerIterator {
+        IndexerIterator {
             breadcrumb_stack: vec![],
             docs: &mut self.docs,
             state: State::InBody,
             inner: events,
-        })
+        }
+    }
+}
+
+impl LunrIndexer {
+    /// Identifies every document as belonging to the item with the stable
+    /// id `item_id` at `url`. Documents corresponding to a heading with the
+    /// anchor `anchor` get the id `{item_id}#{anchor}` and URL `{url}#{anchor}`
+    /// while those preceding any heading get `item_id` and `url`. This makes
+    /// ids unique across items with unique ids.
+    pub fn identify(&mut self, item_id: &str, url: &str) {
+        for doc in &mut self.docs {
+            (doc.id, doc.url) = match doc.id.is_empty() {
+                true => (item_id.to_string(), url.to_string()),
+                false => (format!("{item_id}#{}", doc.id), format!("{url}#{}", doc.id)),
+            };
+        }
+    }
+
+    /// Builds a search index from `docs`.
+    pub fn index<'a, I>(docs: I) -> LunrIndex
+        where I: IntoIterator<Item = &'a LunrDocument>
+    {
+        let mut index = LunrIndex::new(&LunrDocument::FIELDS);
+        for doc in docs {
+            index.add_doc(doc.id(), doc.fields());
+        }
+
+        index
     }
 }
 
 impl LunrDocument {
-    pub const FIELDS: [&str; 3] = ["title", "breadcrumb", "body"];
+    pub const FIELDS: [&'static str; 4] = ["url", "title", "breadcrumb", "body"];
+
+    fn new(id: String) -> Self {
+        LunrDocument {
+            id,
+            url: String::new(),
+            title: String::new(),
+            breadcrumb: String::new(),
+            body: String::new()
+        }
+    }
 
     pub fn id(&self) -> &str {
         &self.id
     }
 
-    pub fn fields(&self) -> [&str; 3] {
-        [&self.title, &self.breadcrumb, &self.body]
+    pub fn fields(&self) -> [&str; 4] {
+        [&self.url, &self.title, &self.breadcrumb, &self.body]
     }
 }
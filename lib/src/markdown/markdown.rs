@@ -3,7 +3,6 @@ use std::sync::Arc;
 
 use pulldown_cmark::{Parser, Options};
 
-use crate::error;
 use crate::util::hlist::{HList, for_each_mut};
 use crate::markdown::Plugin;
 use crate::util::hlist::*;
@@ -0,0 +1,306 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// Wraps TeX math in markup for a client-side renderer, like KaTeX, to pick
+/// up: inline math in `<span class="math inline">` and display math in
+/// `<div class="math display">`. The TeX, without its delimiters, is kept
+/// verbatim but escaped, so markdown doesn't interpret it.
+///
+/// Math in fenced code blocks and code spans is left alone, as are escaped
+/// delimiters, e.g. `\$`. Inline math must not start or end with whitespace
+/// nor be followed by a digit, so `$5 and $10` isn't math. Indented code
+/// blocks aren't recognized, so math in them is wrapped.
+#[derive(Default, Clone)]
+pub struct Math<'c> {
+    config: Cow<'c, MathConfig>,
+}
+
+/// Whether math is wrapped and how it's delimited.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MathConfig {
+    /// Whether to wrap math at all. Off by default, as `$` is common in
+    /// prose.
+    pub enabled: bool,
+    /// The opening and closing delimiters of inline math. Defaults to `$`
+    /// and `$`. Another common choice is `\(` and `\)`.
+    pub inline: (String, String),
+    /// The opening and closing delimiters of display math. Defaults to `$$`
+    /// and `$$`. Another common choice is `\[` and `\]`.
+    pub display: (String, String),
+}
+
+impl Default for MathConfig {
+    fn default() -> Self {
+        MathConfig {
+            enabled: false,
+            inline: ("$".into(), "$".into()),
+            display: ("$$".into(), "$$".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Inline,
+    Display,
+}
+
+impl<'c> Math<'c> {
+    pub fn with_config(config: &'c MathConfig) -> Self {
+        Math { config: Cow::Borrowed(config) }
+    }
+
+    /// The delimiters of each kind, longest opening delimiter first so that,
+    /// e.g., `$$` is tried before `$`.
+    fn delimiters(&self) -> Vec<(Kind, &str, &str)> {
+        let (inline, display) = (&self.config.inline, &self.config.display);
+        let mut delimiters = vec![
+            (Kind::Display, &*display.0, &*display.1),
+            (Kind::Inline, &*inline.0, &*inline.1),
+        ];
+
+        delimiters.retain(|(_, open, close)| !open.is_empty() && !close.is_empty());
+        delimiters.sort_by_key(|(_, open, _)| std::cmp::Reverse(open.len()));
+        delimiters
+    }
+
+    /// If math of `kind` opens at the start of `input`, returns its TeX and
+    /// the length of the math with its delimiters.
+    fn parse<'a>(&self, kind: Kind, open: &str, close: &str, input: &'a str) -> Option<(&'a str, usize)> {
+        let bytes = input.as_bytes();
+        let start = open.len();
+        let mut i = start;
+        while i < bytes.len() {
+            if bytes[i..].starts_with(close.as_bytes()) {
+                break;
+            }
+
+            match bytes[i] {
+                b'\\' if !close.starts_with('\\') => i += 2,
+                b'\n' if kind == Kind::Inline && is_blank_line(&bytes[i + 1..]) => return None,
+                _ => i += 1,
+            }
+        }
+
+        let tex = input.get(start..i).filter(|_| i < bytes.len())?;
+        let end = i + close.len();
+        if tex.trim().is_empty() {
+            return None;
+        }
+
+        if kind == Kind::Inline {
+            let padded = tex.starts_with(char::is_whitespace) || tex.ends_with(char::is_whitespace);
+            if padded || bytes.get(end).is_some_and(u8::is_ascii_digit) {
+                return None;
+            }
+        }
+
+        Some((tex, end))
+    }
+}
+
+impl crate::markdown::Plugin for Math<'_> {
+    fn preprocess<'a>(&self, input: &'a str) -> Result<Cow<'a, str>> {
+        if !self.config.enabled {
+            return Ok(Cow::Borrowed(input));
+        }
+
+        let delimiters = self.delimiters();
+        let bytes = input.as_bytes();
+        let (mut output, mut copied, mut i) = (String::new(), 0, 0);
+        'scan: while i < bytes.len() {
+            if is_line_start(i, input) {
+                if let Some(end) = fenced_code_end(&input[i..]) {
+                    i += end;
+                    continue;
+                }
+            }
+
+            for &(kind, open, close) in &delimiters {
+                if !bytes[i..].starts_with(open.as_bytes()) {
+                    continue;
+                }
+
+                if let Some((tex, len)) = self.parse(kind, open, close, &input[i..]) {
+                    output.push_str(&input[copied..i]);
+                    match kind {
+                        Kind::Inline => {
+                            output.push_str(r#"<span class="math inline">"#);
+                            escape(&mut output, tex);
+                            output.push_str("</span>");
+                        }
+                        Kind::Display => {
+                            output.push_str(r#"<div class="math display">"#);
+                            let lines = tex.lines().filter(|line| !line.trim().is_empty());
+                            escape(&mut output, &lines.collect::<Vec<_>>().join("\n"));
+                            output.push_str("</div>");
+
+                            // On a line of its own, the `<div>` starts an HTML
+                            // block, which only a blank line ends.
+                            if is_line_start(i, input) {
+                                output.push('\n');
+                            }
+                        }
+                    }
+
+                    i += len;
+                    copied = i;
+                    continue 'scan;
+                }
+            }
+
+            match bytes[i] {
+                b'\\' if bytes.get(i + 1).is_some_and(u8::is_ascii_punctuation) => i += 2,
+                b'`' => {
+                    let ticks = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+                    i += ticks + code_span_len(&bytes[i + ticks..], ticks).unwrap_or(0);
+                }
+                _ => i += 1,
+            }
+        }
+
+        if copied == 0 {
+            return Ok(Cow::Borrowed(input));
+        }
+
+        output.push_str(&input[copied..]);
+        Ok(Cow::Owned(output))
+    }
+}
+
+fn is_line_start(i: usize, string: &str) -> bool {
+    i == 0 || string.as_bytes().get(i - 1).is_some_and(|s| *s == b'\n')
+}
+
+fn is_blank_line(bytes: &[u8]) -> bool {
+    let line = bytes.split(|&b| b == b'\n').next().unwrap_or_default();
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+/// If a code fence, possibly indented or quoted, opens the line at the start
+/// of `input`, returns the length of the code block through its closing
+/// fence, or through the end of `input` if it's never closed.
+fn fenced_code_end(input: &str) -> Option<usize> {
+    fn fence(line: &str) -> Option<(char, usize, &str)> {
+        let line = line.trim_start_matches(|c: char| c.is_whitespace() || c == '>');
+        let c = line.chars().next().filter(|&c| c == '`' || c == '~')?;
+        let len = line.chars().take_while(|&d| d == c).count();
+        (len >= 3).then_some((c, len, &line[len..]))
+    }
+
+    let mut lines = input.split_inclusive('\n');
+    let (c, len, _) = fence(lines.next()?)?;
+    let mut end = input.split_inclusive('\n').next()?.len();
+    for line in lines {
+        end += line.len();
+        if let Some((d, n, rest)) = fence(line) {
+            if d == c && n >= len && rest.trim().is_empty() {
+                break;
+            }
+        }
+    }
+
+    Some(end)
+}
+
+/// The length of the code span's contents and closing backticks following
+/// its opening `ticks` backticks at the start of `bytes`, or `None` if it's
+/// never closed.
+fn code_span_len(bytes: &[u8], ticks: usize) -> Option<usize> {
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+        match run {
+            0 => i += 1,
+            n if n == ticks => return Some(i + n),
+            n => i += n,
+        }
+    }
+
+    None
+}
+
+/// Escapes `tex` for HTML and markdown alike: the characters markdown would
+/// otherwise interpret are written as character references.
+fn escape(output: &mut String, tex: &str) {
+    for c in tex.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\\' | '*' | '_' | '`' | '[' | ']' | '~' | '|' | '$' => {
+                output.push_str("&#");
+                output.push_str(&(c as u32).to_string());
+                output.push(';');
+            }
+            c => output.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{Markdown, Math, MathConfig, Plugin, Renderer};
+    use crate::taxonomy::Metadata;
+
+    fn config() -> MathConfig {
+        MathConfig { enabled: true, ..MathConfig::default() }
+    }
+
+    fn render(config: &MathConfig, markdown: &str) -> String {
+        let metadata = Metadata::new();
+        Markdown::from(markdown.to_string())
+            .plugin(Math::with_config(config))
+            .plugin(Renderer::new(metadata.key("content")))
+            .run()
+            .unwrap();
+
+        let content = metadata.get_raw("content").unwrap();
+        content.as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn wraps_inline_and_display_math() {
+        let html = render(&config(), "Let $a_1 * b_2 < c$ hold.\n\n$$\n\\sum_{i} x_i^2\n$$\n\nAfter.\n");
+        assert_eq!(html, concat!(
+            "<p>Let <span class=\"math inline\">a_1 * b_2 &lt; c</span> hold.</p>\n",
+            "<div class=\"math display\">&#92;sum&#95;{i} x&#95;i^2</div>\n",
+            "<p>After.</p>\n",
+        ));
+    }
+
+    #[test]
+    fn leaves_code_escapes_and_prices_alone() {
+        let config = config();
+        let html = render(&config, "`$x$` and ``a `$y$` b``\n\n```\n$z$\n```\n\nCosts \\$5, $5 and $10, or $ x $.\n");
+        assert!(!html.contains("class=\"math"), "{html}");
+        assert!(html.contains("<code>$x$</code>") && html.contains("<code>a `$y$` b</code>"), "{html}");
+        assert!(html.contains("<pre><code>$z$\n</code></pre>"), "{html}");
+        assert!(html.contains("Costs $5, $5 and $10, or $ x $."), "{html}");
+
+        let html = render(&config, "Inline $a$ stops at\n\nblank lines $b.\n");
+        assert!(html.contains("<span class=\"math inline\">a</span>") && html.contains("$b."), "{html}");
+    }
+
+    #[test]
+    fn configurable_delimiters_and_disabled() {
+        let config = MathConfig {
+            enabled: true,
+            inline: ("\\(".into(), "\\)".into()),
+            display: ("\\[".into(), "\\]".into()),
+        };
+
+        let html = render(&config, "Both \\(x^2\\) and $y$.\n\n\\[\n\\frac{1}{2}\n\\]\n");
+        assert!(html.contains("Both <span class=\"math inline\">x^2</span> and $y$."), "{html}");
+        assert!(html.contains("<div class=\"math display\">&#92;frac{1}{2}</div>"), "{html}");
+
+        let input = "Plain $x$.";
+        let output = Math::with_config(&MathConfig::default()).preprocess(input).unwrap();
+        assert_eq!(output, input);
+    }
+}
@@ -2,30 +2,38 @@ mod plugin;
 mod highlight;
 mod code_filter;
 mod admonition;
+mod diagram;
 mod alias;
 mod toc;
 mod frontmatter;
 mod auto_heading;
+mod heading_shift;
+mod footnotes;
+mod math;
 mod render;
 mod templatize;
 mod markdown;
 mod parts;
 mod snippet;
-// mod indexer;
+mod indexer;
 // mod ts_highlight;
 
 pub use plugin::Plugin;
 pub use markdown::Markdown;
 pub use frontmatter::FrontMatter;
 pub use auto_heading::{AutoHeading, HeadingAnchor};
+pub use heading_shift::HeadingShift;
+pub use footnotes::Footnotes;
+pub use math::{Math, MathConfig};
 pub use toc::TableOfContents;
-pub use admonition::Admonition;
+pub use admonition::{Admonition, AdmonitionConfig, AdmonitionKind};
+pub use diagram::{DiagramConfig, DiagramPassthrough};
 pub use code_filter::CodeTrim;
 pub use alias::Alias;
-pub use highlight::SyntaxHighlight;
+pub use highlight::{SyntaxHighlight, CodeBlockStyle, Fence, HighlightMode};
 pub use render::Renderer;
 pub use parts::Parts;
 pub use snippet::Snippet;
 pub use templatize::Templatize;
 // pub use ts_highlight::SyntaxHighlight as TsHighligher;
-// pub use indexer::{LunrIndexer, LunrIndex, LunrDocument};
+pub use indexer::{LunrIndexer, LunrIndex, LunrDocument};
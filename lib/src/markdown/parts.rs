@@ -1,4 +1,6 @@
-use pulldown_cmark::{html, Event, Tag, CowStr};
+use std::collections::VecDeque;
+
+use pulldown_cmark::{html, Event, Tag, TagEnd};
 
 use crate::value::Sink;
 use crate::error::Result;
@@ -6,6 +8,13 @@ use crate::markdown::Plugin;
 
 const SEPERATOR: &str = "===";
 
+/// Splits content into parts at top-level paragraphs starting with `===`,
+/// writing the rendered HTML of each part, in order, to `output`. The list of
+/// parts is empty if there is no separator.
+///
+/// The separators are removed but every other event is passed through, so a
+/// downstream [`Renderer`](crate::markdown::Renderer) renders the combined
+/// content, which is exactly the concatenation of the parts.
 pub struct Parts<O> {
     output: O,
     sections: Vec<String>,
@@ -17,78 +26,95 @@ impl<O> Parts<O> {
     }
 }
 
-struct SectionIterator<'a, I: Iterator<Item = Event<'a>>> {
-    stack: Vec<Event<'a>>,
-    found: bool,
-    inner: I,
+/// Whether `events`, following a `Start(Paragraph)`, complete a separator.
+fn is_separator(events: &VecDeque<Event<'_>>) -> bool {
+    matches!(
+        (events.front(), events.get(1)),
+        (Some(Event::Text(text)), Some(Event::End(TagEnd::Paragraph))) if text.starts_with(SEPERATOR)
+    )
 }
 
-impl<'a, I: Iterator<Item = Event<'a>>> Iterator for SectionIterator<'a, I> {
-    type Item = Event<'a>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(event) = self.stack.pop() {
-            return Some(event);
-        }
-
-        match self.inner.next()? {
-            start@Event::Start(Tag::Paragraph) => match self.inner.next()? {
-                Event::Text(text) if text.starts_with(SEPERATOR) => {
-                    self.found = true;
-                    let _end = self.inner.next()?;
-                    self.stack.push(self.inner.next()?);
-                    return None;
-                }
-                inner => {
-                    self.stack.push(inner);
-                    Some(start)
-                }
-            }
-            event => Some(event),
-        }
-    }
+fn render(events: &[Event<'_>]) -> String {
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, events.iter().cloned());
+    html_output
 }
 
 impl<O: Sink> Plugin for Parts<O> {
-    // TODO: This is a totally lie and breaks any plugins downchain. Fix it. The
-    // way this works is that the iterator returns `None` (stops) for every
-    // secion. This causes the HTML renderer to emit the string so far. We then
-    // reuse the same iterator in the renderer again until it signals it cannot
-    // be reused. We finally return one string containing all of the HTML.
     fn remap<'a, I>(&'a mut self, events: I) -> impl Iterator<Item = Event<'a>> + 'a
         where I: Iterator<Item = Event<'a>> + 'a
     {
-        let mut sections = SectionIterator {
-            stack: vec![],
-            found: false,
-            inner: events,
-        };
-
-        loop {
-            // Render until we get a `None` and record the section.
-            let mut html_output = String::new();
-            html::push_html(&mut html_output, &mut sections);
-            self.sections.push(html_output);
-
-            // There's no where to continue, so bail.
-            if sections.stack.is_empty() {
-                break;
+        let mut events: VecDeque<_> = events.collect();
+        let mut kept = Vec::with_capacity(events.len());
+        let (mut depth, mut section_start, mut found) = (0usize, 0, false);
+        while let Some(event) = events.pop_front() {
+            match event {
+                Event::Start(Tag::Paragraph) if depth == 0 && is_separator(&events) => {
+                    events.drain(..2);
+                    self.sections.push(render(&kept[section_start..]));
+                    section_start = kept.len();
+                    found = true;
+                    continue;
+                }
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth = depth.saturating_sub(1),
+                _ => {}
             }
+
+            kept.push(event);
         }
 
-        let have_parts = sections.found;
-        let complete_html = match self.sections.len() {
-            0 if !have_parts => CowStr::from(""),
-            1 if !have_parts => self.sections.pop().unwrap().into(),
-            1 if have_parts => self.sections.last().map(|s| s.as_str()).unwrap().into(),
-            _ => self.sections.join("").into(),
-        };
+        if found {
+            self.sections.push(render(&kept[section_start..]));
+        }
 
-        Some(Event::Html(complete_html)).into_iter()
+        kept.into_iter()
     }
 
     fn finalize(&mut self) -> Result<()> {
-        let sections = std::mem::replace(&mut self.sections, vec![]);
+        let sections = std::mem::take(&mut self.sections);
         self.output.write(sections)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{Markdown, Parts, Renderer};
+    use crate::taxonomy::Metadata;
+    use crate::value::Value;
+
+    fn render(input: &str) -> (Vec<String>, String) {
+        let metadata = Metadata::new();
+        Markdown::from(input.to_string())
+            .plugin(Parts::new(metadata.key("parts")))
+            .plugin(Renderer::new(metadata.key("content")))
+            .run()
+            .unwrap();
+
+        let parts = match metadata.get_raw("parts").unwrap() {
+            Value::Array(parts) => parts.iter().map(|p| p.as_str().unwrap().to_string()).collect(),
+            value => panic!("expected an array, found {value:?}"),
+        };
+
+        let content = metadata.get_raw("content").unwrap();
+        (parts, content.as_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn parts_concatenate_to_content() {
+        let (parts, content) = render("# One\n\nFirst.\n\n===\n\nSecond.\n\n===\n\n- Third\n");
+        assert_eq!(parts, [
+            "<h1>One</h1>\n<p>First.</p>\n",
+            "<p>Second.</p>\n",
+            "<ul>\n<li>Third</li>\n</ul>\n",
+        ]);
+        assert_eq!(content, parts.concat());
+    }
+
+    #[test]
+    fn no_separator_no_parts() {
+        let (parts, content) = render("Just one.\n\n> ===\n");
+        assert!(parts.is_empty());
+        assert!(content.contains("<p>===</p>"), "{content}");
+    }
+}
@@ -1,25 +1,91 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
 use std::fmt::Write;
 
-use pulldown_cmark::{Event, Tag, TagEnd};
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
 
 use crate::error::Result;
 use crate::markdown::Plugin;
 use crate::value::Sink;
 
-pub struct Snippet<O> {
+pub struct Snippet<O, T = ()> {
     output: O,
     snippet: String,
     length: usize,
+    /// When set, the snippet rendered from a prefix of the source and whether
+    /// the prefix is shorter than the source.
+    source: Option<OnceCell<(String, bool)>>,
+    truncated: bool,
+    truncated_output: T,
 }
 
 impl<O> Snippet<O> {
+    /// Captures a snippet of at least `length` characters of text by emitting
+    /// HTML for the markdown events in the leading paragraphs.
     pub fn new(output: O, length: usize) -> Self {
-        Self { output, snippet: String::new(), length }
+        Self {
+            output,
+            snippet: String::new(),
+            length,
+            source: None,
+            truncated: false,
+            truncated_output: (),
+        }
+    }
+
+    /// Renders a snippet from the shortest prefix of the markdown source that
+    /// ends at a top-level block boundary and contains at least `length`
+    /// characters of text. Unlike with [`Snippet::new()`], the snippet is
+    /// always valid HTML and retains all of the prefix's markup.
+    pub fn source(output: O, length: usize) -> Self {
+        Self { source: Some(OnceCell::new()), ..Self::new(output, length) }
+    }
+}
+
+impl<O, T> Snippet<O, T> {
+    /// Writes `true` to `output` if the snippet omits some of the content's
+    /// text and `false` otherwise.
+    pub fn report_truncation<S: Sink>(self, output: S) -> Snippet<O, S> {
+        Snippet {
+            output: self.output,
+            snippet: self.snippet,
+            length: self.length,
+            source: self.source,
+            truncated: self.truncated,
+            truncated_output: output,
+        }
+    }
+}
+
+/// Returns the shortest prefix of `input` that ends at a top-level block
+/// boundary and contains at least `length` characters of text, or all of
+/// `input` if there is no such prefix.
+fn source_prefix(input: &str, length: usize, options: Options) -> &str {
+    if length == 0 {
+        return "";
+    }
+
+    let mut depth = 0usize;
+    let mut text_len = 0;
+    for (event, range) in Parser::new_ext(input, options).into_offset_iter() {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            Event::Text(text) | Event::Code(text) => text_len += text.len(),
+            _ => {}
+        }
+
+        if depth == 0 && text_len >= length {
+            return &input[..range.end];
+        }
     }
+
+    input
 }
 
 struct SnippetIterator<'a, I: Iterator<Item = Event<'a>>> {
     snippet: &'a mut String,
+    truncated: &'a mut bool,
     inner: I,
     capture: Vec<bool>,
     snip_text_len: usize,
@@ -64,6 +130,7 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for SnippetIterator<'a, I> {
     fn next(&mut self) -> Option<Self::Item> {
         let event = self.inner.next()?;
         if self.done {
+            *self.truncated |= matches!(event, Event::Text(_) | Event::Code(_));
             return Some(event);
         }
 
@@ -100,24 +167,89 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for SnippetIterator<'a, I> {
     }
 }
 
-// IDEA: What if we just take the first k character of the text and render that as markdown?
+impl<O: Sink, T: Sink> Plugin for Snippet<O, T> {
+    fn preprocess<'a>(&self, input: &'a str) -> Result<Cow<'a, str>> {
+        if let Some(ref source) = self.source {
+            let options = Options::all().difference(Options::ENABLE_SMART_PUNCTUATION);
+            let prefix = source_prefix(input, self.length, options);
+            let mut snippet = String::new();
+            html::push_html(&mut snippet, Parser::new_ext(prefix, options));
+            let truncated = !input[prefix.len()..].trim().is_empty();
+            let _ = source.set((snippet, truncated));
+        }
+
+        Ok(Cow::Borrowed(input))
+    }
 
-impl<O: Sink> Plugin for Snippet<O> {
     fn remap<'a, I>(&'a mut self, events: I) -> impl Iterator<Item = Event<'a>> + 'a
         where I: Iterator<Item = Event<'a>> + 'a
     {
         SnippetIterator {
             snippet: &mut self.snippet,
+            truncated: &mut self.truncated,
             snip_text_len: 0,
             inner: events,
             capture: vec![],
             min_length: self.length,
-            done: self.length == 0,
+            done: self.length == 0 || self.source.is_some(),
         }
     }
 
     fn finalize(&mut self) -> Result<()> {
-        let snippet = std::mem::replace(&mut self.snippet, String::new());
-        self.output.write(snippet)
+        let (snippet, truncated) = match self.source.as_mut().and_then(|source| source.take()) {
+            Some(source) => source,
+            None => (std::mem::take(&mut self.snippet), self.truncated),
+        };
+
+        self.output.write(snippet)?;
+        self.truncated_output.write(truncated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{Markdown, Snippet};
+    use crate::taxonomy::{Key, Metadata};
+    use crate::value::Value;
+
+    fn snippet<F>(input: &str, snippet: F) -> (String, bool)
+        where F: FnOnce(&Metadata) -> Snippet<Key<'_, 'static, Value>>
+    {
+        let metadata = Metadata::new();
+        Markdown::from(input.to_string())
+            .plugin(snippet(&metadata).report_truncation(metadata.key("truncated")))
+            .run()
+            .unwrap();
+
+        let snippet = metadata.get_raw("snippet").unwrap();
+        let truncated = metadata.get_raw("truncated").unwrap();
+        (snippet.as_str().unwrap().to_string(), truncated == true.into())
+    }
+
+    fn source_snippet(input: &str, length: usize) -> String {
+        snippet(input, |m| Snippet::source(m.key("snippet"), length)).0
+    }
+
+    #[test]
+    fn source_snippet_ends_at_block() {
+        let input = "# Title\n\nSome *emphasized* text.\n\n- a list\n- of items\n\nMore.";
+        assert_eq!(source_snippet(input, 5), "<h1>Title</h1>\n");
+        let expected = "<h1>Title</h1>\n<p>Some <em>emphasized</em> text.</p>\n";
+        assert_eq!(source_snippet(input, 10), expected);
+        assert_eq!(source_snippet(input, 0), "");
+        assert_eq!(source_snippet(input, 1000), source_snippet(input, usize::MAX));
+        assert!(source_snippet(input, 30).ends_with("</ul>\n"));
+    }
+
+    #[test]
+    fn snippet_reports_truncation() {
+        let input = "First paragraph.\n\nSecond paragraph.";
+        let events = |length| snippet(input, |m| Snippet::new(m.key("snippet"), length));
+        let source = |length| snippet(input, |m| Snippet::source(m.key("snippet"), length));
+
+        assert_eq!(events(5), ("<p>First paragraph.</p>".into(), true));
+        assert!(!events(1000).1);
+        assert_eq!(source(5), ("<p>First paragraph.</p>\n".into(), true));
+        assert!(!source(1000).1);
     }
 }
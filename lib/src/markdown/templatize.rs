@@ -3,7 +3,8 @@ use std::path::Path;
 use std::sync::Arc;
 
 use crate::markdown::Plugin;
-use crate::taxonomy::Metadata;
+use crate::taxonomy::{Metadata, MetaKey};
+use crate::value::Value;
 use crate::templating::Engine;
 use crate::error::{Result, Chainable};
 
@@ -11,11 +12,19 @@ pub struct Templatize<'m> {
     path: &'m Path,
     engine: Arc<dyn Engine>,
     metadata: &'m Metadata,
+    skip_key: Option<&'static str>,
 }
 
 impl<'m> Templatize<'m>{
     pub fn with(path: &'m Path, engine: Arc<dyn Engine>, metadata: &'m Metadata) -> Self {
-        Self { path, engine, metadata }
+        Self { path, engine, metadata, skip_key: None }
+    }
+
+    /// Skips templatization, emitting the input verbatim, when the document's
+    /// metadata sets `K` to `true`.
+    pub fn skip_if<K: MetaKey<Value = bool>>(mut self, _: K) -> Self {
+        self.skip_key = Some(K::KEY);
+        self
     }
 }
 
@@ -25,8 +34,14 @@ impl Plugin for Templatize<'_> {
             return Ok(Cow::Borrowed(input));
         }
 
+        if let Some(key) = self.skip_key {
+            if let Some(Value::Bool(true)) = self.metadata.get_raw(key) {
+                return Ok(Cow::Borrowed(input));
+            }
+        }
+
         self.engine.render_str(self.path.to_str(), input, self.metadata.clone())
-            .chain(error!("markdown templatization failed"))
+            .chain(error!("markdown templatization failed", "path" => self.path.display()))
             .map(Cow::Owned)
     }
 }
@@ -1,4 +1,7 @@
-use pulldown_cmark::{Event, Tag};
+use std::ops::RangeInclusive;
+
+use pulldown_cmark::{Event, Tag, TagEnd};
+use pulldown_cmark_escape::{escape_href, escape_html};
 use serde::Serialize;
 
 use crate::error::Result;
@@ -14,16 +17,49 @@ pub struct Entry {
     pub children: Vec<Entry>,
 }
 
+/// Collects the document's headings, nested by level, and writes them to
+/// `output` as an array of `{ title, level, id, children }` dictionaries. The
+/// `id` is the heading's, so running after [`AutoHeading`] links each entry to
+/// the generated anchor.
+///
+/// Headings at every level are collected unless limited with
+/// [`TableOfContents::with_levels()`].
+///
+/// [`AutoHeading`]: crate::markdown::AutoHeading
 #[derive(Debug, Clone)]
-pub struct TableOfContents<O> {
+pub struct TableOfContents<O, H = ()> {
     pub entries: Vec<Entry>,
     entry: Option<Entry>,
+    levels: RangeInclusive<usize>,
     output: O,
+    html_output: H,
 }
 
 impl<O: Sink> TableOfContents<O> {
     pub fn new(output: O) -> Self {
-        Self { entries: vec![], entry: None, output }
+        Self { entries: vec![], entry: None, levels: 1..=6, output, html_output: () }
+    }
+
+    /// Additionally writes the table of contents as HTML, nested `<ul>`s of
+    /// links to each heading's `id`, to `output`.
+    pub fn render_html<S: Sink>(self, output: S) -> TableOfContents<O, S> {
+        TableOfContents {
+            entries: self.entries,
+            entry: self.entry,
+            levels: self.levels,
+            output: self.output,
+            html_output: output,
+        }
+    }
+}
+
+impl<O, H> TableOfContents<O, H> {
+    /// Only collects headings from level `min` through `max`, inclusive. An
+    /// entry nests under the closest preceding entry of a lower level, so an
+    /// `h4` whose `h3` was filtered out is a child of the preceding `h2`.
+    pub fn with_levels(mut self, min: usize, max: usize) -> Self {
+        self.levels = min..=max;
+        self
     }
 
     pub fn reset(&mut self) {
@@ -51,13 +87,51 @@ impl<O: Sink> TableOfContents<O> {
     }
 }
 
-impl<O: Sink> Plugin for TableOfContents<O> {
+/// Renders `entries` as nested lists of links, one `<li>` per entry.
+pub fn to_html(entries: &[Entry]) -> String {
+    fn render(html: &mut String, entries: &[Entry]) {
+        html.push_str("<ul>");
+        for entry in entries {
+            html.push_str("<li>");
+            match &entry.id {
+                Some(id) => {
+                    html.push_str("<a href=\"#");
+                    let _ = escape_href(&mut *html, id);
+                    html.push_str("\">");
+                    let _ = escape_html(&mut *html, &entry.title);
+                    html.push_str("</a>");
+                }
+                None => { let _ = escape_html(&mut *html, &entry.title); }
+            }
+
+            if !entry.children.is_empty() {
+                render(html, &entry.children);
+            }
+
+            html.push_str("</li>");
+        }
+
+        html.push_str("</ul>");
+    }
+
+    let mut html = String::new();
+    if !entries.is_empty() {
+        render(&mut html, entries);
+    }
+
+    html
+}
+
+impl<O: Sink, H: Sink> Plugin for TableOfContents<O, H> {
     fn remap<'a, I>(&'a mut self, events: I) -> impl Iterator<Item = Event<'a>> + 'a
         where I: Iterator<Item = Event<'a>> + 'a
     {
         self.reset();
 
         events.inspect(|ev| match ev {
+            Event::Start(Tag::Heading { level, .. }) if !self.levels.contains(&(*level as usize)) => {
+                self.entry = None;
+            },
             Event::Start(Tag::Heading { level, id, .. }) => {
                 self.entry = Some(Entry {
                     title: String::new(),
@@ -66,9 +140,13 @@ impl<O: Sink> Plugin for TableOfContents<O> {
                     id: id.as_ref().map(|c| c.to_string()),
                 });
             },
-            Event::Text(text) | Event::Code(text) if self.entry.is_some() => {
-                let mut entry = self.entry.take().unwrap();
-                entry.title.push_str(text);
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(entry) = &mut self.entry {
+                    entry.title.push_str(text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) if self.entry.is_some() => {
+                let entry = self.entry.take().unwrap();
                 if let Some(parent) = self.find_parent(&entry) {
                     parent.children.push(entry);
                 } else {
@@ -84,7 +162,8 @@ impl<O: Sink> Plugin for TableOfContents<O> {
             .map(Value::from)
             .collect::<Value>();
 
-        self.output.write_value(entries)
+        self.output.write_value(entries)?;
+        self.html_output.write(to_html(&self.entries))
     }
 }
 
@@ -102,3 +181,94 @@ impl From<&Entry> for Value {
         Value::from(dict)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{AutoHeading, Markdown, Renderer, TableOfContents};
+    use crate::taxonomy::Metadata;
+    use crate::value::Value;
+
+    fn field<'v>(value: &'v Value, key: &str) -> &'v Value {
+        match value {
+            Value::Dict(dict) => &dict[key],
+            value => panic!("expected a dict, found {value:?}"),
+        }
+    }
+
+    fn items(value: &Value) -> &[Value] {
+        match value {
+            Value::Array(array) => array,
+            value => panic!("expected an array, found {value:?}"),
+        }
+    }
+
+    #[test]
+    fn entries_link_to_heading_anchors() {
+        let metadata = Metadata::new();
+        Markdown::from("# Intro\n\n## Use `foo` *now*\n\n## Use `foo` *now*\n\n# End\n".to_string())
+            .plugin(AutoHeading::default())
+            .plugin(TableOfContents::new(metadata.key("toc")).render_html(metadata.key("toc_html")))
+            .plugin(Renderer::new(metadata.key("content")))
+            .run()
+            .unwrap();
+
+        let toc = metadata.get_raw("toc").unwrap();
+        let content = metadata.get_raw("content").unwrap();
+        let content = content.as_str().unwrap();
+        assert_eq!(items(&toc).len(), 2);
+
+        let children = items(field(&items(&toc)[0], "children"));
+        assert_eq!(children.len(), 2);
+        for child in children {
+            assert_eq!(field(child, "title").as_str(), Some("Use foo now"));
+            assert_eq!(field(child, "level"), &Value::from(2usize));
+            let id = field(child, "id").as_str().unwrap();
+            assert!(content.contains(&format!("id=\"{id}\"")), "{id}: {content}");
+        }
+
+        let first = field(&children[0], "id").as_str().unwrap();
+        let html = metadata.get_raw("toc_html").unwrap();
+        let html = html.as_str().unwrap();
+        assert!(html.starts_with("<ul><li><a href=\"#intro\">Intro</a><ul><li>"), "{html}");
+        assert!(html.contains(&format!("<a href=\"#{first}\">Use foo now</a>")), "{html}");
+        assert!(html.ends_with("<li><a href=\"#end\">End</a></li></ul>"), "{html}");
+    }
+
+    #[test]
+    fn filters_levels_and_nests_across_gaps() {
+        let metadata = Metadata::new();
+        let markdown = "# Title\n\n## A\n\n#### A.1\n\n###### Deep\n\n### A.2\n\n## B\n\n##### B.1\n";
+        Markdown::from(markdown.to_string())
+            .plugin(TableOfContents::new(metadata.key("toc"))
+                .with_levels(2, 4)
+                .render_html(metadata.key("toc_html")))
+            .run()
+            .unwrap();
+
+        let toc = metadata.get_raw("toc").unwrap();
+        let titles = |entries: &[Value]| entries.iter()
+            .map(|e| field(e, "title").as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(titles(items(&toc)), ["A", "B"]);
+        let a = items(field(&items(&toc)[0], "children"));
+        assert_eq!(titles(a), ["A.1", "A.2"]);
+        assert!(a.iter().all(|e| items(field(e, "children")).is_empty()));
+        assert!(items(field(&items(&toc)[1], "children")).is_empty());
+
+        let html = metadata.get_raw("toc_html").unwrap();
+        assert_eq!(html.as_str(), Some("<ul><li>A<ul><li>A.1</li><li>A.2</li></ul></li><li>B</li></ul>"));
+    }
+
+    #[test]
+    fn no_headings_no_html() {
+        let metadata = Metadata::new();
+        Markdown::from("Just text.".to_string())
+            .plugin(TableOfContents::new(metadata.key("toc")).render_html(metadata.key("toc_html")))
+            .run()
+            .unwrap();
+
+        assert!(items(&metadata.get_raw("toc").unwrap()).is_empty());
+        assert_eq!(metadata.get_raw("toc_html").unwrap().as_str(), Some(""));
+    }
+}
@@ -2,11 +2,13 @@ use pulldown_cmark::{Event, Tag, CodeBlockKind, escape::escape_html};
 use tree_sitter_highlight::{HighlightConfiguration, Error};
 use once_cell::sync::Lazy;
 
-use super::Plugin;
+use super::{Plugin, CodeBlockStyle};
 
-pub struct Highlighter<I> {
+pub struct Highlighter<'s, I> {
+    style: &'s CodeBlockStyle,
     config: Option<&'static HighlightConfiguration>,
     code: String,
+    line_numbers: bool,
     inner: I,
 }
 
@@ -74,7 +76,7 @@ define_languages! {
     tree_sitter_toml: ["toml"],
 }
 
-impl<I> Highlighter<I> {
+impl<I> Highlighter<'_, I> {
     fn try_highlight_to_html(&self) -> Result<String, Error> {
         use std::fmt::Write;
         use tree_sitter_highlight::{Highlighter, HighlightEvent};
@@ -86,15 +88,6 @@ impl<I> Highlighter<I> {
         let highlights = hl.highlight(config, source, None, |_| None)?;
 
         let mut html = String::new();
-        html.push_str("<div class=\"code\" style=\"display: flex;\">");
-        html.push_str("<pre class=\"line-nums\">");
-        let lines = memchr::memrchr_iter(b'\n', source).count();
-        for i in 1..=lines {
-            if i < lines { let _ = write!(&mut html, "{}\n", i); }
-            else { let _ = write!(&mut html, "{}", i); }
-        }
-        html.push_str("</pre>");
-        html.push_str("<pre class=\"code\">");
 
         for event in highlights {
             match event? {
@@ -111,8 +104,8 @@ impl<I> Highlighter<I> {
             }
         }
 
-        html.push_str("</div>");
-        Ok(html)
+        let lines = self.line_numbers.then(|| super::highlight::line_count(&self.code));
+        Ok(self.style.code_div(lines, &html))
     }
 
     fn highlight_to_html(&self) -> String {
@@ -121,19 +114,17 @@ impl<I> Highlighter<I> {
     }
 }
 
-impl<'a, I: Iterator<Item = Event<'a>>> Iterator for Highlighter<I> {
+impl<'a, I: Iterator<Item = Event<'a>>> Iterator for Highlighter<'_, I> {
     type Item = Event<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             match self.inner.next()? {
                 Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(label))) => {
-                    let lang = label.split_once(',')
-                        .map(|(prefix, _)| prefix)
-                        .unwrap_or(&*label);
-
+                    let fence = self.style.fence(&label);
                     self.code = String::new();
-                    self.config = find_ts_highlight_config(lang);
+                    self.line_numbers = fence.line_numbers;
+                    self.config = find_ts_highlight_config(fence.lang);
                 }
                 Event::Text(text) if self.config.is_some() => {
                     self.code.push_str(&text);
@@ -150,19 +141,56 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for Highlighter<I> {
 }
 
 #[derive(Default, Clone)]
-pub struct SyntaxHighlight;
+pub struct SyntaxHighlight<'s> {
+    style: std::borrow::Cow<'s, CodeBlockStyle>,
+}
+
+impl<'s> SyntaxHighlight<'s> {
+    pub fn with_style(style: &'s CodeBlockStyle) -> Self {
+        SyntaxHighlight { style: std::borrow::Cow::Borrowed(style) }
+    }
 
-impl SyntaxHighlight {
     pub fn warm_up() {
         use rayon::prelude::*;
         rayon::spawn(|| config::ALL.par_iter().for_each(|lazy| { Lazy::force(lazy); }))
     }
 }
 
-impl Plugin for SyntaxHighlight {
+impl Plugin for SyntaxHighlight<'_> {
     fn remap<'a, I>(&'a mut self, events: I) -> Box<dyn Iterator<Item = Event<'a>> + 'a>
         where I: Iterator<Item = Event<'a>> + 'a
     {
-        Box::new(Highlighter { config: None, code: String::new(), inner: events })
+        Box::new(Highlighter {
+            style: &self.style,
+            config: None,
+            code: String::new(),
+            line_numbers: false,
+            inner: events,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlight(code: &str) -> String {
+        let highlighter = Highlighter {
+            style: &CodeBlockStyle::default(),
+            config: find_ts_highlight_config("rust"),
+            code: code.to_string(),
+            line_numbers: true,
+            inner: std::iter::empty::<Event<'_>>(),
+        };
+
+        highlighter.try_highlight_to_html().unwrap()
+    }
+
+    #[test]
+    fn gutter_matches_code_lines() {
+        assert!(highlight("a();").contains("<pre class=\"line-nums\">1</pre>"));
+        assert!(highlight("a();\n").contains("<pre class=\"line-nums\">1</pre>"));
+        assert!(highlight("a();\nb();").contains("<pre class=\"line-nums\">1\n2</pre>"));
+        assert!(highlight("a();\nb();\n").contains("<pre class=\"line-nums\">1\n2</pre>"));
     }
 }
@@ -15,6 +15,11 @@ pub use into_lossy::IntoPathStrLossy;
 
 use serde::{Serialize, Deserialize, Deserializer, Serializer};
 
+/// A path that is also a `str`: every `PathStr` is valid UTF-8.
+///
+/// Paths that aren't valid UTF-8 can only become a `PathStr` lossily, via
+/// [`IntoPathStrLossy`], which doesn't preserve equality. Comparisons against
+/// paths and OS strings are byte-for-byte.
 #[repr(transparent)]
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PathStr(OsStr);
@@ -53,6 +58,24 @@ impl PathStr {
     }
 }
 
+impl PartialEq<str> for PathStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<OsStr> for PathStr {
+    fn eq(&self, other: &OsStr) -> bool {
+        self.as_bytes() == other.as_encoded_bytes()
+    }
+}
+
+impl PartialEq<Path> for PathStr {
+    fn eq(&self, other: &Path) -> bool {
+        self.as_bytes() == other.as_os_str().as_encoded_bytes()
+    }
+}
+
 impl AsRef<str> for PathStr {
     fn as_ref(&self) -> &str {
         self.as_str()
@@ -0,0 +1,71 @@
+//! `sitemap.xml` files listing a site's pages for search engines.
+
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+
+use crate::url::Url;
+use crate::util::escape_html;
+
+/// A sitemap in the [sitemaps.org] format, rendered by [`Sitemap::render()`].
+///
+/// [sitemaps.org]: https://www.sitemaps.org/protocol.html
+///
+/// ```rust
+/// use harper::sitemap::Sitemap;
+/// use harper::url::Url;
+///
+/// let mut sitemap = Sitemap::default();
+/// sitemap.url(Url::new("https://example.com/a/"), None);
+/// sitemap.url(Url::new("https://example.com/?q=1&r=2"), None);
+///
+/// let xml = sitemap.render();
+/// assert!(xml.contains("<url>\n<loc>https://example.com/a/</loc>\n</url>\n"));
+/// assert!(xml.contains("<loc>https://example.com/?q=1&amp;r=2</loc>"));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Sitemap {
+    urls: Vec<(String, Option<SystemTime>)>,
+}
+
+impl Sitemap {
+    /// Adds the page at `loc`, last modified at `lastmod`, if it's known.
+    pub fn url(&mut self, loc: &Url, lastmod: Option<SystemTime>) -> &mut Self {
+        self.urls.push((loc.as_str().to_string(), lastmod));
+        self
+    }
+
+    /// The number of pages in the sitemap.
+    pub fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    /// Whether the sitemap has no pages.
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    /// Renders the sitemap with pages sorted by, and deduplicated on, their
+    /// URL. A page's `lastmod` is its date in UTC.
+    pub fn render(&self) -> String {
+        let mut urls: Vec<_> = self.urls.iter().collect();
+        urls.sort_by(|a, b| a.0.cmp(&b.0));
+        urls.dedup_by(|a, b| a.0 == b.0);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+        for (loc, lastmod) in urls {
+            let _ = write!(xml, "<url>\n<loc>{}</loc>\n", escape_html(loc));
+            if let Some(lastmod) = lastmod {
+                let date: DateTime<Utc> = (*lastmod).into();
+                let _ = writeln!(xml, "<lastmod>{}</lastmod>", date.format("%Y-%m-%d"));
+            }
+
+            xml.push_str("</url>\n");
+        }
+
+        xml.push_str("</urlset>\n");
+        xml
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use crate::diagnostics::{Diagnostics, Report};
+use crate::error::Result;
+use crate::taxonomy::*;
+
+/// A site generator: discovers a [`Site`], then renders it as a [`Renderer`].
+/// Run one with [`build()`].
+pub trait Generator: Renderer {
+    /// Discovers the site to render, e.g. by walking a directory of sources.
+    fn discover(&self) -> Result<Site>;
+
+    /// Where the generator records non-fatal issues, if anywhere. Those
+    /// recorded are returned by [`build()`]. Returns `None` by default.
+    #[inline(always)]
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        None
+    }
+}
+
+/// The result of a successful [`build()`].
+#[derive(Debug)]
+pub struct Build<O> {
+    /// The site that was discovered and rendered.
+    pub site: Arc<Site>,
+    /// What [`Renderer::render_site()`] returned.
+    pub output: O,
+    /// The diagnostics recorded while building, sorted by path.
+    pub report: Report,
+}
+
+/// Builds a site with `generator`: discovers it, renders it, and takes the
+/// diagnostics recorded along the way. Rendering uses whichever templating
+/// [`Engine`] `generator` was set up with.
+///
+/// [`Engine`]: crate::templating::Engine
+pub fn build<G: Generator + ?Sized>(generator: &G) -> Result<Build<G::Output>> {
+    let site = Arc::new(generator.discover()?);
+    let output = generator.render_site(&site)?;
+    let report = generator.diagnostics().map(Diagnostics::take).unwrap_or_default();
+    Ok(Build { site, output, report })
+}
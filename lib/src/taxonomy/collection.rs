@@ -4,7 +4,7 @@ use rayon::prelude::*;
 use rustc_hash::FxHashMap;
 
 use crate::fstree::{Entry, EntryId, FsTree, OwnedEntry};
-use crate::value::List;
+use crate::value::{Dict, List, Value};
 use crate::taxonomy::*;
 
 #[derive(Debug)]
@@ -43,6 +43,36 @@ impl Collection {
         &self.entry
     }
 
+    /// The collection in `site` rooted at the nearest ancestor directory of
+    /// this collection's root, if there is one.
+    pub fn parent<'s>(&self, site: &'s Site) -> Option<&'s Arc<Collection>> {
+        self.entry.tree.ancestors_of(self.entry.id)
+            .find_map(|id| site.collections.get(&id))
+    }
+
+    /// The collections in `site` whose parent is this collection, ordered by
+    /// the path of their root directory.
+    pub fn children<'s>(&self, site: &'s Site) -> Vec<&'s Arc<Collection>> {
+        let mut children: Vec<_> = site.collections.values()
+            .filter(|c| c.parent(site).is_some_and(|p| p.entry.id == self.entry.id))
+            .collect();
+
+        children.sort_by(|a, b| a.entry.path.cmp(&b.entry.path));
+        children
+    }
+
+    /// The metadata of every data item in the collection deep-merged, as by
+    /// [`Value::merge()`], into one dictionary. Items are merged in order of
+    /// their path, so on conflicting keys, the item whose path sorts last,
+    /// e.g. `b.toml` over `a.toml` and `z/a.toml` over `y/b.toml`, wins.
+    pub fn merged_data(&self) -> Value {
+        let mut data: Vec<_> = self.data.values().flat_map(|items| items.iter()).collect();
+        data.sort_by(|a, b| a.entry.path.cmp(&b.entry.path));
+        data.into_iter().fold(Value::Dict(Arc::new(Dict::new())), |merged, item| {
+            merged.merge(item.metadata.to_value())
+        })
+    }
+
     pub fn new_item(&mut self, id: EntryId) -> Arc<Item> {
         let item = Arc::new(Item::new(self.entry.tree.clone(), id));
         self.items.push(item.clone());
@@ -82,4 +112,28 @@ impl Collection {
             .map(|(kind, item)| map(kind, item))
             .collect()
     }
+
+    /// Like [`Collection::par_map_items()`], but returns results in a
+    /// deterministic order: the index, then items by position, then data by
+    /// group directory path and by position within each group.
+    pub fn map_items_ordered<M, R: Send>(&self, map: M) -> Vec<R>
+        where M: Fn(Kind, &Arc<Item>) -> R + Send + Sync
+    {
+        let tree = &self.entry.tree;
+        let mut data: Vec<_> = self.data.iter().collect();
+        data.sort_by(|(a, _), (b, _)| tree[**a].path.cmp(&tree[**b].path));
+
+        let index_content = self.index.iter().map(|index| (Kind::Index, index));
+        let item_content = self.items.iter()
+            .enumerate()
+            .map(|(i, item)| (Kind::Item(i), item));
+
+        let data_content = data.into_iter()
+            .flat_map(|(&id, items)| items.iter().map(move |item| (Kind::Datum(id), item)));
+
+        let content: Vec<_> = index_content.chain(item_content).chain(data_content).collect();
+        content.into_par_iter()
+            .map(|(kind, item)| map(kind, item))
+            .collect()
+    }
 }
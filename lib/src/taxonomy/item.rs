@@ -1,7 +1,10 @@
 use std::sync::Arc;
+use std::path::Path;
 
+use crate::error::{Chainable, Result};
 use crate::fstree::{EntryId, FsTree, OwnedEntry};
 use crate::taxonomy::*;
+use crate::url::Url;
 
 #[derive(Debug, Clone)]
 pub struct Item {
@@ -17,4 +20,74 @@ impl Item {
             metadata: Metadata::new(),
         }
     }
+
+    /// Reads the URL-typed metadata key `K`, if it's set.
+    pub fn url<K: MetaKey<Value = Arc<Url>>>(&self, key: K) -> Result<Option<Arc<Url>>> {
+        self.metadata.url(key).chain_with(|| error! {
+            "failed to read item URL",
+            "item" => self.entry.relative_path().display(),
+        })
+    }
+
+    /// Reads the path-typed metadata key `K`, if it's set.
+    pub fn path<K: MetaKey<Value = Arc<Path>>>(&self, key: K) -> Result<Option<Arc<Path>>> {
+        self.metadata.path(key).chain_with(|| error! {
+            "failed to read item path",
+            "item" => self.entry.relative_path().display(),
+        })
+    }
+
+    /// Reads the item's rendered [`Content`], if it's been rendered.
+    pub fn content(&self) -> Result<Option<Arc<str>>> {
+        self.metadata.try_get(Content).chain_with(|| error! {
+            "failed to read item content",
+            "item" => self.entry.relative_path().display(),
+        })
+    }
+
+    /// Whether the item is a [`Draft`]: `draft` is `true` in its metadata.
+    pub fn is_draft(&self) -> bool {
+        matches!(self.metadata.get(Draft), Some(Ok(true)))
+    }
+
+    /// Returns an identifier for `self` that is stable across builds. The id
+    /// is derived from the item's path relative to the tree root, is URL-safe,
+    /// and is unique to the path: bytes outside of `[A-Za-z0-9._-]` and `/`
+    /// are escaped as `~XX`, where `XX` is the byte in uppercase hex.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use harper::{Site, fstree::FsTree};
+    ///
+    /// let tree = Arc::new(FsTree::from_memory("/site", [
+    ///     ("content/My Post.md", ""),
+    ///     ("content/My~20Post.md", ""),
+    /// ]).unwrap());
+    ///
+    /// let mut site = Site::new(tree.clone());
+    /// let a = site.new_resource(tree.get_id(None, "content/My Post.md").unwrap());
+    /// let b = site.new_resource(tree.get_id(None, "content/My~20Post.md").unwrap());
+    /// assert_eq!(a.stable_id(), "content/My~20Post.md");
+    /// assert_eq!(b.stable_id(), "content/My~7E20Post.md");
+    /// ```
+    pub fn stable_id(&self) -> String {
+        use std::fmt::Write;
+
+        let path = self.entry.relative_path();
+        let mut id = String::with_capacity(path.as_os_str().len());
+        for (i, component) in path.components().enumerate() {
+            if i > 0 {
+                id.push('/');
+            }
+
+            for b in component.as_os_str().as_encoded_bytes() {
+                match b {
+                    b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' | b'-' => id.push(*b as char),
+                    _ => { let _ = write!(id, "~{:02X}", b); }
+                }
+            }
+        }
+
+        id
+    }
 }
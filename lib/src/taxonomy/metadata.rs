@@ -2,10 +2,12 @@ use std::fmt;
 use std::borrow::Borrow;
 use std::marker::PhantomData;
 use std::sync::Arc;
+use std::path::Path;
 
 use crate::value::{Source, Sink};
 use crate::error::Result;
 use crate::value::Value;
+use crate::url::Url;
 
 type Hasher = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
 
@@ -17,8 +19,9 @@ pub trait MetaKey: 'static {
 
 #[macro_export]
 macro_rules! define_meta_key {
-    ($($v:vis $T:ident : $key:literal => $V:ty),+ $(,)?) => {
+    ($($(#[$attr:meta])* $v:vis $T:ident : $key:literal => $V:ty),+ $(,)?) => {
         $(
+            $(#[$attr])*
             $v struct $T;
 
             impl $crate::MetaKey for $T {
@@ -29,6 +32,25 @@ macro_rules! define_meta_key {
     }
 }
 
+define_meta_key! {
+    /// An item's rendered content, e.g. the HTML rendered from its markdown.
+    /// Renderers that write content under this key let features like feeds
+    /// and search read it with [`Item::content()`].
+    ///
+    /// [`Item::content()`]: crate::taxonomy::Item::content()
+    pub Content : "content" => Arc<str>,
+
+    /// Whether an item is a draft. Drafts are never rendered and are left out
+    /// of collection listings and navigation. See [`Item::is_draft()`].
+    ///
+    /// [`Item::is_draft()`]: crate::taxonomy::Item::is_draft()
+    pub Draft : "draft" => bool,
+
+    /// An item's URL, relative to the site's root, once it's known. Template
+    /// functions like `ref` and `relref` resolve other items to this URL.
+    pub UrlRef : "url" => Arc<Url>,
+}
+
 #[derive(Clone)]
 pub struct Key<'m, 'k, V> {
     map: &'m Metadata,
@@ -56,6 +78,15 @@ impl Metadata {
         self.map.len()
     }
 
+    /// Returns a snapshot of the metadata as a dictionary.
+    pub fn to_value(&self) -> Value {
+        let dict: crate::value::Dict = self.map.iter()
+            .map(|r| (r.key().clone(), r.value().clone()))
+            .collect();
+
+        Value::Dict(Arc::new(dict))
+    }
+
     #[inline(always)]
     pub fn keys(&self) -> impl Iterator<Item = Arc<str>> + '_ {
         self.map.iter().map(|r| r.key().clone())
@@ -86,6 +117,36 @@ impl Metadata {
         Some(value.clone().try_into().map_err(|_| value))
     }
 
+    /// Returns the value for `K` if there is one. Returns an error if there is
+    /// a value but it isn't a `K::Value`.
+    pub fn try_get<K: MetaKey>(&self, _: K) -> Result<Option<K::Value>> {
+        let Some(value) = self.get_raw(K::KEY) else {
+            return Ok(None);
+        };
+
+        let kind = value.kind();
+        value.try_into()
+            .map(Some)
+            .map_err(|_| error! {
+                "invalid metadata value type",
+                "key" => K::KEY,
+                "expected type" => std::any::type_name::<K::Value>(),
+                "found value type" => kind,
+            })
+    }
+
+    /// Like [`Metadata::try_get()`] for a URL-typed key.
+    #[inline(always)]
+    pub fn url<K: MetaKey<Value = Arc<Url>>>(&self, key: K) -> Result<Option<Arc<Url>>> {
+        self.try_get(key)
+    }
+
+    /// Like [`Metadata::try_get()`] for a path-typed key.
+    #[inline(always)]
+    pub fn path<K: MetaKey<Value = Arc<Path>>>(&self, key: K) -> Result<Option<Arc<Path>>> {
+        self.try_get(key)
+    }
+
     #[inline(always)]
     pub fn contains<K: MetaKey>(&self, _: K) -> bool {
         self.contains_key(K::KEY)
@@ -157,16 +218,33 @@ impl Metadata {
         }
     }
 
-    // #[inline(always)]
-    // pub fn extend<I, K, V>(&self, iter: I)
-    //     where I: IntoIterator<Item = (K, V)>,
-    //           K: Into<Arc<str>>,
-    //           V: Into<Value>
-    // {
-    //     for (k, v) in iter.into_iter() {
-    //         self.insert_raw(k.into(), v.into());
-    //     }
-    // }
+    /// Inserts every entry in `dict`, like [`Metadata::append_all()`], but
+    /// skips keys that are already set unless `overwrite` is `true`. Useful
+    /// for injecting computed values, e.g. build information, into items
+    /// without clobbering what they set themselves.
+    pub fn inject(&self, dict: &crate::value::Dict, overwrite: bool) {
+        if overwrite {
+            return self.append_all(dict);
+        }
+
+        for (k, v) in dict {
+            self.map.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+    }
+
+    /// Inserts every `(key, value)` pair in `iter`, overwriting existing keys
+    /// like [`Metadata::insert_raw()`]. Unlike [`Metadata::append_all()`],
+    /// the pairs needn't be collected into a [`Dict`](crate::value::Dict).
+    #[inline(always)]
+    pub fn extend<I, K, V>(&self, iter: I)
+        where I: IntoIterator<Item = (K, V)>,
+              K: Into<Arc<str>>,
+              V: Into<Value>
+    {
+        for (k, v) in iter.into_iter() {
+            self.insert_raw(k.into(), v.into());
+        }
+    }
 }
 
 impl<V> fmt::Debug for Key<'_, '_, V> {
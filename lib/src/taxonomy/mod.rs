@@ -3,9 +3,11 @@ mod collection;
 mod item;
 mod renderer;
 mod metadata;
+mod build;
 
 pub use site::*;
 pub use collection::*;
 pub use item::*;
 pub use metadata::*;
 pub use renderer::*;
+pub use build::*;
@@ -38,8 +38,11 @@ pub fn render_collection<R>(
         }),
     );
 
-    collection.par_map_items(|kind, item| {
-        renderer.render_collection_item(kind, site, collection, item)
+    collection.par_map_items(|kind, item| -> Result<R::Render> {
+        renderer.before_item(kind, site, collection, item)?;
+        let render = renderer.render_collection_item(kind, site, collection, item)?;
+        renderer.after_item(kind, site, collection, item)?;
+        Ok(render)
     })
 }
 
@@ -64,6 +67,22 @@ pub trait Renderer: Sync {
         render_collection(self, site, collection)
     }
 
+    /// Called immediately before [`Renderer::render_collection_item()`] for
+    /// every collection item. Does nothing by default.
+    ///
+    /// Like `render_collection_item`, this is called concurrently from several
+    /// threads, with items rendered in no particular order. Any state the hook
+    /// touches must be synchronized.
+    #[inline(always)]
+    fn before_item(&self,
+        _kind: Kind,
+        _site: &Arc<Site>,
+        _collection: &Arc<Collection>,
+        _item: &Arc<Item>
+    ) -> Result<()> {
+        Ok(())
+    }
+
     fn render_collection_item(&self,
         kind: Kind,
         site: &Arc<Site>,
@@ -71,5 +90,21 @@ pub trait Renderer: Sync {
         item: &Arc<Item>
     ) -> Result<Self::Render>;
 
+    /// Called immediately after [`Renderer::render_collection_item()`]
+    /// succeeds for a collection item. Does nothing by default.
+    ///
+    /// The same thread-safety expectations as [`Renderer::before_item()`]
+    /// apply, and the two hooks for a given item are called on the same
+    /// thread.
+    #[inline(always)]
+    fn after_item(&self,
+        _kind: Kind,
+        _site: &Arc<Site>,
+        _collection: &Arc<Collection>,
+        _item: &Arc<Item>
+    ) -> Result<()> {
+        Ok(())
+    }
+
     fn render_site_item(&self, item: &Item) -> Result<()>;
 }
@@ -1,7 +1,9 @@
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 
 use rustc_hash::FxHashMap;
 
+use crate::feed::FeedLink;
 use crate::fstree::{EntryId, FsTree};
 use crate::taxonomy::*;
 
@@ -11,11 +13,67 @@ pub struct Site {
     pub items: Vec<Arc<Item>>,
     pub collections: FxHashMap<EntryId, Arc<Collection>>,
     pub index: FxHashMap<Arc<str>, EntryId>,
+    /// The directories that content paths, as passed to [`Site::item_at()`],
+    /// are relative to, in order of precedence. Defaults to the tree's root.
+    pub content_roots: Vec<EntryId>,
+    /// The feeds written for the site, available to templates as `site.feeds`
+    /// and, for a collection's feed, `collection.feed`.
+    pub feeds: Vec<FeedLink>,
+    /// Whether drafts are part of the build, as in a preview. Otherwise, as in
+    /// a production build, they're left out of collection listings, counts,
+    /// and navigation. Defaults to `false`.
+    pub drafts: bool,
+    /// Every item, including collection items, by the id of its source file.
+    /// Built on first use, so only once the site is complete.
+    by_source: OnceLock<FxHashMap<EntryId, Arc<Item>>>,
 }
 
 impl Site {
     pub fn new(tree: Arc<FsTree>) -> Site {
-        Site { tree, items: vec![], collections: Default::default(), index: Default::default() }
+        Site {
+            content_roots: vec![tree.root_id()],
+            tree,
+            items: vec![],
+            collections: Default::default(),
+            index: Default::default(),
+            feeds: vec![],
+            drafts: false,
+            by_source: OnceLock::new(),
+        }
+    }
+
+    /// Whether `item` is listed in collection listings, counts, and
+    /// navigation: it isn't a draft, or drafts are part of the build.
+    pub fn is_listed(&self, item: &Item) -> bool {
+        self.drafts || !item.is_draft()
+    }
+
+    /// Returns the item whose source file is `id`, if there is one.
+    ///
+    /// The first call indexes every item in the site. Items added afterwards
+    /// are not found.
+    pub fn item(&self, id: EntryId) -> Option<&Arc<Item>> {
+        let index = self.by_source.get_or_init(|| {
+            let collection_items = self.collections.values().flat_map(|collection| {
+                collection.index.iter()
+                    .chain(collection.items.iter())
+                    .chain(collection.data.values().flat_map(|data| data.iter()))
+            });
+
+            collection_items.chain(self.items.iter())
+                .map(|item| (item.entry.id, item.clone()))
+                .collect()
+        });
+
+        index.get(&id)
+    }
+
+    /// Returns the item whose source file is at `path` relative to the first
+    /// of the [`Site::content_roots`] containing such a file.
+    pub fn item_at<P: AsRef<Path>>(&self, path: P) -> Option<&Arc<Item>> {
+        self.content_roots.iter()
+            .find_map(|&root| self.tree.get_file_id(root, path.as_ref()))
+            .and_then(|id| self.item(id))
     }
 
     /// Panics if `name` is not unique to `root`.
@@ -53,7 +111,7 @@ impl Site {
             }
         }
 
-        println!("{prefix}{}", entry.path.strip_prefix(&root.path).unwrap().display());
+        println!("{prefix}{}", entry.path_relative_to(root).unwrap().display());
     }
 
     pub fn visualize(&self) {
@@ -1,18 +1,27 @@
 use std::sync::Arc;
+use std::path::Path;
+
 use minijinja::{Environment, path_loader};
 use minijinja::value::Value;
 use serde::Serialize;
 
-use crate::taxonomy::{Site, Item, Collection, Metadata};
-use crate::error::Result;
+use crate::taxonomy::{Site, Item, Collection, Kind, Metadata};
+use crate::error::{ErrorDetail, Result};
 use crate::fstree::{FsTree, EntryId};
 use crate::templating::{Engine, EngineInit};
 
 #[derive(Debug)]
 pub struct MiniJinjaEngine {
     env: Result<Environment<'static>>,
+    cache: Option<RenderCache>,
 }
 
+type Hasher = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
+
+/// Memoized renders keyed on the template name, the template string, and the
+/// context.
+type RenderCache = dashmap::DashMap<(Option<String>, String, crate::value::Value), String, Hasher>;
+
 #[derive(Debug)]
 pub struct SiteItem {
     pub site: Arc<Site>,
@@ -31,6 +40,51 @@ impl SiteItem {
         self.collection.as_ref()
             .and_then(|c| c.items.iter().position(|i| i.entry.id == self.item.entry.id))
     }
+
+    /// The kind of the item in its collection, or `None` if it isn't in one.
+    pub fn kind(&self) -> Option<Kind> {
+        if self.is_index() {
+            return Some(Kind::Index);
+        }
+
+        if let Some(i) = self.position() {
+            return Some(Kind::Item(i));
+        }
+
+        let collection = self.collection.as_ref()?;
+        collection.data.iter()
+            .find(|(_, data)| data.iter().any(|i| i.entry.id == self.item.entry.id))
+            .map(|(&group, _)| Kind::Datum(group))
+    }
+}
+
+/// Directories, relative to the templates root, searched for partials.
+pub const INCLUDE_DIRS: &[&str] = &["includes", "include"];
+
+/// Returns a loader that resolves template names relative to `root` and, if
+/// not found there, relative to each of the [`INCLUDE_DIRS`] in `root`. This
+/// allows `{% include "nav.html" %}` to find `includes/nav.html`.
+fn loader(root: &Path)
+    -> impl Fn(&str) -> Result<Option<String>, minijinja::Error> + Send + Sync + 'static
+{
+    let base = path_loader(root.to_path_buf());
+    let includes = INCLUDE_DIRS.iter()
+        .map(|dir| path_loader(root.join(dir)))
+        .collect::<Vec<_>>();
+
+    move |name| {
+        if let Some(source) = base(name)? {
+            return Ok(Some(source));
+        }
+
+        for include in &includes {
+            if let Some(source) = include(name)? {
+                return Ok(Some(source));
+            }
+        }
+
+        Ok(None)
+    }
 }
 
 fn try_init<G: Serialize>(
@@ -40,13 +94,14 @@ fn try_init<G: Serialize>(
 ) -> Result<Environment<'static>> {
     let mut env = Environment::new();
     env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+    env.set_debug(true);
 
     if let Some(root) = root {
-        env.set_loader(path_loader(&tree[root].path));
+        env.set_loader(loader(&tree[root].path));
     }
 
     #[cfg(feature = "plugins")]
-    if let Some(plugins) = super::plugins::init(tree)? {
+    if let Some(plugins) = super::plugins::init(tree.clone())? {
         use minijinja::State;
         use minijinja::value::Rest;
 
@@ -83,13 +138,36 @@ fn try_init<G: Serialize>(
         }
     }
 
-    env.add_global("G", Value::from_serializable(&globals));
+    // Menus, if any, are common enough to deserve a global of their own.
+    let globals = Value::from_serializable(&globals);
+    if let Some(menu) = globals.get_attr("menu").ok().filter(|menu| !menu.is_undefined()) {
+        env.add_global("menu", menu);
+    }
+
+    env.add_global("G", globals);
     env.add_function("join", ext::join);
     env.add_function("now", ext::now);
+    env.add_function("now_timestamp", ext::now_timestamp);
+    env.add_function("ref", ext::ref_);
+    env.add_function("relref", ext::relref);
+    env.add_function("load", {
+        let cache = Arc::new(ext::LoadCache::default());
+        move |path: &str| ext::load(&tree, &cache, path)
+    });
     env.add_filter("deslug", ext::deslug);
     env.add_filter("date", ext::date);
+    env.add_filter("timeago", ext::timeago);
     env.add_filter("split", ext::split);
+    env.add_filter("truncate", ext::truncate);
+    env.add_filter("truncatewords", ext::truncatewords);
+    env.add_filter("striptags", ext::striptags);
+    env.add_filter("plaintext", ext::striptags);
     env.add_filter("get", ext::get);
+    env.add_filter("default", ext::default);
+    env.add_filter("d", ext::default);
+    env.add_filter("coalesce", ext::default);
+    env.add_filter("concat", ext::concat);
+    env.add_filter("merge", ext::merge);
     Ok(env)
 }
 
@@ -97,11 +175,55 @@ impl EngineInit for MiniJinjaEngine {
     type Engine = Self;
 
     fn init<G: Serialize>(tree: Arc<FsTree>, root: Option<EntryId>, globals: G) -> Self::Engine {
-        MiniJinjaEngine { env: try_init(tree, root, globals) }
+        MiniJinjaEngine { env: try_init(tree, root, globals), cache: None }
+    }
+
+    fn init_cached<G: Serialize>(
+        tree: Arc<FsTree>,
+        root: Option<EntryId>,
+        globals: G
+    ) -> Self::Engine {
+        MiniJinjaEngine { cache: Some(RenderCache::default()), ..Self::init(tree, root, globals) }
+    }
+}
+
+impl MiniJinjaEngine {
+    /// Renders `template_str` with the context `context`, memoizing the output
+    /// if caching is enabled.
+    fn render_cached(
+        &self,
+        name: Option<&str>,
+        template_str: &str,
+        context: crate::value::Value,
+    ) -> Result<String> {
+        let env = self.env.as_ref().map_err(|e| e.clone())?;
+        let render = |context| match name {
+            Some(name) => env.render_named_str(name, template_str, context),
+            None => env.render_str(template_str, context),
+        };
+
+        let Some(cache) = &self.cache else {
+            return Ok(render(Value::from(context))?);
+        };
+
+        let key = (name.map(String::from), template_str.to_string(), context);
+        if let Some(output) = cache.get(&key) {
+            return Ok(output.clone());
+        }
+
+        let output = render(Value::from(key.2.clone()))?;
+        cache.insert(key, output.clone());
+        Ok(output)
     }
 }
 
 impl Engine for MiniJinjaEngine {
+    fn compile(&self, name: &str) -> Result<()> {
+        let env = self.env.as_ref().map_err(|e| e.clone())?;
+        env.get_template(name)?;
+        Ok(())
+    }
+
     fn render(
         &self,
         name: &str,
@@ -150,6 +272,10 @@ impl Engine for MiniJinjaEngine {
         template_str: &str,
         meta: Metadata,
     ) -> Result<String> {
+        if self.cache.is_some() {
+            return self.render_cached(name, template_str, meta.to_value());
+        }
+
         let env = self.env.as_ref().map_err(|e| e.clone())?;
         let context = Value::from_object(meta);
         let string = match name {
@@ -159,15 +285,35 @@ impl Engine for MiniJinjaEngine {
 
         Ok(string)
     }
+
+    fn render_value(
+        &self,
+        name: Option<&str>,
+        template_str: &str,
+        context: crate::value::Value,
+    ) -> Result<String> {
+        self.render_cached(name, template_str, context)
+    }
 }
 
 mod ext {
+    use std::fmt;
     use std::sync::Arc;
 
-    use chrono::{NaiveDate, NaiveTime, DateTime, Utc};
-    use minijinja::{value::{intern, DynObject, Rest, Value}, Error, ErrorKind, State};
+    use chrono::{NaiveDate, NaiveTime, NaiveDateTime, DateTime, Utc};
+    use chrono_tz::Tz;
+    use minijinja::value::{intern, DynObject, Enumerator, Kwargs, Object, ObjectRepr, Rest, Value, ValueKind};
+    use minijinja::{Error, ErrorKind, State};
+
+    use dashmap::DashMap;
 
     use crate::url::Url;
+    use crate::fstree::{EntryId, FsTree};
+    use crate::value::{Json, Mapper, Toml, Yaml};
+
+    type Hasher = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
+
+    pub type LoadCache = DashMap<EntryId, Value, Hasher>;
 
     trait Ext {
         fn find(self, key: &str) -> Result<Value, Error>;
@@ -220,7 +366,15 @@ mod ext {
             .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "G.root must be a URL"))?;
 
         let mut url = url_base.to_url_buf();
-        url.extend(values.iter());
+        for value in values.iter() {
+            let segment = Url::try_new(value).ok_or_else(|| Error::new(
+                ErrorKind::InvalidOperation,
+                format!("`join` argument {value:?} isn't a valid URL")
+            ))?;
+
+            url.append(segment);
+        }
+
         Ok(Value::from_safe_string(url.into()))
     }
 
@@ -228,38 +382,301 @@ mod ext {
         value.replace('-', " ")
     }
 
-    pub fn date(value: Value, fmt: &str) -> Result<Value, Error> {
-        use chrono::naive::NaiveDateTime;
+    /// A point in time parsed from a template value.
+    pub enum Moment {
+        Date(NaiveDate),
+        Time(NaiveTime),
+        Naive(NaiveDateTime),
+        Instant(DateTime<Utc>),
+    }
+
+    impl Moment {
+        /// Parses a unix timestamp, a TOML datetime, or a date, time, or
+        /// datetime string. `filter` names the filter for error messages.
+        pub fn parse(value: Value, filter: &str) -> Result<Moment, Error> {
+            if let Some(now) = value.downcast_object_ref::<Now>() {
+                return Ok(Moment::Instant(now.0));
+            }
+
+            // Fractional timestamps keep their sub-second part.
+            let timestamp = match value.clone().try_into() {
+                Ok(secs) => Some((secs, 0)),
+                Err(_) => f64::try_from(value.clone()).ok()
+                    .filter(|ts| ts.is_finite() && ts.abs() < i64::MAX as f64)
+                    .map(|ts| (ts.floor() as i64, ((ts - ts.floor()) * 1e9) as u32)),
+            };
+
+            if let Some((secs, nanos)) = timestamp {
+                let datetime = DateTime::from_timestamp(secs, nanos)
+                    .ok_or_else(|| Error::new(
+                        ErrorKind::InvalidOperation,
+                        format!("invalid timestamp provided to `{filter}`")
+                    ))?;
 
-        if let Ok(ts) = value.clone().try_into() {
-            let datetime = DateTime::from_timestamp(ts, 0)
+                return Ok(Moment::Instant(datetime));
+            }
+
+            let kind = value.kind();
+            let attr = value.get_attr("$__toml_private_datetime").ok()
+                .filter(|v| !v.is_undefined());
+
+            let string = attr.as_ref()
+                .map_or_else(|| value.as_str(), |v| v.as_str())
                 .ok_or_else(|| Error::new(
                     ErrorKind::InvalidOperation,
-                    "invalid timestamp provided to `date`"
+                    format!("`{filter}` must be applied to a string or integer, found {kind}")
                 ))?;
 
-            return Ok(datetime.format(fmt).to_string().into());
+            string.parse::<NaiveDate>().map(Moment::Date)
+                .or_else(|_| string.parse::<NaiveTime>().map(Moment::Time))
+                .or_else(|_| string.parse::<NaiveDateTime>().map(Moment::Naive))
+                .or_else(|_| string.parse::<DateTime<Utc>>().map(Moment::Instant))
+                .map_err(|e| Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("failed to parse {string}: {e}")
+                ))
         }
 
-        let kind = value.kind();
-        let attr = value.get_attr("$__toml_private_datetime");
-        let string = attr.as_ref()
-            .map_or_else(|_| value.as_str(), |v| v.as_str())
+        /// Converts `self` into a UTC instant. Dates are taken to be midnight.
+        /// Times alone don't name an instant and result in an error.
+        pub fn to_utc(&self, filter: &str) -> Result<DateTime<Utc>, Error> {
+            match self {
+                Moment::Date(d) => Ok(d.and_time(NaiveTime::MIN).and_utc()),
+                Moment::Naive(dt) => Ok(dt.and_utc()),
+                Moment::Instant(dt) => Ok(*dt),
+                Moment::Time(_) => Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("`{filter}` must be applied to a date or datetime, found a time")
+                )),
+            }
+        }
+    }
+
+    /// Returns the timezone named `tz` or, if `None`, the site-wide default in
+    /// `G.timezone`, or UTC if neither is set.
+    pub fn timezone(state: &State, tz: Option<&str>) -> Result<Tz, Error> {
+        let global = state.lookup("G").and_then(|g| g.get_attr("timezone").ok());
+        let name = match (tz, global.as_ref().and_then(|v| v.as_str())) {
+            (Some(name), _) | (None, Some(name)) => name,
+            (None, None) => return Ok(Tz::UTC),
+        };
+
+        name.parse::<Tz>().map_err(|e| Error::new(
+            ErrorKind::InvalidOperation,
+            format!("invalid timezone {name}: {e}")
+        ))
+    }
+
+    pub fn date(state: &State, value: Value, fmt: &str, kwargs: Kwargs) -> Result<Value, Error> {
+        let tz = timezone(state, kwargs.get("tz")?)?;
+        kwargs.assert_all_used()?;
+
+        let string = match Moment::parse(value, "date")? {
+            Moment::Date(d) => d.format(fmt).to_string(),
+            Moment::Time(t) => t.format(fmt).to_string(),
+            Moment::Naive(dt) => dt.format(fmt).to_string(),
+            Moment::Instant(dt) => dt.with_timezone(&tz).format(fmt).to_string(),
+        };
+
+        Ok(string.into())
+    }
+
+    pub fn timeago(value: Value, granularity: Option<&str>) -> Result<Value, Error> {
+        const UNITS: &[(&str, i64)] = &[
+            ("year", 365 * 86400),
+            ("month", 30 * 86400),
+            ("week", 7 * 86400),
+            ("day", 86400),
+            ("hour", 3600),
+            ("minute", 60),
+            ("second", 1),
+        ];
+
+        let granularity = granularity.unwrap_or("second");
+        let (_, min) = UNITS.iter()
+            .find(|(unit, _)| *unit == granularity.trim_end_matches('s'))
             .ok_or_else(|| Error::new(
                 ErrorKind::InvalidOperation,
-                format!("`date` must be applied to a string or integer, found {kind}")
+                format!("unknown `timeago` granularity {granularity}")
             ))?;
 
-        let datetime = string.parse::<NaiveDate>().map(|d| d.format(fmt))
-            .or_else(|_| string.parse::<NaiveTime>().map(|t| t.format(fmt)))
-            .or_else(|_| string.parse::<NaiveDateTime>().map(|dt| dt.format(fmt)))
-            .or_else(|_| string.parse::<DateTime<Utc>>().map(|dt| dt.format(fmt)))
-            .map_err(|e| Error::new(
+        let then = Moment::parse(value, "timeago")?.to_utc("timeago")?;
+        let delta = Utc::now().signed_duration_since(then).num_seconds();
+        let seconds = delta.abs();
+        if seconds < *min {
+            return Ok("just now".into());
+        }
+
+        let (unit, n) = UNITS.iter()
+            .filter(|(_, length)| length >= min)
+            .find(|(_, length)| seconds >= *length)
+            .map(|(unit, length)| (unit, seconds / length))
+            .unwrap();
+
+        let s = if n == 1 { "" } else { "s" };
+        match delta >= 0 {
+            true => Ok(format!("{n} {unit}{s} ago").into()),
+            false => Ok(format!("in {n} {unit}{s}").into()),
+        }
+    }
+
+    /// Loads and parses the data file at `path`, relative to the site root,
+    /// according to its extension: `toml`, `json`, or `yaml`/`yml`. Parsed
+    /// files are cached in `cache`. Paths that are absolute or contain `..`
+    /// are rejected.
+    pub fn load(
+        tree: &FsTree,
+        cache: &LoadCache,
+        path: &str,
+    ) -> Result<Value, Error> {
+        use std::path::{Component, Path};
+
+        let escapes = Path::new(path).components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+
+        if escapes {
+            return Err(Error::new(
                 ErrorKind::InvalidOperation,
-                format!("failed to parse {string}: {e}")
+                format!("`load`: data file path {path} must be relative to the site root")
+            ));
+        }
+
+        let id = tree.get_file_id(None, path)
+            .ok_or_else(|| Error::new(
+                ErrorKind::InvalidOperation,
+                format!("`load`: data file {path} does not exist")
             ))?;
 
-        Ok(datetime.to_string().into())
+        if let Some(value) = cache.get(&id) {
+            return Ok(value.clone());
+        }
+
+        let entry = &tree[id];
+        let result = match entry.file_ext() {
+            Some("toml") => Toml.map(entry),
+            Some("json") => Json.map(entry),
+            Some("yaml" | "yml") => Yaml.map(entry),
+            ext => return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("`load`: unsupported data file format `{}` in {path}; \
+                    expected toml, json, or yaml", ext.unwrap_or_default())
+            )),
+        };
+
+        let value = result.map(Value::from).map_err(|e| Error::new(
+            ErrorKind::InvalidOperation,
+            format!("`load`: failed to parse {path}:\n{e}")
+        ))?;
+
+        cache.insert(id, value.clone());
+        Ok(value)
+    }
+
+    /// Returns the URL of the item at `reference`, a path to its source file
+    /// with or without an extension, relative to the URL of the current item.
+    /// `reference` is relative to the root of the `collection` named in the
+    /// kwargs or, by default, of the current item's collection. The target's
+    /// URL must already be known, as it is when rendering templates.
+    pub fn relref(state: &State, reference: &str, kwargs: Kwargs) -> Result<Value, Error> {
+        use std::path::Path;
+        use crate::util::PathExt;
+
+        let error = |msg: String| {
+            Error::new(ErrorKind::InvalidOperation, format!("`relref`: {msg}"))
+        };
+
+        let name = kwargs.get::<Option<&str>>("collection")?;
+        kwargs.assert_all_used()?;
+
+        let current = state.lookup("collection");
+        let current = current.as_ref()
+            .and_then(|c| c.downcast_object_ref::<super::taxonomy_object::SiteCollection>())
+            .ok_or_else(|| error("the current item isn't in a collection".into()))?;
+
+        let site = &current.site;
+        let collection = match name {
+            Some(name) => site.index.get(name)
+                .and_then(|id| site.collections.get(id))
+                .ok_or_else(|| error(format!("unknown collection {name:?}")))?,
+            None => &current.collection,
+        };
+
+        let root = collection.entry.path.join(reference);
+        let target = collection.index.iter()
+            .chain(collection.items.iter())
+            .chain(collection.data.values().flat_map(|data| data.iter()))
+            .find(|item| *item.entry.path == *root || item.entry.path.without_extension() == root)
+            .ok_or_else(|| error(format!(
+                "no item {reference:?} in collection {:?}", collection.name
+            )))?;
+
+        let target_url = target.metadata.url(crate::UrlRef)
+            .map_err(|e| error(e.to_string()))?
+            .ok_or_else(|| error(format!("{reference:?} doesn't have a URL")))?;
+        let target_url = target_url.as_str();
+
+        let current_url = state.lookup("url")
+            .and_then(|url| url.as_str().map(String::from))
+            .ok_or_else(|| error("the current item doesn't have a URL".into()))?;
+
+        let (from, to) = (url_path(&current_url), url_path(target_url));
+        let base = &from[..from.rfind('/').map_or(0, |i| i + 1)];
+        let relative = crate::util::diff_paths(Path::new(to), Path::new(base))
+            .ok_or_else(|| error(format!("no relative path from {current_url} to {target_url}")))?;
+
+        let mut relative = relative.to_string_lossy().into_owned();
+        if relative.is_empty() {
+            relative.push('.');
+        }
+
+        if to.ends_with('/') && !relative.ends_with('/') {
+            relative.push('/');
+        }
+
+        Ok(Value::from_safe_string(relative))
+    }
+
+    /// Returns the URL of the item whose source file is at `path`, relative to
+    /// a content root. Fails if there's no such item or it has no URL yet.
+    pub fn ref_(state: &State, path: &str) -> Result<Value, Error> {
+        let error = |msg: String| Error::new(ErrorKind::InvalidOperation, format!("`ref`: {msg}"));
+        let site = state.lookup("site");
+        let site = site.as_ref()
+            .and_then(|site| site.downcast_object_ref::<crate::taxonomy::Site>())
+            .ok_or_else(|| error("the site isn't in the context".into()))?;
+
+        let item = site.item_at(path)
+            .ok_or_else(|| error(format!("no content item at {path:?}")))?;
+
+        let url = item.metadata.url(crate::UrlRef)
+            .map_err(|e| error(e.to_string()))?
+            .ok_or_else(|| error(format!("{path:?} doesn't have a URL yet")))?;
+
+        Ok(Value::from_safe_string(url.as_str().into()))
+    }
+
+    /// The path of `url`, without its scheme and authority, if any.
+    fn url_path(url: &str) -> &str {
+        match url.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+            None => url,
+        }
+    }
+
+    pub fn truncate(value: &str, n: usize, kwargs: Kwargs) -> Result<String, Error> {
+        let suffix = kwargs.get::<Option<&str>>("suffix")?.unwrap_or("…");
+        kwargs.assert_all_used()?;
+        Ok(crate::util::truncate(value, n, suffix).into_owned())
+    }
+
+    pub fn truncatewords(value: &str, n: usize, kwargs: Kwargs) -> Result<String, Error> {
+        let suffix = kwargs.get::<Option<&str>>("suffix")?.unwrap_or("…");
+        kwargs.assert_all_used()?;
+        Ok(crate::util::truncate_words(value, n, suffix).into_owned())
+    }
+
+    pub fn striptags(value: &str) -> String {
+        crate::util::strip_tags(value)
     }
 
     pub fn split(value: &str, pat: &str, n: Option<usize>) -> Result<Value, Error> {
@@ -269,7 +686,44 @@ mod ext {
         }
     }
 
-    pub fn now() -> u64 {
+    /// The current instant as returned by `now()`. Exposes `year`, `month`,
+    /// `day`, `hour`, `minute`, `second`, and `timestamp` and renders as an
+    /// RFC 3339 string.
+    #[derive(Debug)]
+    pub struct Now(pub DateTime<Utc>);
+
+    impl Object for Now {
+        fn repr(self: &Arc<Self>) -> ObjectRepr {
+            ObjectRepr::Plain
+        }
+
+        fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+            use chrono::{Datelike, Timelike};
+
+            let value = match key.as_str()? {
+                "year" => self.0.year().into(),
+                "month" => self.0.month().into(),
+                "day" => self.0.day().into(),
+                "hour" => self.0.hour().into(),
+                "minute" => self.0.minute().into(),
+                "second" => self.0.second().into(),
+                "timestamp" => self.0.timestamp().into(),
+                _ => return None,
+            };
+
+            Some(value)
+        }
+
+        fn render(self: &Arc<Self>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.0.to_rfc3339())
+        }
+    }
+
+    pub fn now() -> Value {
+        Value::from_object(Now(Utc::now()))
+    }
+
+    pub fn now_timestamp() -> u64 {
         std::time::SystemTime::now()
             .duration_since(std::time::SystemTime::UNIX_EPOCH)
             .unwrap()
@@ -279,6 +733,119 @@ mod ext {
     pub fn get(map: DynObject, key: &str, default: Value) -> Value {
         map.get_value(&Value::from(key)).unwrap_or(default)
     }
+
+    /// `value | default(fallback)`: `fallback`, or `""` if unset, if `value`
+    /// is missing, else `value`. A value is missing if it is:
+    ///
+    ///   * undefined, which includes Harper's `null`, or `none`
+    ///   * an empty string, unless `strings=false`
+    ///   * an empty sequence, unless `sequences=false`
+    ///   * falsy at all, if `boolean` is `true`, as in Jinja's `default`
+    ///
+    /// Strings of whitespace and empty maps are not missing.
+    pub fn default(
+        value: Value,
+        fallback: Option<Value>,
+        boolean: Option<bool>,
+        kwargs: Kwargs,
+    ) -> Result<Value, Error> {
+        let strings = kwargs.get::<Option<bool>>("strings")?.unwrap_or(true);
+        let sequences = kwargs.get::<Option<bool>>("sequences")?.unwrap_or(true);
+        kwargs.assert_all_used()?;
+
+        let missing = value.is_undefined()
+            || value.is_none()
+            || (boolean.unwrap_or(false) && !value.is_true())
+            || (strings && value.as_str() == Some(""))
+            || (sequences && value.kind() == ValueKind::Seq && value.len() == Some(0));
+
+        match missing {
+            true => Ok(fallback.unwrap_or_else(|| Value::from(""))),
+            false => Ok(value),
+        }
+    }
+
+    /// Whether `value` is a sequence: a list, or an object, like a
+    /// collection's `items`, enumerated by index.
+    fn is_sequence(value: &Value) -> bool {
+        match value.as_object() {
+            Some(object) => matches!(object.enumerate(), Enumerator::Seq(_))
+                || matches!(object.repr(), ObjectRepr::Seq | ObjectRepr::Iterable),
+            None => matches!(value.kind(), ValueKind::Seq | ValueKind::Iterable),
+        }
+    }
+
+    /// `value | concat(other, ..., unique=false)`: the items of `value`
+    /// followed by those of each `other` sequence. Undefined and `none`
+    /// sequences are skipped. With `unique=true`, only the first of equal
+    /// items is kept, so the same item in two lists appears once.
+    pub fn concat(value: Value, others: Rest<Value>, kwargs: Kwargs) -> Result<Value, Error> {
+        let unique = kwargs.get::<Option<bool>>("unique")?.unwrap_or(false);
+        kwargs.assert_all_used()?;
+
+        let mut items: Vec<Value> = vec![];
+        for sequence in std::iter::once(&value).chain(others.iter()) {
+            if sequence.is_undefined() || sequence.is_none() {
+                continue;
+            }
+
+            if !is_sequence(sequence) {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("`concat` expects sequences, found {}", sequence.kind())
+                ));
+            }
+
+            for item in sequence.try_iter()? {
+                if !unique || !items.contains(&item) {
+                    items.push(item);
+                }
+            }
+        }
+
+        Ok(Value::from(items))
+    }
+
+    /// `value | merge(other, ..., deep=true)`: the dictionary `value` with the
+    /// keys of each `other` merged in, later ones winning. With `deep=true`,
+    /// nested dictionaries are merged as in [`Value::merge()`], otherwise they
+    /// are replaced. Undefined and `none` dictionaries are skipped.
+    ///
+    /// [`Value::merge()`]: crate::value::Value::merge()
+    pub fn merge(value: Value, others: Rest<Value>, kwargs: Kwargs) -> Result<Value, Error> {
+        use serde::Deserialize;
+        use crate::value::{Dict, Value as HValue};
+
+        let deep = kwargs.get::<Option<bool>>("deep")?.unwrap_or(true);
+        kwargs.assert_all_used()?;
+
+        let empty: Dict = Dict::new();
+        let mut merged = HValue::from(empty);
+        for dict in std::iter::once(value).chain(others.iter().cloned()) {
+            if dict.is_undefined() || dict.is_none() {
+                continue;
+            }
+
+            if dict.kind() != ValueKind::Map || is_sequence(&dict) {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("`merge` expects dictionaries, found {}", dict.kind())
+                ));
+            }
+
+            let dict = HValue::deserialize(dict)?;
+            merged = match (deep, merged, dict) {
+                (false, HValue::Dict(base), HValue::Dict(other)) => {
+                    let mut base = Arc::try_unwrap(base).unwrap_or_else(|base| (*base).clone());
+                    base.extend(other.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    HValue::from(Arc::new(base))
+                }
+                (_, merged, dict) => merged.merge(dict),
+            };
+        }
+
+        Ok(Value::from(merged))
+    }
 }
 
 mod value_object {
@@ -296,10 +863,11 @@ mod value_object {
             ObjectRepr::Map
         }
 
+        /// A missing key is undefined rather than an error, even with strict
+        /// undefined behavior, so that `dict.key is defined` works.
         fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
-            self.0.get(key.as_str()?)
-                .cloned()
-                .map(Value::from)
+            let value = self.0.get(key.as_str()?).cloned();
+            Some(value.map_or(Value::UNDEFINED, Value::from))
         }
 
         fn enumerate(self: &Arc<Self>) -> Enumerator {
@@ -327,6 +895,7 @@ mod value_object {
             match value {
                 Value::Null => Self::UNDEFINED,
                 Value::Bool(b) => Self::from(b),
+                Value::Num(n) if n.is_float() => Self::from(n.to_f64()),
                 Value::Num(n) => match n.to_u128_strict() {
                     Ok(v) => Self::from(v),
                     Err(v) => Self::from(v),
@@ -345,11 +914,12 @@ mod taxonomy_object {
     use minijinja::value::{Enumerator, Object, ObjectExt, ObjectRepr, Value};
 
     use super::SiteItem;
-    use crate::{declare_variation, taxonomy::{Collection, Item, Metadata, Site}, value::List};
+    use crate::{declare_variation, taxonomy::{Collection, Item, Kind, Metadata, Site}};
+    use crate::value::{Dict, Value as HValue};
 
     declare_variation!(SiteItems of Site);
     declare_variation!(SiteCollections of Site);
-    declare_variation!(CollectionItems of Collection);
+    declare_variation!(CollectionItems of SiteCollection);
     declare_variation!(CollectionData of Collection);
 
     // FIXME: Use `this` or `item` to refer to the item to avoid key collisions
@@ -363,23 +933,62 @@ mod taxonomy_object {
         fn get_value(self: &Arc<Self>, name: &Value) -> Option<Value> {
             let value = match name.as_str()? {
                 "site" => Value::from_dyn_object(self.site.clone()),
-                "collection" => Value::from_dyn_object(self.collection.as_ref()?.clone()),
+                "collection" => {
+                    let collection = self.collection.as_ref()?.clone();
+                    SiteCollection::value(self.site.clone(), collection)
+                },
                 "position" => self.position()?.into(),
                 "is_index" => self.is_index().into(),
+                // A paginated index lists the `paginate` published items on
+                // its `page`, 1-based. Anything else lists all of them.
+                "page_items" => {
+                    let collection = self.collection.as_ref()?;
+                    let meta = |key| self.item.metadata.get_raw(key).and_then(|v| usize::try_from(v).ok());
+                    let (skip, take) = match (meta("paginate").filter(|&n| n > 0), meta("page")) {
+                        (Some(n), Some(page)) => (page.saturating_sub(1).saturating_mul(n), n),
+                        _ => (0, usize::MAX),
+                    };
+
+                    collection.items.iter()
+                        .filter(|item| self.site.is_listed(item))
+                        .skip(skip)
+                        .take(take)
+                        .map(|item| Value::from_dyn_object(item.clone()))
+                        .collect::<Vec<_>>()
+                        .into()
+                }
+                "kind" | "group" if self.item.metadata.contains_key(name.as_str()?) => {
+                    self.item.get_value(name)?
+                }
+                "kind" => match self.kind()? {
+                    Kind::Index => "index".into(),
+                    Kind::Item(_) => "item".into(),
+                    Kind::Datum(_) => "datum".into(),
+                },
+                "group" => match self.kind()? {
+                    Kind::Datum(group) => self.site.tree[group].file_stem().into(),
+                    _ => return None,
+                },
+                // Navigation skips drafts, unless they're part of the build.
                 "next" => {
                     let collection = self.collection.as_ref()?;
                     let j = self.is_index()
                         .then_some(0)
                         .or_else(|| self.position().map(|i| i.saturating_add(1)))?;
 
-                    let next = collection.items.get(j)?;
+                    let next = collection.items.iter().skip(j).find(|item| self.site.is_listed(item))?;
                     Value::from_dyn_object(next.clone())
                 },
                 "previous" => {
                     let collection = self.collection.as_ref()?;
-                    let item = match self.position()? {
-                        0 => collection.index.as_ref()?,
-                        i => collection.items.get(i - 1)?,
+                    let i = self.position()?;
+                    let previous = (0..i).rev()
+                        .filter_map(|j| collection.items.get(j))
+                        .find(|item| self.site.is_listed(item));
+
+                    let item = match previous {
+                        Some(item) => item,
+                        None => collection.index.as_ref()?,
                     };
 
                     Value::from_dyn_object(item.clone())
@@ -392,7 +1001,10 @@ mod taxonomy_object {
 
         fn enumerate(self: &Arc<Self>) -> Enumerator {
             self.mapped_enumerator(|this| Box::new({
-                let keys = &["site", "collection", "position", "is_index", "next", "previous"];
+                let keys = &[
+                    "site", "collection", "position", "is_index", "page_items", "kind",
+                    "group", "next", "previous",
+                ];
                 let unique_keys = keys.into_iter()
                     .filter(|x| !this.item.metadata.contains_key(x))
                     .map(|x| Value::from(*x));
@@ -407,6 +1019,20 @@ mod taxonomy_object {
             let value = match key.as_str()? {
                 "items" => Value::from_dyn_object(SiteItems::new(self.clone())),
                 "collections" => Value::from_dyn_object(SiteCollections::new(self.clone())),
+                "feeds" => self.feeds.iter()
+                    .map(|feed| {
+                        let collection = feed.collection
+                            .and_then(|id| self.collections.get(&id))
+                            .map(|collection| collection.name.clone());
+
+                        HValue::from(Dict::<&str>::from([
+                            ("url", HValue::from(feed.url.clone())),
+                            ("type", feed.format.media_type().into()),
+                            ("collection", collection.into()),
+                        ]))
+                    })
+                    .collect::<HValue>()
+                    .into(),
                 _ => return None,
             };
 
@@ -414,7 +1040,7 @@ mod taxonomy_object {
         }
 
         fn enumerate(self: &Arc<Self>) -> Enumerator {
-            Enumerator::Str(&["items", "collections"])
+            Enumerator::Str(&["items", "collections", "feeds"])
         }
     }
 
@@ -437,7 +1063,7 @@ mod taxonomy_object {
         fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
             let id = self.index.get(key.as_str()?)?;
             let collection = self.collections.get(id)?.clone();
-            Some(Value::from_dyn_object(collection))
+            Some(SiteCollection::value(self.as_original().clone(), collection))
         }
 
         fn enumerate(self: &Arc<Self>) -> Enumerator {
@@ -451,25 +1077,81 @@ mod taxonomy_object {
         fn get_value(self: &Arc<Self>, name: &Value) -> Option<Value> {
             Some(match name.as_str()? {
                 "index" => Value::from_dyn_object(self.index.clone()?),
-                "items" => Value::from_dyn_object(CollectionItems::new(self.clone())),
                 "data" => Value::from_dyn_object(CollectionData::new(self.clone())),
+                "merged_data" => Value::from(self.merged_data()),
                 _ => return None,
             })
         }
 
         fn enumerate(self: &Arc<Self>) -> Enumerator {
-            Enumerator::Str(&["index", "items", "data"])
+            Enumerator::Str(&["index", "data", "merged_data"])
+        }
+    }
+
+    /// A collection along with the site it belongs to, which is needed to
+    /// resolve the collection's `parent` and `children`.
+    #[derive(Debug)]
+    pub(super) struct SiteCollection {
+        pub(super) site: Arc<Site>,
+        pub(super) collection: Arc<Collection>,
+    }
+
+    impl SiteCollection {
+        fn value(site: Arc<Site>, collection: Arc<Collection>) -> Value {
+            Value::from_object(SiteCollection { site, collection })
+        }
+
+        /// The collection's items, in order, that are listed. See
+        /// [`Site::is_listed()`].
+        fn listed_items(&self) -> impl DoubleEndedIterator<Item = &Arc<Item>> + '_ {
+            self.collection.items.iter().filter(|item| self.site.is_listed(item))
+        }
+    }
+
+    impl Object for SiteCollection {
+        fn get_value(self: &Arc<Self>, name: &Value) -> Option<Value> {
+            let site = &self.site;
+            Some(match name.as_str()? {
+                "items" => Value::from_dyn_object(CollectionItems::new(self.clone())),
+                "count" => self.listed_items().count().into(),
+                "first" => Value::from_dyn_object(self.listed_items().next()?.clone()),
+                "last" => Value::from_dyn_object(self.listed_items().next_back()?.clone()),
+                // Undefined rather than missing, so that strict undefined
+                // behavior allows `collection.parent is undefined`.
+                "parent" => match self.collection.parent(site) {
+                    Some(parent) => SiteCollection::value(site.clone(), parent.clone()),
+                    None => Value::UNDEFINED,
+                },
+                "children" => self.collection.children(site)
+                    .into_iter()
+                    .map(|child| SiteCollection::value(site.clone(), child.clone()))
+                    .collect(),
+                "feed" => {
+                    let id = self.collection.entry.id;
+                    let feed = site.feeds.iter().find(|feed| feed.collection == Some(id))?;
+                    HValue::from(feed.url.clone()).into()
+                }
+                _ => return self.collection.get_value(name),
+            })
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::Str(&[
+                "index", "items", "data", "merged_data", "count", "first", "last",
+                "parent", "children", "feed",
+            ])
         }
     }
 
+    // Drafts are left out of listings unless they're part of the build.
     impl Object for CollectionItems {
         fn get_value(self: &Arc<Self>, value: &Value) -> Option<Value> {
-            let item = self.items.get(value.as_usize()?)?;
+            let item = self.listed_items().nth(value.as_usize()?)?;
             Some(Value::from_dyn_object(item.clone()))
         }
 
         fn enumerate(self: &Arc<Self>) -> Enumerator {
-            Enumerator::Seq(List::len(&self.items))
+            Enumerator::Seq(self.listed_items().count())
         }
     }
 
@@ -519,7 +1201,7 @@ mod taxonomy_object {
         fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
             let name = key.as_str()?;
             let id = self.data.keys()
-                .find(|id| self.entry.tree[**id].file_stem() == name)?;
+                .find(|id| self.entry.tree[**id].file_stem_eq(name))?;
 
             let list = self.data.get(id)?.clone();
             Some(Value::from_dyn_object(list))
@@ -535,4 +1217,55 @@ mod taxonomy_object {
     }
 }
 
-impl_error_detail_with_std_error!(minijinja::Error);
+impl ErrorDetail for minijinja::Error {
+    fn context(&self) -> Vec<(Option<String>, String)> {
+        use std::error::Error as StdError;
+
+        let mut ctxt = vec![];
+        if let Some(name) = self.name() {
+            ctxt.push((Some("template".into()), name.to_string()));
+        }
+
+        if let Some(line) = self.line() {
+            ctxt.push((Some("line".into()), line.to_string()));
+        }
+
+        // minijinja only records a span for some instructions. Failing that,
+        // if an undefined value's line has exactly one `{{ }}` expression,
+        // it's taken to be the culprit.
+        let range = self.range().or_else(|| {
+            if self.kind() != minijinja::ErrorKind::UndefinedError {
+                return None;
+            }
+
+            let source = self.template_source()?;
+            let line = source.split_inclusive('\n').nth(self.line()?.checked_sub(1)?)?;
+            let line_start = line.as_ptr() as usize - source.as_ptr() as usize;
+            let (open, close) = (line.find("{{")?, line.find("}}")?);
+            if line[open + 2..].contains("{{") || close < open {
+                return None;
+            }
+
+            let inner = &line[open + 2..close];
+            let expr = inner.split('|').next()?.trim();
+            let start = line_start + open + 2 + inner.find(expr)?;
+            (!expr.is_empty()).then_some(start..start + expr.len())
+        });
+
+        if let (Some(range), Some(source)) = (range, self.template_source()) {
+            let line_start = source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+            let column = source[line_start..range.start].chars().count() + 1;
+            ctxt.push((Some("column".into()), column.to_string()));
+
+            if self.kind() == minijinja::ErrorKind::UndefinedError {
+                if let Some(expr) = source.get(range) {
+                    ctxt.push((Some("undefined".into()), expr.to_string()));
+                }
+            }
+        }
+
+        let error: &(dyn StdError + Send + Sync) = self;
+        ctxt.extend(error.context());
+        ctxt
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(feature = "plugins")]
 pub mod plugins;
 pub mod minijinja;
+#[cfg(feature = "tera")]
+pub mod tera;
 
 use std::fmt::Debug;
 use std::sync::Arc;
@@ -10,14 +12,38 @@ use serde::Serialize;
 use crate::error::Result;
 use crate::fstree::{EntryId, FsTree};
 use crate::taxonomy::{Site, Item, Collection, Metadata};
+use crate::value::Value;
 
 pub trait EngineInit {
     type Engine: Engine + 'static;
 
     fn init<G: Serialize>(tree: Arc<FsTree>, root: Option<EntryId>, globals: G) -> Self::Engine;
+
+    /// Like [`EngineInit::init()`], but the engine may memoize the output of
+    /// [`Engine::render_str()`] and [`Engine::render_value()`] for identical
+    /// templates and contexts. The cache lives as long as the engine. Renders
+    /// of items, whose context is the entire site, are never memoized.
+    ///
+    /// By default, this is [`EngineInit::init()`].
+    fn init_cached<G: Serialize>(
+        tree: Arc<FsTree>,
+        root: Option<EntryId>,
+        globals: G
+    ) -> Self::Engine {
+        Self::init(tree, root, globals)
+    }
 }
 
 pub trait Engine: Send + Sync + Debug {
+    /// Loads and compiles the template named `name` without rendering it.
+    ///
+    /// By default, this does nothing: errors in the template are reported
+    /// when it is first rendered.
+    fn compile(&self, name: &str) -> Result<()> {
+        let _ = name;
+        Ok(())
+    }
+
     fn render(
         &self,
         name: &str,
@@ -41,4 +67,32 @@ pub trait Engine: Send + Sync + Debug {
         template_str: &str,
         meta: Metadata,
     ) -> Result<String>;
+
+    /// Renders `template_str` with `context` as the templating context.
+    ///
+    /// Unlike [`Engine::render_str()`], `context` need not be item metadata.
+    /// This is useful for templatizing configuration strings and the like.
+    ///
+    /// By default, the entries of `context`, which must be a dictionary or
+    /// null, are rendered as metadata with [`Engine::render_str()`].
+    fn render_value(
+        &self,
+        name: Option<&str>,
+        template_str: &str,
+        context: Value,
+    ) -> Result<String> {
+        let meta = Metadata::new();
+        match context {
+            Value::Dict(dict) => for (key, value) in dict.iter() {
+                meta.insert_raw(key.clone(), value.clone());
+            },
+            Value::Null => {},
+            _ => return err! {
+                "template context must be a dictionary",
+                "found" => context.kind(),
+            },
+        }
+
+        self.render_str(name, template_str, meta)
+    }
 }
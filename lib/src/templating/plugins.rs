@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use either::Either;
@@ -6,7 +7,8 @@ use minijinja::value::Value as TemplateValue;
 use thread_local::ThreadLocal;
 
 use crate::fstree::FsTree;
-use crate::error::{Result, Error, ErrorDetail};
+use crate::error::{Result, Error, ErrorDetail, Chainable};
+use crate::markdown::Plugin;
 use crate::value::Source;
 
 pub struct LazyThreadLocal<T: Send> {
@@ -97,6 +99,55 @@ impl PluginContext {
         let value = value.try_into()?;
         Ok(value)
     }
+
+    /// Passes `input` through each markdown transform registered via
+    /// `harper.register_markdown()`, in registration order. A transform
+    /// receives markdown and returns its replacement or `nil` to keep it.
+    pub fn transform_markdown(&self, input: &str) -> Result<String> {
+        let transforms: Table = self.api()?.get("markdown")?;
+        let mut output = input.to_string();
+        for transform in transforms.sequence_values::<Table>() {
+            let transform = transform?;
+            let name: String = transform.get("name")?;
+            let func: Function = transform.get("func")?;
+            let result: Option<String> = func.call(output.as_str())
+                .chain_with(|| error! {
+                    "lua markdown transform failed",
+                    "transform" => name,
+                })?;
+
+            if let Some(result) = result {
+                output = result;
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// A markdown [`Plugin`] that preprocesses markdown with the transforms
+/// registered by Lua plugins. See [`PluginContext::transform_markdown()`].
+#[derive(Clone)]
+pub struct LuaMarkdown {
+    context: Arc<PluginContext>,
+}
+
+impl LuaMarkdown {
+    pub fn new(context: Arc<PluginContext>) -> Self {
+        LuaMarkdown { context }
+    }
+}
+
+impl std::fmt::Debug for LuaMarkdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LuaMarkdown").finish_non_exhaustive()
+    }
+}
+
+impl Plugin for LuaMarkdown {
+    fn preprocess<'a>(&self, input: &'a str) -> Result<Cow<'a, str>> {
+        Ok(Cow::Owned(self.context.transform_markdown(input)?))
+    }
 }
 
 pub fn lua(chunk: &str, name: &str) -> mlua::Result<Lua> {
@@ -108,6 +159,7 @@ pub fn lua(chunk: &str, name: &str) -> mlua::Result<Lua> {
             filters = {},
             functions = {},
             tests = {},
+            markdown = {},
         }
 
         function harper.register_filter(name, func)
@@ -121,6 +173,10 @@ pub fn lua(chunk: &str, name: &str) -> mlua::Result<Lua> {
         function harper.register_test(name, func)
             harper.tests[name] = func
         end
+
+        function harper.register_markdown(name, func)
+            table.insert(harper.markdown, { name = name, func = func })
+        end
     "#).exec()?;
 
     lua.load(&*chunk).set_name(&*name).exec()?;
@@ -133,7 +189,7 @@ pub fn init(tree: Arc<FsTree>) -> Result<Option<PluginContext>> {
         None => return Ok(None)
     };
 
-    let chunk = match file.path.as_ref().read()? {
+    let chunk = match file.read()? {
         Either::Left(string) => string,
         _ => return err!(
             "init.lua contained invalid UTF-8",
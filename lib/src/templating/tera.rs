@@ -0,0 +1,502 @@
+//! A [Tera](https://keats.github.io/tera/) implementation of [`Engine`].
+//!
+//! Templates see the same context as with [`MiniJinjaEngine`]: the item's
+//! metadata along with `site`, `collection`, `position`, `is_index`,
+//! `page_items`, `kind`, `group`, `next`, and `previous`, and the globals
+//! `G` and `menu`. As Tera contexts are plain data, the context is serialized
+//! up front, with a few differences:
+//!
+//!   * A collection's `parent` and `children` are collection names, so the
+//!     parent is `site.collections[collection.parent]`.
+//!   * The site is serialized once, on its first render, so templates see the
+//!     site as it was then.
+//!   * Functions take named arguments only, as is Tera's way:
+//!     `join(path="a/")`, `value | date(format="%Y")`, etc.
+//!
+//! As usual with Tera, HTML templates are autoescaped, so rendered content is
+//! written with `{{ content | safe }}`.
+//!
+//! [`MiniJinjaEngine`]: crate::templating::minijinja::MiniJinjaEngine
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value as Json};
+use tera::{Context, Tera};
+
+use crate::error::Result;
+use crate::fstree::{EntryId, FsTree};
+use crate::taxonomy::{Collection, Item, Kind, Metadata, Site};
+use crate::templating::minijinja::{SiteItem, INCLUDE_DIRS};
+use crate::templating::{Engine, EngineInit};
+use crate::value::{Dict, Source, Value};
+
+#[derive(Debug)]
+pub struct TeraEngine {
+    tera: Result<Tera>,
+    globals: Json,
+    /// The most recently rendered site, serialized.
+    site: Mutex<Option<(Arc<Site>, Arc<Json>)>>,
+}
+
+/// The name raw templates without a name are rendered under.
+const RAW_TEMPLATE: &str = "__harper_raw";
+
+fn try_init(tree: &FsTree, root: Option<EntryId>, globals: &Json) -> Result<Tera> {
+    let mut tera = Tera::default();
+
+    // Partials in the include directories are also found by their name
+    // relative to the directory, so `{% include "nav.html" %}` can find
+    // `includes/nav.html`, unless a template by that name exists.
+    let mut templates: Vec<(String, String)> = vec![];
+    if let Some(root) = root {
+        for (id, path) in tree.iter_relative(root) {
+            if !tree[id].file_type.is_file() {
+                continue;
+            }
+
+            let name = path.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let source: Arc<str> = tree[id].try_read()?;
+            templates.push((name, source.to_string()));
+        }
+
+        let partials: Vec<_> = templates.iter()
+            .filter_map(|(name, source)| {
+                let (dir, rest) = name.split_once('/')?;
+                INCLUDE_DIRS.contains(&dir).then(|| (rest.to_string(), source.clone()))
+            })
+            .collect();
+
+        for (name, source) in partials {
+            if !templates.iter().any(|(n, _)| *n == name) {
+                templates.push((name, source));
+            }
+        }
+    }
+
+    tera.add_raw_templates(templates)?;
+
+    let root = globals.get("root").and_then(|root| root.as_str()).map(String::from);
+    let timezone = globals.get("timezone").and_then(|tz| tz.as_str()).map(String::from);
+    tera.register_function("join", move |args: &HashMap<String, Json>| ext::join(root.as_deref(), args));
+    tera.register_function("now", ext::now);
+    tera.register_filter("deslug", ext::deslug);
+    tera.register_filter("date", move |value: &Json, args: &HashMap<String, Json>| {
+        ext::date(value, args, timezone.as_deref())
+    });
+
+    tera.register_filter("split", ext::split);
+    tera.register_filter("get", ext::get);
+    Ok(tera)
+}
+
+impl EngineInit for TeraEngine {
+    type Engine = Self;
+
+    fn init<G: Serialize>(tree: Arc<FsTree>, root: Option<EntryId>, globals: G) -> Self::Engine {
+        let globals = serde_json::to_value(globals).unwrap_or_default();
+        TeraEngine {
+            tera: try_init(&tree, root, &globals),
+            globals,
+            site: Mutex::default(),
+        }
+    }
+}
+
+impl TeraEngine {
+    fn tera(&self) -> Result<&Tera> {
+        self.tera.as_ref().map_err(|e| e.clone())
+    }
+
+    /// A context of the fields of `map`, a dictionary, and the globals.
+    fn context(&self, mut map: Map<String, Json>) -> Result<Context> {
+        if let Some(menu) = self.globals.get("menu").filter(|menu| !menu.is_null()) {
+            map.entry("menu").or_insert_with(|| menu.clone());
+        }
+
+        map.insert("G".into(), self.globals.clone());
+        Ok(Context::from_value(Json::Object(map))?)
+    }
+
+    /// The context of `item` in `collection` of `site`. See the module docs.
+    fn item_context(
+        &self,
+        site: &Arc<Site>,
+        collection: Option<&Arc<Collection>>,
+        item: &Arc<Item>,
+    ) -> Result<Context> {
+        let site_json = self.site_json(site)?;
+        let site_item = SiteItem {
+            site: site.clone(),
+            collection: collection.cloned(),
+            item: item.clone(),
+        };
+
+        let mut map = metadata(&item.metadata)?;
+        map.insert("site".into(), (*site_json).clone());
+        map.insert("is_index".into(), site_item.is_index().into());
+        if let Some(position) = site_item.position() {
+            map.insert("position".into(), position.into());
+        }
+
+        if let Some(collection) = collection {
+            let json = site_json["collections"].get(&*collection.name).cloned();
+            map.insert("collection".into(), json.unwrap_or_default());
+            map.insert("page_items".into(), page_items(site, collection, item)?);
+        }
+
+        let kind = match site_item.kind() {
+            Some(Kind::Index) => Some("index"),
+            Some(Kind::Item(_)) => Some("item"),
+            Some(Kind::Datum(_)) => Some("datum"),
+            None => None,
+        };
+
+        if let Some(kind) = kind {
+            map.entry("kind").or_insert_with(|| kind.into());
+        }
+
+        if let Some(Kind::Datum(group)) = site_item.kind() {
+            map.entry("group").or_insert_with(|| site.tree[group].file_stem().into());
+        }
+
+        // Navigation skips drafts, unless they're part of the build.
+        if let Some(collection) = collection {
+            let next = match site_item.is_index() {
+                true => Some(0),
+                false => site_item.position().map(|i| i.saturating_add(1)),
+            };
+
+            let next = next.and_then(|j| collection.items.iter().skip(j).find(|item| site.is_listed(item)));
+            if let Some(next) = next {
+                map.insert("next".into(), metadata(&next.metadata)?.into());
+            }
+
+            let previous = site_item.position().and_then(|i| {
+                collection.items.iter().take(i).rev()
+                    .find(|item| site.is_listed(item))
+                    .or(collection.index.as_ref())
+            });
+
+            if let Some(previous) = previous {
+                map.insert("previous".into(), metadata(&previous.metadata)?.into());
+            }
+        }
+
+        self.context(map)
+    }
+
+    /// `site` as seen by templates, serialized on its first render.
+    fn site_json(&self, site: &Arc<Site>) -> Result<Arc<Json>> {
+        let mut cache = self.site.lock().unwrap();
+        if let Some((cached, json)) = &*cache {
+            if Arc::ptr_eq(cached, site) {
+                return Ok(json.clone());
+            }
+        }
+
+        let mut collections = Map::new();
+        for collection in site.collections.values() {
+            let published: Vec<_> = collection.items.iter().filter(|item| site.is_listed(item)).collect();
+            let items = published.iter()
+                .map(|item| metadata(&item.metadata).map(Json::Object))
+                .collect::<Result<Vec<_>>>()?;
+
+            let mut data = Map::new();
+            for (id, list) in &collection.data {
+                let list = list.iter()
+                    .map(|item| metadata(&item.metadata).map(Json::Object))
+                    .collect::<Result<Vec<_>>>()?;
+
+                data.insert(site.tree[*id].file_stem().into(), list.into());
+            }
+
+            let feed = site.feeds.iter()
+                .find(|feed| feed.collection == Some(collection.entry.id))
+                .map(|feed| Value::from(feed.url.clone()));
+
+            let json = json!({
+                "name": &*collection.name,
+                "index": collection.index.as_ref().map(|index| metadata(&index.metadata)).transpose()?,
+                "count": items.len(),
+                "first": items.first(),
+                "last": items.last(),
+                "items": items,
+                "data": data,
+                "merged_data": serde_json::to_value(collection.merged_data())?,
+                "parent": collection.parent(site).map(|parent| &*parent.name),
+                "children": collection.children(site).iter().map(|c| &*c.name).collect::<Vec<_>>(),
+                "feed": serde_json::to_value(feed)?,
+            });
+
+            collections.insert(collection.name.to_string(), json);
+        }
+
+        let items = site.items.iter()
+            .map(|item| metadata(&item.metadata).map(Json::Object))
+            .collect::<Result<Vec<_>>>()?;
+
+        let feeds: Value = site.feeds.iter()
+            .map(|feed| {
+                let collection = feed.collection
+                    .and_then(|id| site.collections.get(&id))
+                    .map(|collection| collection.name.clone());
+
+                Value::from(Dict::<&str>::from([
+                    ("url", Value::from(feed.url.clone())),
+                    ("type", feed.format.media_type().into()),
+                    ("collection", collection.into()),
+                ]))
+            })
+            .collect();
+
+        let json = Arc::new(json!({
+            "items": items,
+            "collections": collections,
+            "feeds": serde_json::to_value(feeds)?,
+        }));
+
+        *cache = Some((site.clone(), json.clone()));
+        Ok(json)
+    }
+
+    /// Renders `template_str` as the template `name`, or as a nameless one,
+    /// alongside the loaded templates.
+    fn render_template_str(&self, name: Option<&str>, template_str: &str, context: &Context) -> Result<String> {
+        let mut tera = self.tera()?.clone();
+        let name = name.unwrap_or(RAW_TEMPLATE);
+        tera.add_raw_template(name, template_str)?;
+        Ok(tera.render(name, context)?)
+    }
+}
+
+impl Engine for TeraEngine {
+    fn compile(&self, name: &str) -> Result<()> {
+        match self.tera()?.get_template_names().any(|n| n == name) {
+            true => Ok(()),
+            false => err!("template not found", "template" => name),
+        }
+    }
+
+    fn render(
+        &self,
+        name: &str,
+        site: &Arc<Site>,
+        collection: Option<&Arc<Collection>>,
+        item: &Arc<Item>,
+    ) -> Result<String> {
+        let context = self.item_context(site, collection, item)?;
+        Ok(self.tera()?.render(name, &context)?)
+    }
+
+    fn render_raw(
+        &self,
+        name: Option<&str>,
+        template_str: &str,
+        site: &Arc<Site>,
+        collection: Option<&Arc<Collection>>,
+        item: &Arc<Item>,
+    ) -> Result<String> {
+        let context = self.item_context(site, collection, item)?;
+        self.render_template_str(name, template_str, &context)
+    }
+
+    fn render_str(
+        &self,
+        name: Option<&str>,
+        template_str: &str,
+        meta: Metadata,
+    ) -> Result<String> {
+        let context = self.context(metadata(&meta)?)?;
+        self.render_template_str(name, template_str, &context)
+    }
+
+    fn render_value(
+        &self,
+        name: Option<&str>,
+        template_str: &str,
+        context: Value,
+    ) -> Result<String> {
+        let map = match serde_json::to_value(&context)? {
+            Json::Object(map) => map,
+            Json::Null => Map::new(),
+            _ => return err! {
+                "template context must be a dictionary",
+                "found" => context.kind(),
+            },
+        };
+
+        let context = self.context(map)?;
+        self.render_template_str(name, template_str, &context)
+    }
+}
+
+/// `metadata` as a JSON object.
+fn metadata(metadata: &Metadata) -> Result<Map<String, Json>> {
+    match serde_json::to_value(metadata.to_value())? {
+        Json::Object(map) => Ok(map),
+        _ => Ok(Map::new()),
+    }
+}
+
+/// The listed items of `collection` that `item`, a paginated index, lists on
+/// its `page`, or all of them. See `page_items` in [`MiniJinjaEngine`].
+///
+/// [`MiniJinjaEngine`]: crate::templating::minijinja::MiniJinjaEngine
+fn page_items(site: &Site, collection: &Collection, item: &Item) -> Result<Json> {
+    let meta = |key| item.metadata.get_raw(key).and_then(|v| usize::try_from(v).ok());
+    let (skip, take) = match (meta("paginate").filter(|&n| n > 0), meta("page")) {
+        (Some(n), Some(page)) => (page.saturating_sub(1).saturating_mul(n), n),
+        _ => (0, usize::MAX),
+    };
+
+    let items = collection.items.iter()
+        .filter(|item| site.is_listed(item))
+        .skip(skip)
+        .take(take)
+        .map(|item| metadata(&item.metadata).map(Json::Object))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(items.into())
+}
+
+/// Tera versions of the MiniJinja engine's functions and filters.
+mod ext {
+    use std::collections::HashMap;
+
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+    use chrono_tz::Tz;
+    use serde_json::Value as Json;
+    use tera::{Error, Result};
+
+    use crate::url::Url;
+
+    fn str_arg<'a>(args: &'a HashMap<String, Json>, name: &str, function: &str) -> Result<Option<&'a str>> {
+        match args.get(name) {
+            Some(value) => value.as_str()
+                .map(Some)
+                .ok_or_else(|| Error::msg(format!("`{function}` expects `{name}` to be a string"))),
+            None => Ok(None),
+        }
+    }
+
+    /// `join(path="a/")` or `join(paths=["a/", "b"])`: the URL of the paths
+    /// appended, in order, to `G.root`.
+    pub fn join(root: Option<&str>, args: &HashMap<String, Json>) -> Result<Json> {
+        let mut url = root.and_then(Url::try_new)
+            .ok_or_else(|| Error::msg("G.root must be a URL"))?
+            .to_url_buf();
+
+        let paths = match (args.get("path"), args.get("paths")) {
+            (Some(path), None) => vec![path],
+            (None, Some(Json::Array(paths))) => paths.iter().collect(),
+            _ => return Err(Error::msg("`join` expects either a `path` or a `paths` list")),
+        };
+
+        for path in paths {
+            let segment = path.as_str()
+                .and_then(Url::try_new)
+                .ok_or_else(|| Error::msg(format!("`join` argument {path} isn't a valid URL")))?;
+
+            url.append(segment);
+        }
+
+        Ok(Json::String(url.into()))
+    }
+
+    /// `now()`: the current instant as an RFC 3339 string, which `date`
+    /// formats, or, with `timestamp=true`, as a unix timestamp.
+    pub fn now(args: &HashMap<String, Json>) -> Result<Json> {
+        let now = Utc::now();
+        match args.get("timestamp").and_then(Json::as_bool) {
+            Some(true) => Ok(now.timestamp().into()),
+            _ => Ok(now.to_rfc3339().into()),
+        }
+    }
+
+    pub fn deslug(value: &Json, _: &HashMap<String, Json>) -> Result<Json> {
+        let string = value.as_str().ok_or_else(|| Error::msg("`deslug` must be applied to a string"))?;
+        Ok(string.replace('-', " ").into())
+    }
+
+    /// `value | date(format="%Y", tz="Europe/Paris")`: `value`, a unix
+    /// timestamp, a TOML datetime, or a date, time, or datetime string,
+    /// formatted. Instants are shown in `tz`, else `default_tz`, else UTC.
+    pub fn date(value: &Json, args: &HashMap<String, Json>, default_tz: Option<&str>) -> Result<Json> {
+        let format = str_arg(args, "format", "date")?
+            .ok_or_else(|| Error::msg("`date` expects a `format` argument"))?;
+
+        let tz = match str_arg(args, "tz", "date")?.or(default_tz) {
+            Some(name) => name.parse::<Tz>()
+                .map_err(|e| Error::msg(format!("invalid timezone {name}: {e}")))?,
+            None => Tz::UTC,
+        };
+
+        // Fractional timestamps keep their sub-second part.
+        let timestamp = match (value.as_i64(), value.as_f64()) {
+            (Some(secs), _) => Some((secs, 0)),
+            (None, Some(ts)) if ts.is_finite() && ts.abs() < i64::MAX as f64 => {
+                Some((ts.floor() as i64, ((ts - ts.floor()) * 1e9) as u32))
+            }
+            _ => None,
+        };
+
+        if let Some((secs, nanos)) = timestamp {
+            let datetime = DateTime::from_timestamp(secs, nanos)
+                .ok_or_else(|| Error::msg("invalid timestamp provided to `date`"))?;
+
+            return Ok(datetime.with_timezone(&tz).format(format).to_string().into());
+        }
+
+        let string = value.get("$__toml_private_datetime")
+            .unwrap_or(value)
+            .as_str()
+            .ok_or_else(|| Error::msg("`date` must be applied to a string or integer"))?;
+
+        let string = if let Ok(d) = string.parse::<NaiveDate>() {
+            d.format(format).to_string()
+        } else if let Ok(t) = string.parse::<NaiveTime>() {
+            t.format(format).to_string()
+        } else if let Ok(dt) = string.parse::<NaiveDateTime>() {
+            dt.format(format).to_string()
+        } else {
+            let dt = string.parse::<DateTime<Utc>>()
+                .map_err(|e| Error::msg(format!("failed to parse {string}: {e}")))?;
+
+            dt.with_timezone(&tz).format(format).to_string()
+        };
+
+        Ok(string.into())
+    }
+
+    /// `value | split(pat=",")`: the parts of `value` split on `pat` or, with
+    /// `n=i`, only the `i`th part, or null if there isn't one.
+    pub fn split(value: &Json, args: &HashMap<String, Json>) -> Result<Json> {
+        let string = value.as_str().ok_or_else(|| Error::msg("`split` must be applied to a string"))?;
+        let pat = str_arg(args, "pat", "split")?
+            .ok_or_else(|| Error::msg("`split` expects a `pat` argument"))?;
+
+        match args.get("n").map(Json::as_u64) {
+            Some(Some(n)) => Ok(string.split(pat).nth(n as usize).map(Json::from).unwrap_or_default()),
+            Some(None) => Err(Error::msg("`split` expects `n` to be a non-negative integer")),
+            None => Ok(string.split(pat).collect::<Vec<_>>().into()),
+        }
+    }
+
+    /// `map | get(key="k", default=v)`: the value of `key` in `map`, or
+    /// `default`, or null, if it's missing.
+    pub fn get(value: &Json, args: &HashMap<String, Json>) -> Result<Json> {
+        let key = str_arg(args, "key", "get")?
+            .ok_or_else(|| Error::msg("`get` expects a `key` argument"))?;
+
+        let default = args.get("default").cloned().unwrap_or_default();
+        Ok(value.get(key).cloned().unwrap_or(default))
+    }
+}
+
+impl_error_detail_with_std_error!(tera::Error);
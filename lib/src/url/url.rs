@@ -3,6 +3,7 @@ use std::borrow::Borrow;
 use std::sync::Arc;
 
 pub use super::{UrlBuf, is_url_char};
+use crate::error::Result;
 
 #[derive(Debug)]
 #[repr(transparent)]
@@ -24,6 +25,29 @@ impl Url {
         Some(unsafe { &*(from as *const str as *const Url) })
     }
 
+    /// Like [`Url::try_new()`] but returns an error naming the first character
+    /// in `from` that isn't allowed in a URL.
+    ///
+    /// ```rust
+    /// use harper::url::Url;
+    ///
+    /// assert_eq!(Url::parse("/docs/intro").unwrap().as_str(), "/docs/intro");
+    /// assert!(Url::parse("/my page").is_err());
+    /// assert!(Url::parse("/café").is_err());
+    /// ```
+    pub fn parse(from: &str) -> Result<&Url> {
+        match from.bytes().position(|b| !is_url_char(&b)) {
+            // Every non-ASCII byte is invalid, so `i` is at a char boundary.
+            Some(i) => err! {
+                "invalid URL",
+                "url" => from,
+                "reason" => format!("{:?} isn't allowed in URLs", from[i..].chars().next().unwrap()),
+                "help" => "percent-encode the character",
+            },
+            None => Ok(Url::new(from)),
+        }
+    }
+
     pub fn from(arc: Arc<str>) -> Arc<Url> {
         Self::try_from(arc).expect("invalid URL")
     }
@@ -56,6 +80,10 @@ impl Url {
         let mut url = self.as_str();
         if let Some(scheme) = self.scheme() {
             url = &url[scheme.len() + 1..];
+            // Drop the authority, e.g. the `//rocket.rs` of `//rocket.rs/foo`.
+            if let Some(rest) = url.strip_prefix("//") {
+                url = rest.find('/').map_or("", |i| &rest[i..]);
+            }
         }
 
         url = url.trim_start_matches('/');
@@ -144,12 +172,14 @@ impl AsRef<str> for Url {
     }
 }
 
+/// Panics if `self` isn't a valid URL. See [`Url::parse()`].
 impl AsRef<Url> for str {
     fn as_ref(&self) -> &Url {
         Url::new(self)
     }
 }
 
+/// Panics if `self` isn't a valid URL. See [`Url::parse()`].
 impl AsRef<Url> for Arc<str> {
     fn as_ref(&self) -> &Url {
         Url::new(&*self)
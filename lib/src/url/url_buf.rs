@@ -1,13 +1,14 @@
 use std::ops::Deref;
 use std::borrow::Borrow;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::sync::Arc;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 pub use super::Url;
+use super::{is_url_char, PATH_CHARS};
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
 #[repr(transparent)]
 #[serde(transparent)]
 pub struct UrlBuf(String);
@@ -43,7 +44,25 @@ impl UrlBuf {
     /// url.prepend("/bar/baz");
     /// assert_eq!(url.as_str(), "https://rocket.rs/bar/baz/foo/bar");
     /// ```
-    // FIXME: Deal with query and hash, in `self` and `url`.
+    ///
+    /// The query and fragment of `url` and `self` are kept as described in
+    /// [`UrlBuf::append()`], with `self` appended to `url`:
+    ///
+    /// ```rust
+    /// use harper::url::UrlBuf;
+    ///
+    /// let mut url = UrlBuf::from("baz");
+    /// url.prepend("https://site.com/page?foo=bar");
+    /// assert_eq!(url.as_str(), "https://site.com/page/baz?foo=bar");
+    ///
+    /// let mut url = UrlBuf::from("baz#top");
+    /// url.prepend("/page?foo=bar");
+    /// assert_eq!(url.as_str(), "/page/baz?foo=bar#top");
+    ///
+    /// let mut url = UrlBuf::from("baz?q=1#top");
+    /// url.prepend("/page?foo=bar#end");
+    /// assert_eq!(url.as_str(), "/page/baz?q=1#top");
+    /// ```
     pub fn prepend<T: AsRef<Url>>(&mut self, url: T) -> &mut Self {
         if self.scheme().is_some() {
             return self;
@@ -76,22 +95,67 @@ impl UrlBuf {
     /// url.append("/");
     /// assert_eq!(url.as_str(), "/foo/bar/baz/");
     /// ```
-    // FIXME: Deal with query and hash, in `self` and `url`.
+    ///
+    /// Only the paths are joined. The query and fragment of `self`, if any,
+    /// are kept at the end unless `url` replaces them: a query in `url`
+    /// replaces both, and a fragment in `url` replaces the fragment.
+    ///
+    /// ```rust
+    /// use harper::url::UrlBuf;
+    ///
+    /// let mut url = UrlBuf::from("https://site.com/page?foo=bar");
+    /// url.append("baz");
+    /// assert_eq!(url.as_str(), "https://site.com/page/baz?foo=bar");
+    ///
+    /// url.append("?q=1");
+    /// assert_eq!(url.as_str(), "https://site.com/page/baz?q=1");
+    ///
+    /// let mut url = UrlBuf::from("/page#top");
+    /// url.append("baz/");
+    /// assert_eq!(url.as_str(), "/page/baz/#top");
+    ///
+    /// url.append("#end");
+    /// assert_eq!(url.as_str(), "/page/baz/#end");
+    ///
+    /// let mut url = UrlBuf::from("/page?foo=bar#top");
+    /// url.append("/baz");
+    /// assert_eq!(url.as_str(), "/page/baz?foo=bar#top");
+    ///
+    /// url.append("qux?q=1#end");
+    /// assert_eq!(url.as_str(), "/page/baz/qux?q=1#end");
+    /// ```
     pub fn append<T: AsRef<Url>>(&mut self, url: T) -> &mut Self {
         let url = url.as_ref();
         if url.scheme().is_some() {
             *self = url.to_owned();
-        } else {
-            match (self.ends_with('/'), url.starts_with('/')) {
-                (true, true) => self.0.push_str(&url[1..]),
-                (true, false) | (false, true) => self.0.push_str(&*url),
+            return self;
+        }
+
+        let (base, base_query, base_fragment) = split_url(self.as_str());
+        let (path, url_query, url_fragment) = split_url(url.as_str());
+        let (query, fragment) = match (url_query.is_empty(), url_fragment.is_empty()) {
+            (false, _) => (url_query, url_fragment),
+            (true, false) => (base_query, url_fragment),
+            (true, true) => (base_query, base_fragment),
+        };
+
+        // A bare `?query` or `#fragment` leaves the path as it is.
+        let mut string = String::with_capacity(self.len() + url.len() + 1);
+        string.push_str(base);
+        if !path.is_empty() || (url_query.is_empty() && url_fragment.is_empty()) {
+            match (base.ends_with('/'), path.starts_with('/')) {
+                (true, true) => string.push_str(&path[1..]),
+                (true, false) | (false, true) => string.push_str(path),
                 (false, false) => {
-                    self.0.push('/');
-                    self.0.push_str(&*url);
+                    string.push('/');
+                    string.push_str(path);
                 }
             }
         }
 
+        string.push_str(query);
+        string.push_str(fragment);
+        self.0 = string;
         self
     }
 
@@ -103,6 +167,63 @@ impl UrlBuf {
         self
     }
 
+    /// Like [`UrlBuf::append()`], but appends the path `path`, percent-encoding
+    /// the bytes of each `/`-separated segment that aren't path characters.
+    /// `%` and `:` are encoded, too, so `path` is never read as an escape or
+    /// a URL with a scheme.
+    ///
+    /// ```rust
+    /// use harper::url::UrlBuf;
+    ///
+    /// let mut url = UrlBuf::from("https://rocket.rs");
+    /// url.append_encoded("bar baz");
+    /// assert_eq!(url.as_str(), "https://rocket.rs/bar%20baz");
+    ///
+    /// url.append_encoded("/café/why?/");
+    /// assert_eq!(url.as_str(), "https://rocket.rs/bar%20baz/caf%C3%A9/why%3F/");
+    ///
+    /// url.append_encoded("https://rwf2.org/100%");
+    /// assert_eq!(url.as_str(), "https://rocket.rs/bar%20baz/caf%C3%A9/why%3F/https%3A//rwf2.org/100%25");
+    ///
+    /// let mut url = UrlBuf::from("/foo/bar");
+    /// url.append_encoded("日本 語");
+    /// assert_eq!(url.as_str(), "/foo/bar/%E6%97%A5%E6%9C%AC%20%E8%AA%9E");
+    ///
+    /// url.append_encoded("/");
+    /// assert_eq!(url.as_str(), "/foo/bar/%E6%97%A5%E6%9C%AC%20%E8%AA%9E/");
+    /// ```
+    pub fn append_encoded<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
+        let path = path.as_ref();
+        let mut encoded = String::with_capacity(path.len());
+        for (i, segment) in path.split('/').enumerate() {
+            if i != 0 {
+                encoded.push('/');
+            }
+
+            push_encoded_segment(&mut encoded, segment);
+        }
+
+        self.append(UrlBuf(encoded))
+    }
+
+    /// Like [`UrlBuf::extend()`], but appends each path with
+    /// [`UrlBuf::append_encoded()`].
+    ///
+    /// ```rust
+    /// use harper::url::UrlBuf;
+    ///
+    /// let mut url = UrlBuf::from("/blog");
+    /// url.extend_encoded(["my post", "ünïcode", "a#b"]);
+    /// assert_eq!(url.as_str(), "/blog/my%20post/%C3%BCn%C3%AFcode/a%23b");
+    /// ```
+    pub fn extend_encoded<S: AsRef<str>, I: IntoIterator<Item = S>>(&mut self, iter: I) -> &mut Self {
+        for path in iter.into_iter() {
+            self.append_encoded(path);
+        }
+
+        self
+    }
+
     pub fn make_absolute(&mut self) -> &mut Self {
         self.prepend("/");
         self
@@ -130,14 +251,51 @@ impl UrlBuf {
 }
 
 impl From<String> for UrlBuf {
+    /// Converts `value` into a URL, percent-encoding any bytes that aren't
+    /// allowed in one. Use [`Url::parse()`] to reject such strings instead.
+    ///
+    /// ```rust
+    /// use harper::url::UrlBuf;
+    ///
+    /// let url = UrlBuf::from("/my page/café".to_string());
+    /// assert_eq!(url.as_str(), "/my%20page/caf%C3%A9");
+    ///
+    /// let url = UrlBuf::from("https://rocket.rs/a%20b?q#x".to_string());
+    /// assert_eq!(url.as_str(), "https://rocket.rs/a%20b?q#x");
+    /// ```
     fn from(value: String) -> Self {
-        UrlBuf(value)
+        if Url::try_new(&value).is_some() {
+            return UrlBuf(value);
+        }
+
+        let mut string = String::with_capacity(value.len());
+        for &byte in value.as_bytes() {
+            match is_url_char(&byte) {
+                true => string.push(byte as char),
+                false => push_encoded_byte(&mut string, byte),
+            }
+        }
+
+        UrlBuf(string)
     }
 }
 
 impl From<&str> for UrlBuf {
     fn from(value: &str) -> Self {
-        Url::new(value).to_url_buf()
+        match Url::try_new(value) {
+            Some(url) => url.to_url_buf(),
+            None => UrlBuf::from(value.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for UrlBuf {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(de)?;
+        match Url::parse(&string) {
+            Ok(_) => Ok(UrlBuf(string)),
+            Err(e) => Err(serde::de::Error::custom(e)),
+        }
     }
 }
 
@@ -148,27 +306,88 @@ impl From<&Url> for UrlBuf {
 }
 
 impl From<&Path> for UrlBuf {
+    /// Converts `value` into a URL path, resolving `.` and `..` components
+    /// and percent-encoding each remaining component.
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use harper::url::{Url, UrlBuf};
+    ///
+    /// let url = UrlBuf::from(Path::new("/docs/./old/../my page.html"));
+    /// assert_eq!(url.as_str(), "/docs/my%20page.html");
+    ///
+    /// let url = UrlBuf::from(Path::new("c#/100%/q?.md"));
+    /// assert_eq!(url.as_str(), "c%23/100%25/q%3F.md");
+    ///
+    /// let url = UrlBuf::from(Path::new("café/日本"));
+    /// assert_eq!(url.as_str(), "caf%C3%A9/%E6%97%A5%E6%9C%AC");
+    ///
+    /// let url = UrlBuf::from(Path::new("a:b/c"));
+    /// assert_eq!(url.as_str(), "a%3Ab/c");
+    /// assert_eq!(url.scheme(), None);
+    /// assert!(Url::try_new(url.as_str()).is_some());
+    /// ```
     fn from(value: &Path) -> Self {
         use std::path::Component;
 
-        let mut sane_path = PathBuf::new();
+        let mut absolute = false;
+        let mut segments = vec![];
         for component in value.components() {
             match component {
                 Component::Prefix(_) | Component::CurDir => continue,
-                Component::RootDir => sane_path.push(component),
-                Component::ParentDir => { sane_path.pop(); },
-                Component::Normal(v) => match v.to_str() {
-                    Some(v) => sane_path.push(v),
-                    None => sane_path.push(v.to_string_lossy().to_string())
-                }
+                Component::RootDir => absolute = true,
+                Component::ParentDir => { segments.pop(); },
+                Component::Normal(v) => segments.push(v.to_string_lossy()),
             }
         }
 
-        let string = sane_path.into_os_string().into_string();
-        UrlBuf(string.expect("should contain only UTF-8"))
+        let mut string = String::new();
+        if absolute {
+            string.push('/');
+        }
+
+        for (i, segment) in segments.iter().enumerate() {
+            if i != 0 {
+                string.push('/');
+            }
+
+            push_encoded_segment(&mut string, segment);
+        }
+
+        UrlBuf(string)
     }
 }
 
+/// Splits `url` into its path, `?query`, and `#fragment`. The query and
+/// fragment keep their delimiters and are empty if they're missing.
+fn split_url(url: &str) -> (&str, &str, &str) {
+    let (rest, fragment) = url.find('#').map_or((url, ""), |i| url.split_at(i));
+    let (path, query) = rest.find('?').map_or((rest, ""), |i| rest.split_at(i));
+    (path, query, fragment)
+}
+
+/// Pushes `segment` to `string`, percent-encoding every byte that isn't a
+/// path character. `/`, `%`, and `:` are encoded as well since they would
+/// otherwise be read as a separator, an escape, or a scheme delimiter.
+fn push_encoded_segment(string: &mut String, segment: &str) {
+    for &byte in segment.as_bytes() {
+        if PATH_CHARS[byte as usize] != 0 && !matches!(byte, b'/' | b'%' | b':') {
+            string.push(byte as char);
+        } else {
+            push_encoded_byte(string, byte);
+        }
+    }
+}
+
+/// Pushes `byte` to `string` percent-encoded as `%XX`.
+fn push_encoded_byte(string: &mut String, byte: u8) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    string.push('%');
+    string.push(HEX[(byte >> 4) as usize] as char);
+    string.push(HEX[(byte & 0xF) as usize] as char);
+}
+
 impl Deref for UrlBuf {
     type Target = Url;
 
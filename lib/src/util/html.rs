@@ -0,0 +1,177 @@
+/// Tags that, when stripped, separate the text around them.
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt",
+    "figcaption", "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "hr", "li", "main", "nav", "ol", "p", "pre", "section", "table",
+    "td", "th", "tr", "ul",
+];
+
+/// Removes all HTML tags and comments from `html`, decodes entities, and
+/// collapses runs of whitespace into a single space.
+///
+/// Malformed markup is handled leniently: a `<` that doesn't begin a tag, or
+/// a tag that is never closed, is retained as text.
+///
+/// ```
+/// use harper::util::strip_tags;
+///
+/// assert_eq!(strip_tags("<p>Fish &amp; <em>Chips</em></p>\n<p>Peas</p>"), "Fish & Chips Peas");
+/// assert_eq!(strip_tags("1 < 2 <b"), "1 < 2 <b");
+/// ```
+pub fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find('<') {
+        decode_entities_into(&mut text, &rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            match comment.find("-->") {
+                Some(j) => rest = &comment[(j + 3)..],
+                None => rest = "",
+            }
+
+            continue;
+        }
+
+        let is_tag = rest[1..].starts_with(|c: char| {
+            c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?'
+        });
+
+        match rest.find('>') {
+            Some(j) if is_tag => {
+                let name = rest[1..j].trim_start_matches('/');
+                let name_len = name.find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(name.len());
+
+                if BLOCK_TAGS.iter().any(|tag| tag.eq_ignore_ascii_case(&name[..name_len])) {
+                    text.push(' ');
+                }
+
+                rest = &rest[(j + 1)..];
+            }
+            _ => {
+                text.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decode_entities_into(&mut text, rest);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Escapes `&`, `<`, `>`, `"`, and `'` in `text` so that it can be safely
+/// embedded in HTML text or attribute values.
+///
+/// ```
+/// use harper::util::escape_html;
+///
+/// assert_eq!(escape_html("Fish & <Chips>"), "Fish &amp; &lt;Chips&gt;");
+/// assert_eq!(escape_html("plain"), "plain");
+/// ```
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Decodes HTML entities in `string`, pushing the result into `output`.
+/// Unknown or malformed entities are pushed as is.
+fn decode_entities_into(output: &mut String, string: &str) {
+    let mut rest = string;
+    while let Some(i) = rest.find('&') {
+        output.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let decoded = rest[1..].find(';')
+            .filter(|&j| j <= 10)
+            .and_then(|j| Some((decode_entity(&rest[1..(j + 1)])?, j + 2)));
+
+        match decoded {
+            Some((c, len)) => {
+                output.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "copy" => '©',
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+
+            char::from_u32(code)?
+        }
+    };
+
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_tags;
+
+    #[test]
+    fn test_strip_tags() {
+        assert_eq!(strip_tags(""), "");
+        assert_eq!(strip_tags("plain"), "plain");
+        assert_eq!(strip_tags("<p>a</p><p>b</p>"), "a b");
+        assert_eq!(strip_tags("a<em>b</em>c"), "abc");
+        assert_eq!(strip_tags("<a href=\"/x\" title='y'>link</a>"), "link");
+        assert_eq!(strip_tags("a<!-- <p>hidden</p> -->b"), "ab");
+        assert_eq!(strip_tags("  lots \n\n of\t space  "), "lots of space");
+    }
+
+    #[test]
+    fn test_strip_tags_entities() {
+        assert_eq!(strip_tags("Fish &amp; Chips"), "Fish & Chips");
+        assert_eq!(strip_tags("&lt;p&gt;"), "<p>");
+        assert_eq!(strip_tags("&#39;&#x27;&quot;"), "''\"");
+        assert_eq!(strip_tags("&unknown; & &amp"), "&unknown; & &amp");
+    }
+
+    #[test]
+    fn test_strip_tags_malformed() {
+        assert_eq!(strip_tags("1 < 2"), "1 < 2");
+        assert_eq!(strip_tags("a <b"), "a <b");
+        assert_eq!(strip_tags("a <!-- unterminated"), "a");
+        assert_eq!(strip_tags("<<p>>"), "< >");
+        assert_eq!(strip_tags("<p>unclosed <em>tags"), "unclosed tags");
+    }
+}
@@ -2,6 +2,7 @@ mod variation;
 mod macros;
 mod path_ext;
 mod lazy_result;
+mod html;
 
 pub mod hlist;
 
@@ -9,39 +10,201 @@ pub use path_ext::*;
 pub use macros::*;
 pub use lazy_result::*;
 pub use variation::*;
+pub use html::*;
 
+use std::borrow::Cow;
 use std::path::{Path, PathBuf, Component};
 
 /// Convert spaces to hyphens. Remove characters that aren't alphanumerics,
 /// underscores, or hyphens. Convert to lowercase. Also strip leading and
 /// trailing whitespace.
+///
+/// This is [`slugify_with()`] with the default [`SlugOptions`].
 pub fn slugify(string: &str) -> String {
+    slugify_with(string, &SlugOptions::default())
+}
+
+/// Options for [`slugify_with()`]. The defaults are [`slugify()`]'s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SlugOptions {
+    /// The character that replaces each run of non-word characters. Defaults
+    /// to `-`.
+    pub separator: char,
+    /// Whether to transliterate to ASCII first, so that only ASCII
+    /// alphanumerics and `_` are kept. Otherwise, alphanumerics in any script
+    /// are kept as they are. Defaults to `true`.
+    pub transliterate: bool,
+    /// Whether to convert to lowercase. Defaults to `true`.
+    pub lowercase: bool,
+}
+
+impl Default for SlugOptions {
+    fn default() -> Self {
+        SlugOptions { separator: '-', transliterate: true, lowercase: true }
+    }
+}
+
+/// Like [`slugify()`], but with the separator, transliteration, and case
+/// conversion set by `options`.
+///
+/// ```
+/// use harper::util::{slugify_with, SlugOptions};
+///
+/// let options = SlugOptions { separator: '_', ..Default::default() };
+/// assert_eq!(slugify_with("Hello, World!", &options), "hello_world");
+///
+/// let options = SlugOptions { transliterate: false, lowercase: false, ..Default::default() };
+/// assert_eq!(slugify_with("Ünïcode Wörds", &options), "Ünïcode-Wörds");
+/// ```
+pub fn slugify_with(string: &str, options: &SlugOptions) -> String {
     let mut output = String::with_capacity(string.len());
 
-    let mut need_dash = false;
+    let mut need_separator = false;
+    let mut push = |ch: char, is_word: bool| {
+        if !is_word {
+            // This deviates from Django: all sequences of characters not
+            // alphanumeric or `_` or converted into one separator.
+            need_separator = !output.is_empty();
+            return;
+        }
+
+        if need_separator {
+            output.push(options.separator);
+            need_separator = false;
+        }
+
+        match options.lowercase {
+            true => output.extend(ch.to_lowercase()),
+            false => output.push(ch),
+        }
+    };
+
     for ch in string.chars() {
-        for b in deunicode::deunicode_char(ch).unwrap_or("-").bytes() {
-            match b {
-                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' => {
-                    if need_dash {
-                        output.push('-');
-                        need_dash = false;
-                    }
-
-                    output.push(b.to_ascii_lowercase() as char);
-                }
-                _ => {
-                    // This deviates from Django: all sequences of characters
-                    // not alphanumeric or `_` or converted into one `-`.
-                    need_dash = !output.is_empty();
-                }
+        if options.transliterate {
+            // Combining marks transliterate to nothing and are dropped, but
+            // control characters, like `\n`, still separate words.
+            let ascii = match ch.is_control() {
+                true => "-",
+                false => deunicode::deunicode_char(ch).unwrap_or("-"),
+            };
+
+            for ch in ascii.chars() {
+                push(ch, ch.is_ascii_alphanumeric() || ch == '_');
             }
+        } else {
+            push(ch, ch.is_alphanumeric() || ch == '_');
         }
     }
 
     output
 }
 
+/// An algorithm for deriving URL slugs, e.g. heading anchors, from text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugStyle {
+    /// [`slugify()`]: transliterates to ASCII and collapses every run of other
+    /// characters into one `-`.
+    #[default]
+    Harper,
+    /// GitHub's heading anchors: lowercases without transliterating, keeps
+    /// letters and numbers in any script, `-`, and `_`, replaces each space
+    /// with `-`, without collapsing runs, and drops everything else,
+    /// including punctuation and emoji.
+    ///
+    /// ```
+    /// use harper::util::SlugStyle;
+    ///
+    /// assert_eq!(SlugStyle::GitHub.slugify("Hello,  World!"), "hello--world");
+    /// assert_eq!(SlugStyle::Harper.slugify("Hello,  World!"), "hello-world");
+    /// ```
+    #[serde(rename = "github")]
+    GitHub,
+}
+
+impl SlugStyle {
+    pub fn slugify(self, string: &str) -> String {
+        match self {
+            SlugStyle::Harper => slugify(string),
+            SlugStyle::GitHub => string.chars()
+                .filter_map(|ch| match ch {
+                    ' ' => Some('-'),
+                    '-' | '_' => Some(ch),
+                    ch if ch.is_alphanumeric() => Some(ch),
+                    _ => None,
+                })
+                .flat_map(char::to_lowercase)
+                .collect(),
+        }
+    }
+}
+
+/// Truncates `string` to at most `n` characters, appending `suffix` if any
+/// characters were removed. If a word boundary is within a few characters of
+/// the cut, the string is instead cut at the boundary to avoid splitting a
+/// word. Never splits a multibyte character.
+///
+/// ```
+/// use harper::util::truncate;
+///
+/// assert_eq!(truncate("hello", 10, "…"), "hello");
+/// assert_eq!(truncate("hello world", 8, "…"), "hello…");
+/// assert_eq!(truncate("héllo wörld", 3, "..."), "hél...");
+/// ```
+pub fn truncate<'a>(string: &'a str, n: usize, suffix: &str) -> Cow<'a, str> {
+    const WORD_SLACK: usize = 10;
+
+    let Some((end, _)) = string.char_indices().nth(n) else {
+        return Cow::Borrowed(string);
+    };
+
+    let mut prefix = &string[..end];
+    let at_boundary = string[end..].starts_with(char::is_whitespace);
+    if !at_boundary {
+        let boundary = prefix.rfind(char::is_whitespace)
+            .filter(|&i| prefix[i..].chars().count() <= WORD_SLACK);
+
+        if let Some(i) = boundary {
+            prefix = &prefix[..i];
+        }
+    }
+
+    Cow::Owned(format!("{}{suffix}", prefix.trim_end()))
+}
+
+/// Truncates `string` to at most `n` whitespace-separated words, appending
+/// `suffix` if any words were removed. Whitespace between retained words is
+/// preserved.
+///
+/// ```
+/// use harper::util::truncate_words;
+///
+/// assert_eq!(truncate_words("a b c", 3, "…"), "a b c");
+/// assert_eq!(truncate_words("a  b c", 2, "…"), "a  b…");
+/// ```
+pub fn truncate_words<'a>(string: &'a str, n: usize, suffix: &str) -> Cow<'a, str> {
+    let trimmed = string.trim_start();
+    let mut words = 0;
+    let mut in_word = false;
+    for (i, c) in trimmed.char_indices() {
+        match (c.is_whitespace(), in_word) {
+            (true, true) => in_word = false,
+            (false, false) => {
+                if words == n {
+                    return Cow::Owned(format!("{}{suffix}", trimmed[..i].trim_end()));
+                }
+
+                in_word = true;
+                words += 1;
+            }
+            _ => continue,
+        }
+    }
+
+    Cow::Borrowed(string)
+}
+
 /// A helper function to determine the relative path to `path` from `base`.
 ///
 /// Returns `None` if there is no relative path from `base` to `path`, that is,
@@ -129,6 +292,55 @@ pub fn is_template(input: &str) -> bool {
     false
 }
 
+/// Parses `value`, a unix timestamp or a date or datetime string, e.g. a
+/// normalized TOML datetime, as seconds since the unix epoch. Dates are taken
+/// to be midnight and datetimes without an offset to be in UTC. Returns `None`
+/// if `value` is neither.
+pub fn timestamp(value: &crate::value::Value) -> Option<i64> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+    if let Ok(timestamp) = i64::try_from(value.clone()) {
+        return Some(timestamp);
+    }
+
+    let string = value.as_str()?.trim();
+    string.parse::<NaiveDate>().map(|d| d.and_time(NaiveTime::MIN).and_utc())
+        .or_else(|_| string.parse::<NaiveDateTime>().map(|dt| dt.and_utc()))
+        .or_else(|_| string.parse::<DateTime<Utc>>())
+        .map(|dt| dt.timestamp())
+        .ok()
+}
+
+/// Matches `name` against `pattern`, where `*` matches any, possibly empty,
+/// sequence of characters and `?` matches exactly one character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    n = matched + 1;
+                }
+                None => return false,
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 #[cfg(test)]
 mod slug_tests {
     #[test]
@@ -141,5 +353,95 @@ mod slug_tests {
         assert_eq!(slugify("Æúű--cool?"), "aeuu-cool");
         assert_eq!(slugify("You & Me"), "you-me");
         assert_eq!(slugify("  user@-- example.com  "), "user-example-com");
+        assert_eq!(slugify("re\u{301}sume\u{301}"), "resume");
+    }
+
+    #[test]
+    fn test_slugify_with() {
+        use crate::util::{slugify_with, SlugOptions};
+
+        let keep = SlugOptions { transliterate: false, ..Default::default() };
+        assert_eq!(slugify_with("日本語の テキスト！", &keep), "日本語の-テキスト");
+        assert_eq!(slugify_with("  「你好」，世界 2024  ", &keep), "你好-世界-2024");
+        assert_eq!(slugify_with("Crème Brûlée", &keep), "crème-brûlée");
+        assert!(slugify_with("日本語", &SlugOptions::default()).is_ascii());
+
+        let snake = SlugOptions { separator: '_', lowercase: false, ..Default::default() };
+        assert_eq!(slugify_with("My Test String!!!1!1", &snake), "My_Test_String_1_1");
+        assert_eq!(slugify_with("  --test_-_cool- -  ", &snake), "test___cool");
+    }
+}
+
+#[cfg(test)]
+mod truncate_tests {
+    use crate::util::{truncate, truncate_words};
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("", 0, "…"), "");
+        assert_eq!(truncate("abc", 0, "…"), "…");
+        assert_eq!(truncate("abc", 3, "…"), "abc");
+        assert_eq!(truncate("the quick brown fox", 12, "…"), "the quick…");
+        assert_eq!(truncate("the quick brown fox", 15, "…"), "the quick brown…");
+        assert_eq!(truncate("supercalifragilistic", 5, "…"), "super…");
+    }
+
+    #[test]
+    fn test_truncate_multibyte() {
+        assert_eq!(truncate("日本語のテキストです", 4, "…"), "日本語の…");
+        assert_eq!(truncate("日本語 テキスト", 5, "…"), "日本語…");
+        assert_eq!(truncate("🦀🦀🦀🦀", 2, "…"), "🦀🦀…");
+        assert_eq!(truncate("👩‍👩‍👧 family", 20, "…"), "👩‍👩‍👧 family");
+        assert_eq!(truncate("naïve café", 4, "..."), "naïv...");
+    }
+
+    #[test]
+    fn test_truncate_words() {
+        assert_eq!(truncate_words("", 2, "…"), "");
+        assert_eq!(truncate_words("  one two  ", 2, "…"), "  one two  ");
+        assert_eq!(truncate_words("one two three", 2, "…"), "one two…");
+        assert_eq!(truncate_words("one two three", 0, "…"), "…");
+        assert_eq!(truncate_words("日本語 の テキスト", 2, "…"), "日本語 の…");
+        assert_eq!(truncate_words("🦀 rust 🦀 crab", 3, "…"), "🦀 rust 🦀…");
+    }
+}
+
+#[cfg(test)]
+mod timestamp_tests {
+    use crate::util::timestamp;
+    use crate::value::Value;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(&Value::from(86400)), Some(86400));
+        assert_eq!(timestamp(&Value::from("1970-01-02")), Some(86400));
+        assert_eq!(timestamp(&Value::from("1970-01-02T00:01:00")), Some(86460));
+        assert_eq!(timestamp(&Value::from("1970-01-02T01:00:00+01:00")), Some(86400));
+        assert_eq!(timestamp(&Value::from("1970-01-02T00:00:00Z")), Some(86400));
+        assert_eq!(timestamp(&Value::from("next tuesday")), None);
+        assert_eq!(timestamp(&Value::from(true)), None);
+    }
+}
+
+#[cfg(test)]
+mod glob_tests {
+    use crate::util::glob_match;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("*.bak", "notes.md.bak"));
+        assert!(glob_match("draft-*", "draft-1.md"));
+        assert!(glob_match("?.md", "a.md"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(glob_match("a*b*c", "abbc"));
+        assert!(glob_match("Thumbs.db", "Thumbs.db"));
+
+        assert!(!glob_match("*.bak", "notes.md"));
+        assert!(!glob_match("?.md", "ab.md"));
+        assert!(!glob_match("a*b*c", "aXXbYY"));
+        assert!(!glob_match("draft-*", "a-draft-1.md"));
+        assert!(!glob_match("", "a"));
     }
 }
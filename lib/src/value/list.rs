@@ -42,10 +42,7 @@ impl<T> List<T> {
     }
 
     pub fn iter(&self) -> SliceIter<'_, T> {
-        SliceIter {
-            slice: Slice::new(self, 0, self.len()),
-            next: 0,
-        }
+        Slice::new(self, 0, self.len()).into_iter()
     }
 }
 
@@ -99,7 +96,8 @@ impl<'a, T> Slice<'a, T> {
     }
 
     fn into_iter(self) -> SliceIter<'a, T> {
-        SliceIter { slice: self, next: 0 }
+        let back = self.len();
+        SliceIter { slice: self, next: 0, back }
     }
 }
 
@@ -115,20 +113,27 @@ impl<'a, T: Send + Sync> IntoParallelIterator for &'a List<T> {
 
 pub struct SliceIter<'a, T> {
     slice: Slice<'a, T>,
+    /// The index of the next item from the front.
     next: usize,
+    /// One past the index of the next item from the back.
+    back: usize,
 }
 
 impl<'a, T> Iterator for SliceIter<'a, T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.back {
+            return None;
+        }
+
         let item = self.slice.get(self.next)?;
         self.next += 1;
         Some(item)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let remaining = self.slice.len() - self.next;
+        let remaining = self.back.saturating_sub(self.next);
         (remaining, Some(remaining))
     }
 }
@@ -137,9 +142,9 @@ impl<T> ExactSizeIterator for SliceIter<'_, T> { }
 
 impl<T> DoubleEndedIterator for SliceIter<'_, T> {
     fn next_back(&mut self) -> Option<Self::Item> {
-        if self.next > 0 {
-            self.next -= 1;
-            return self.slice.get(self.next);
+        if self.back > self.next {
+            self.back -= 1;
+            return self.slice.get(self.back);
         }
 
         None
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::error::{ErrorDetail, Result};
@@ -42,7 +43,7 @@ impl<F: Format> Mapper for F {
     type Output = Value;
 
     fn map<I: Source>(&self, input: I) -> Result<Self::Output> {
-        Self::read(input)
+        Self::read::<_, Value>(input).map(Value::normalize_datetimes)
     }
 }
 
@@ -63,22 +64,69 @@ macro_rules! impl_format {
 
 impl_format!(Toml: toml::from_str, toml::de::Error);
 impl_format!(Json: serde_json::from_str, serde_json::error::Error);
+impl_format!(Yaml: serde_yaml::from_str, serde_yaml::Error);
 
 #[derive(Debug, Default)]
 pub struct Grass {
     options: grass::Options<'static>,
+    load_paths: Vec<PathBuf>,
+}
+
+impl Grass {
+    /// Resolves `@use` and `@import` in `path` too, after the importing file's
+    /// own directory, e.g. for partials shared by several stylesheets.
+    pub fn load_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        let path = path.into();
+        self.options = self.options.load_path(&path);
+        self.load_paths.push(path);
+        self
+    }
 }
 
 impl Mapper for Grass {
     type Output = String;
 
     fn map<I: Source>(&self, input: I) -> Result<Self::Output> {
+        let dir = input.path().and_then(|path| Some(path.parent()?.to_path_buf()));
         let result = match input.path() {
             Some(path) => grass::from_path(path, &self.options),
             None => input.try_read::<Arc<str>>()
                 .map(|string| grass::from_string(&*string, &self.options))?,
         };
 
-        result.map_err(|e| error!("failed to render sass as css", e))
+        result.map_err(|e| {
+            let message = e.to_string();
+            let Some(import) = missing_import(&message) else {
+                return error!("failed to render sass as css", e);
+            };
+
+            let searched = dir.iter()
+                .chain(self.load_paths.iter())
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>();
+
+            error! {
+                "failed to render sass as css: stylesheet to import not found",
+                "import" => import,
+                "searched paths" => searched.join(", "),
+                e,
+            }
+        })
     }
 }
+
+/// Returns the name imported by the `@use`, `@forward`, or `@import` that
+/// `message`, the text of a `grass` error, reports as not found, if it does.
+fn missing_import(message: &str) -> Option<&str> {
+    if !message.contains("Can't find stylesheet to import") {
+        return None;
+    }
+
+    let (_, rule) = message.split_once("@use")
+        .or_else(|| message.split_once("@forward"))
+        .or_else(|| message.split_once("@import"))?;
+
+    let rule = rule.trim_start();
+    let quote = rule.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    rule[1..].split(quote).next()
+}
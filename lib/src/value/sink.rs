@@ -3,7 +3,7 @@ use std::path::{Path, PathBuf};
 use std::fmt::Debug;
 
 use crate::error::{Result, Chainable};
-use crate::value::{Value, Source};
+use crate::value::{Num, Value, Source};
 
 pub trait Sink: Debug {
     fn write<V: Into<Value> + 'static>(&self, value: V) -> Result<()> {
@@ -32,6 +32,8 @@ impl Sink for fs::File {
                 Value::String(s) => write_bytes(to, s.as_bytes()),
                 Value::Path(s) => write_bytes(to, s.as_bytes()),
                 Value::Array(array) => array.iter().try_for_each(|v| write_value(to, v)),
+                Value::Num(Num::F32(v)) => write_bytes(to, &v.to_le_bytes()[..]),
+                Value::Num(Num::F64(v)) => write_bytes(to, &v.to_le_bytes()[..]),
                 Value::Num(n) => {
                     match n.to_u128_strict() {
                         Ok(v) => {
@@ -75,3 +77,10 @@ impl<T: Sink> Sink for &T {
         <T as Sink>::write(self, value)
     }
 }
+
+/// Discards every value written to it.
+impl Sink for () {
+    fn write_value(&self, _: Value) -> Result<()> {
+        Ok(())
+    }
+}
@@ -99,10 +99,13 @@ impl Source for &Entry {
     type Value = <&'static Path as Source>::Value;
 
     fn read(self) -> Result<Self::Value> {
-        self.path.as_ref().read()
+        match self.contents {
+            Some(ref contents) => Ok(Either::Left(contents.to_string())),
+            None => self.path.as_ref().read(),
+        }
     }
 
     fn path(&self) -> Option<&Path> {
-        Some(&*self.path)
+        self.contents.is_none().then_some(&*self.path)
     }
 }
@@ -109,6 +109,65 @@ impl Value {
             Value::Dict(_) => "dict",
         }
     }
+
+    /// Deep-merges `other` into `self`. Where both are dictionaries, keys in
+    /// only one are kept and keys in both are merged recursively. Otherwise,
+    /// including for arrays, `other` replaces `self`.
+    pub fn merge(self, other: Value) -> Value {
+        match (self, other) {
+            (Value::Dict(base), Value::Dict(other)) => {
+                let mut base = Arc::try_unwrap(base).unwrap_or_else(|base| (*base).clone());
+                let other = Arc::try_unwrap(other).unwrap_or_else(|other| (*other).clone());
+                for (key, value) in other {
+                    let merged = match base.remove(&key) {
+                        Some(existing) => existing.merge(value),
+                        None => value,
+                    };
+
+                    base.insert(key, merged);
+                }
+
+                Value::Dict(Arc::new(base))
+            }
+            (_, other) => other,
+        }
+    }
+
+    /// Returns `self` as pretty-printed JSON. Dictionary keys are sorted.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("values serialize to JSON")
+    }
+
+    /// Recursively replaces TOML datetimes, which deserialize as the
+    /// dictionary `{ "$__toml_private_datetime" = "..." }`, with their
+    /// RFC 3339 string. This gives dates from every format the same,
+    /// sortable, string representation.
+    pub fn normalize_datetimes(self) -> Value {
+        const TOML_DATETIME_KEY: &str = "$__toml_private_datetime";
+
+        match self {
+            Value::Dict(dict) if dict.len() == 1 && dict.contains_key(TOML_DATETIME_KEY) => {
+                dict[TOML_DATETIME_KEY].clone()
+            }
+            Value::Dict(dict) => {
+                let dict = Arc::try_unwrap(dict).unwrap_or_else(|dict| (*dict).clone());
+                let dict = dict.into_iter()
+                    .map(|(k, v)| (k, v.normalize_datetimes()))
+                    .collect();
+
+                Value::Dict(Arc::new(dict))
+            }
+            Value::Array(array) => {
+                let array = Arc::try_unwrap(array).unwrap_or_else(|array| (*array).clone());
+                let array = array.into_iter()
+                    .map(|v| v.normalize_datetimes())
+                    .collect();
+
+                Value::Array(Arc::new(array))
+            }
+            value => value,
+        }
+    }
 }
 
 macro_rules! impl_from_primitive {
@@ -134,6 +193,7 @@ impl_from_primitive!(Arc<Vec<Value>> => Value::Array);
 impl_from_primitive!(Arc<Dict> => Value::Dict);
 impl_from_primitive!(u8, u16, u32, u64, u128, usize => Value::Num);
 impl_from_primitive!(i8, i16, i32, i64, i128, isize => Value::Num);
+impl_from_primitive!(f32, f64 => Value::Num);
 
 impl From<()> for Value  {
     fn from(_: ()) -> Self {
@@ -196,7 +256,12 @@ impl FromIterator<Value> for Value {
     }
 }
 
-/// A signed or unsigned numeric value.
+/// A signed or unsigned integer or a floating point numeric value.
+///
+/// Numbers compare by their mathematical value regardless of their type, so
+/// `Num::from(2u8) == Num::from(2.0f64)` and `Num::from(1i8) < Num::from(1.5f32)`.
+/// For `Num` to be totally ordered, `NaN` is equal to itself and greater than
+/// every other number, including infinity, and `-0.0` is equal to `0.0`.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Num {
@@ -224,10 +289,17 @@ pub enum Num {
     I128(i128),
     /// A signed integer of platform width.
     ISize(isize),
+    /// A 32-bit float. Fractional numbers always deserialize as [`Num::F64`].
+    #[serde(skip_deserializing)]
+    F32(f32),
+    /// A 64-bit float.
+    F64(f64),
 }
 
 impl Num {
-    /// Converts `self` into a `u128`.
+    /// Converts `self` into a `u128` if it's an unsigned integer. Otherwise
+    /// returns it as an `i128`. Floats, which are neither, are truncated
+    /// toward zero and saturated: use [`Num::to_f64()`] for them instead.
     pub fn to_u128_strict(self) -> Result<u128, i128> {
         match self {
             Num::U8(v) => Ok(v as u128),
@@ -242,10 +314,14 @@ impl Num {
             Num::I64(v) => Err(v as i128),
             Num::I128(v) => Err(v as i128),
             Num::ISize(v) => Err(v as i128),
+            Num::F32(v) => Err(v as i128),
+            Num::F64(v) => Err(v as i128),
         }
     }
 
-    /// Converts `self` into a `u128`.
+    /// Converts `self` into a `u128` if it's non-negative. Otherwise returns
+    /// it as an `i128`. Floats are truncated toward zero and saturated, and
+    /// `NaN` is `0`.
     pub fn to_u128_lossy(self) -> Result<u128, i128> {
         Ok(match self {
             Num::U8(v) => v as u128,
@@ -260,33 +336,114 @@ impl Num {
             Num::I64(v) if v >= 0 => v as u128,
             Num::I128(v) if v >= 0 => v as u128,
             Num::ISize(v) if v >= 0 => v as u128,
+            Num::F32(v) if v >= 0.0 || v.is_nan() => v as u128,
+            Num::F64(v) if v >= 0.0 || v.is_nan() => v as u128,
             Num::I8(v) => return Err(v as i128),
             Num::I16(v) => return Err(v as i128),
             Num::I32(v) => return Err(v as i128),
             Num::I64(v) => return Err(v as i128),
             Num::I128(v) => return Err(v as i128),
             Num::ISize(v) => return Err(v as i128),
+            Num::F32(v) => return Err(v as i128),
+            Num::F64(v) => return Err(v as i128),
         })
     }
+
+    /// Converts `self` into an integer, as by [`Num::to_u128_lossy()`], only
+    /// if it's exactly one: an integer or a whole, finite float in range.
+    ///
+    /// ```rust
+    /// use harper::value::Num;
+    ///
+    /// assert_eq!(Num::from(3u8).to_integer(), Some(Ok(3)));
+    /// assert_eq!(Num::from(-3.0f64).to_integer(), Some(Err(-3)));
+    /// assert_eq!(Num::from(3.5f32).to_integer(), None);
+    /// assert_eq!(Num::from(f64::NAN).to_integer(), None);
+    /// ```
+    pub fn to_integer(self) -> Option<Result<u128, i128>> {
+        const U128_END: f64 = u128::MAX as f64;
+        const I128_MIN: f64 = i128::MIN as f64;
+
+        match self {
+            Num::F32(_) | Num::F64(_) => {
+                let v = self.to_f64();
+                if v.fract() != 0.0 || !v.is_finite() {
+                    return None;
+                }
+
+                match v >= 0.0 {
+                    true if v < U128_END => Some(Ok(v as u128)),
+                    false if v >= I128_MIN => Some(Err(v as i128)),
+                    _ => None,
+                }
+            }
+            int => Some(int.to_u128_lossy()),
+        }
+    }
+
+    /// Converts `self` into an `f64`. Integers of large magnitude lose
+    /// precision.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Num::F32(v) => v as f64,
+            Num::F64(v) => v,
+            int => match int.to_u128_lossy() {
+                Ok(v) => v as f64,
+                Err(v) => v as f64,
+            }
+        }
+    }
+
+    /// Whether `self` is a float.
+    pub fn is_float(self) -> bool {
+        matches!(self, Num::F32(_) | Num::F64(_))
+    }
+}
+
+/// Compares the integer `int`, as returned by [`Num::to_u128_lossy()`], to
+/// the float `float` exactly.
+fn cmp_int_float(int: Result<u128, i128>, float: f64) -> std::cmp::Ordering {
+    use std::cmp::Ordering::*;
+
+    const U128_END: f64 = u128::MAX as f64;
+    const I128_MIN: f64 = i128::MIN as f64;
+
+    if float.is_nan() {
+        return Less;
+    }
+
+    let fraction = float.fract();
+    match int {
+        Ok(_) if float < 0.0 => Greater,
+        Ok(_) if float >= U128_END => Less,
+        Ok(v) => v.cmp(&(float as u128)).then(0f64.partial_cmp(&fraction).unwrap_or(Equal)),
+        Err(_) if float >= 0.0 => Less,
+        Err(_) if float < I128_MIN => Greater,
+        Err(v) => v.cmp(&(float as i128)).then(0f64.partial_cmp(&fraction).unwrap_or(Equal)),
+    }
+}
+
+/// Compares floats, with `NaN` equal to itself and greater than all else.
+fn cmp_floats(a: f64, b: f64) -> std::cmp::Ordering {
+    a.partial_cmp(&b).unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
 }
 
 impl PartialEq for Num {
     fn eq(&self, other: &Self) -> bool {
-        match (self.to_u128_lossy(), other.to_u128_lossy()) {
-            (Ok(a), Ok(b)) => a == b,
-            (Err(a), Err(b)) => a == b,
-            (Ok(_), Err(_)) | (Err(_), Ok(_)) => false,
-        }
+        self.cmp(other).is_eq()
     }
 }
 
 impl Eq for Num { }
 
 impl std::hash::Hash for Num {
+    /// Numbers that are equal hash equally: whole floats hash as integers.
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        match self.to_u128_lossy() {
-            Ok(v) => v.hash(state),
-            Err(v) => v.hash(state),
+        match self.to_integer() {
+            Some(Ok(v)) => v.hash(state),
+            Some(Err(v)) => v.hash(state),
+            None if self.to_f64().is_nan() => f64::NAN.to_bits().hash(state),
+            None => self.to_f64().to_bits().hash(state),
         }
     }
 }
@@ -308,13 +465,27 @@ impl Ord for Num {
     /// assert!(Num::from(-2i8) > Num::from(-3i8));
     /// assert!(Num::from(1i8) > Num::from(0u8));
     /// assert!(Num::from(5u32) > Num::from(-1i64));
+    ///
+    /// assert!(Num::from(2.0f64) == Num::from(2u8));
+    /// assert!(Num::from(-0.0f64) == Num::from(0u8));
+    /// assert!(Num::from(1.5f32) > Num::from(1i8) && Num::from(1.5f32) < Num::from(2u8));
+    /// assert!(Num::from(-1.5f64) < Num::from(-1i8) && Num::from(-1.5f64) > Num::from(-2i8));
+    /// assert!(Num::from(u128::MAX) < Num::from(f64::INFINITY));
+    /// assert!(Num::from(f64::NAN) == Num::from(f64::NAN));
+    /// assert!(Num::from(f64::NAN) > Num::from(f64::INFINITY));
+    /// assert!(Num::from(0.5f32) == Num::from(0.5f64));
     /// ```
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (self.to_u128_lossy(), other.to_u128_lossy()) {
-            (Ok(a), Ok(b)) => a.cmp(&b),
-            (Ok(_), Err(_)) => std::cmp::Ordering::Greater,
-            (Err(_), Ok(_)) => std::cmp::Ordering::Less,
-            (Err(a), Err(b)) => a.cmp(&b),
+        match (self.is_float(), other.is_float()) {
+            (true, true) => cmp_floats(self.to_f64(), other.to_f64()),
+            (false, true) => cmp_int_float(self.to_u128_lossy(), other.to_f64()),
+            (true, false) => cmp_int_float(other.to_u128_lossy(), self.to_f64()).reverse(),
+            (false, false) => match (self.to_u128_lossy(), other.to_u128_lossy()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => std::cmp::Ordering::Greater,
+                (Err(_), Ok(_)) => std::cmp::Ordering::Less,
+                (Err(a), Err(b)) => a.cmp(&b),
+            }
         }
     }
 }
@@ -332,6 +503,7 @@ macro_rules! impl_from_for_num_value {
 impl_from_for_num_value! {
     u8: U8, u16: U16, u32: U32, u64: U64, u128: U128, usize: USize,
     i8: I8, i16: I16, i32: I32, i64: I64, i128: I128, isize: ISize,
+    f32: F32, f64: F64,
 }
 
 macro_rules! impl_try_from_value {
@@ -360,11 +532,25 @@ impl_try_from_value!(Arc<Url> =>  |v| {
 
 impl_try_from_value!(Num => |v| v.to_num().ok_or(v));
 
-impl_try_from_value!(u8, u16, u32, u64, u128, usize =>
-    |v| v.to_num().and_then(|v| v.to_u128_lossy().ok()?.try_into().ok()).ok_or(v));
+impl_try_from_value!(u8, u16, u32, u64, usize =>
+    |v| v.to_num().and_then(|v| v.to_integer()?.ok()?.try_into().ok()).ok_or(v));
+
+impl_try_from_value!(u128 => |v| v.to_num().and_then(|v| v.to_integer()?.ok()).ok_or(v));
+
+impl_try_from_value!(i8, i16, i32, i64, isize =>
+    |v| v.to_num().and_then(|v| match v.to_integer()? {
+        Ok(v) => v.try_into().ok(),
+        Err(v) => v.try_into().ok(),
+    }).ok_or(v));
+
+impl_try_from_value!(i128 =>
+    |v| v.to_num().and_then(|v| match v.to_integer()? {
+        Ok(v) => v.try_into().ok(),
+        Err(v) => Some(v),
+    }).ok_or(v));
 
-impl_try_from_value!(i8, i16, i32, i64, i128, isize =>
-    |v| v.to_num().and_then(|v| v.to_u128_lossy().ok()?.try_into().ok()).ok_or(v));
+impl_try_from_value!(f32 => |v| v.to_num().map(|v| v.to_f64() as f32).ok_or(v));
+impl_try_from_value!(f64 => |v| v.to_num().map(Num::to_f64).ok_or(v));
 
 impl<T: TryFrom<Value, Error = Value>> TryFrom<Value> for Vec<T> {
     type Error = Value;
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use harper::{error, Collection, Generator, Item, Kind, Renderer, Site};
+use harper::diagnostics::{Diagnostics, Level};
+use harper::error::Result;
+use harper::fstree::FsTree;
+
+/// Makes `guide/` a collection and records a warning for each untitled item.
+struct Titles {
+    tree: Arc<FsTree>,
+    diagnostics: Diagnostics,
+}
+
+impl Generator for Titles {
+    fn discover(&self) -> Result<Site> {
+        let id = |path: &str| self.tree.get_id(None, path).unwrap();
+        let mut site = Site::new(self.tree.clone());
+        let collection = site.get_or_insert_collection(|| "guide".into(), id("guide"));
+        collection.set_index_item(id("guide/index.md"));
+        collection.new_item(id("guide/b.md")).metadata.insert_raw("title", "B");
+        collection.new_item(id("guide/a.md"));
+        Ok(site)
+    }
+
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        Some(&self.diagnostics)
+    }
+}
+
+impl Renderer for Titles {
+    type Output = Vec<Vec<String>>;
+    type Collected = Vec<String>;
+    type Render = String;
+
+    fn render_collection_item(&self,
+        _: Kind,
+        _: &Arc<Site>,
+        _: &Arc<Collection>,
+        item: &Arc<Item>
+    ) -> Result<String> {
+        match item.metadata.get_raw("title") {
+            Some(title) => Ok(title.as_str().unwrap().into()),
+            None => {
+                let path = item.entry.relative_path();
+                self.diagnostics.push(Level::Warning, Some(path), error!("untitled"));
+                Ok(item.entry.file_stem().into())
+            }
+        }
+    }
+
+    fn render_site_item(&self, _: &Item) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn build_returns_site_output_and_report() {
+    let tree = Arc::new(FsTree::from_memory("/site", [
+        ("guide/index.md", ""),
+        ("guide/b.md", ""),
+        ("guide/a.md", ""),
+    ]).unwrap());
+
+    let generator = Titles { tree, diagnostics: Diagnostics::default() };
+    let build = harper::build(&generator).unwrap();
+    assert_eq!(build.site.collections.len(), 1);
+
+    let mut titles = build.output.concat();
+    titles.sort();
+    assert_eq!(titles, ["B", "a", "index"]);
+
+    assert_eq!(build.report.warnings.len(), 2);
+    assert!(build.report.notes.is_empty());
+
+    // The build takes the diagnostics, leaving none for the next one.
+    assert!(generator.diagnostics.take().warnings.is_empty());
+}
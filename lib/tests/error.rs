@@ -0,0 +1,46 @@
+use harper::error;
+use harper::error::{Chainable, Error, Result};
+
+fn layered() -> Error {
+    let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+    let result: Result<()> = Err(Error::from(io));
+    result.chain(error!("failed to read template", "path" => "page.html"))
+        .chain(error!("failed to render item", "item" => "guide/intro.md", "draft"))
+        .unwrap_err()
+}
+
+#[test]
+fn layers_outermost_first() {
+    let error = layered();
+    let layers: Vec<_> = error.iter().collect();
+    assert_eq!(layers.len(), 3);
+
+    assert_eq!(layers[0].message(), "failed to render item");
+    assert_eq!(layers[0].context(), [
+        (Some("item".to_string()), "guide/intro.md".to_string()),
+        (None, "draft".to_string()),
+    ]);
+
+    assert_eq!(layers[1].message(), "failed to read template");
+    assert_eq!(layers[1].context(), [(Some("path".to_string()), "page.html".to_string())]);
+    assert_eq!(layers[2].message(), "no such file");
+    assert!(layers.iter().all(|layer| layer.location().file().ends_with("error.rs")));
+}
+
+#[test]
+fn root_cause_is_innermost() {
+    let error = layered();
+    assert_eq!(error.root_cause().message(), "no such file");
+
+    let single = error!("just one");
+    assert_eq!(single.iter().count(), 1);
+    assert_eq!(single.root_cause().message(), "just one");
+    assert!(single.root_cause().context().is_empty());
+}
+
+#[test]
+fn display_is_unchanged() {
+    let display = layered().to_string();
+    assert!(display.starts_with("failed to render item\n    failed to read template\n"));
+    assert!(display.contains("item: guide/intro.md"));
+}
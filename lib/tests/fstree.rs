@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use harper::fstree::{Bfs, Dfs, Entries, EntryId, FsTree, Ignore};
+
+#[test]
+fn iter_relative_paths() {
+    let tree = FsTree::from_memory("/site", [
+        ("content/index.md", ""),
+        ("content/guide/intro.md", ""),
+        ("templates/page.html", ""),
+    ]).unwrap();
+
+    let content = tree.get_id(None, "content").unwrap();
+    let mut paths: Vec<_> = tree.iter_relative(content)
+        .map(|(id, path)| (tree[id].file_name.clone(), path.to_path_buf()))
+        .collect();
+
+    paths.sort();
+    assert_eq!(paths, vec![
+        ("content".to_string(), Path::new("").to_path_buf()),
+        ("guide".to_string(), Path::new("guide").to_path_buf()),
+        ("index.md".to_string(), Path::new("index.md").to_path_buf()),
+        ("intro.md".to_string(), Path::new("guide/intro.md").to_path_buf()),
+    ]);
+
+    let (root, path) = tree.iter_relative(tree.root_id()).next().unwrap();
+    assert_eq!((root, path), (tree.root_id(), Path::new("")));
+    assert_eq!(tree.search("content/guide/intro.md"), tree.get_id(None, "content/guide/intro.md"));
+    assert_eq!(tree.search("content/missing.md"), None);
+}
+
+#[test]
+#[cfg(unix)]
+fn non_utf8_file_stems_compare_exactly() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let latin1 = Path::new(OsStr::from_bytes(b"data/caf\xe9.toml"));
+    let utf8 = Path::new("data/cafe.toml");
+    let tree = FsTree::from_memory("/site", [(latin1, ""), (utf8, "")]).unwrap();
+
+    let entry = tree.get(None, latin1).unwrap();
+    assert!(!entry.has_utf8_name());
+    assert_eq!(entry.file_stem(), "caf\u{FFFD}");
+    assert!(!entry.file_stem_eq("caf\u{FFFD}"));
+    assert!(!entry.file_stem_eq("café"));
+
+    let entry = tree.get(None, utf8).unwrap();
+    assert!(entry.has_utf8_name());
+    assert!(entry.file_stem_eq("cafe"));
+    assert!(!entry.file_stem_eq("cafe.toml"));
+}
+
+#[test]
+fn from_entries_links_like_build() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let metadata = |path: &str| std::fs::metadata(root.join(path)).unwrap();
+    let tree = FsTree::from_entries(root, [
+        ("src/lib.rs", metadata("src/lib.rs")),
+        ("src", metadata("src")),
+        ("Cargo.toml", metadata("Cargo.toml")),
+    ]).unwrap();
+
+    assert_eq!(tree.len(), 4);
+    assert_eq!(tree.root().path.as_ref(), root);
+    assert_eq!(tree.root().parent, None);
+    assert!(tree.root().file_type.is_dir() && tree.root().fs_metadata().is_none());
+
+    let src = tree.get(None, "src").unwrap();
+    assert!(src.file_type.is_dir() && src.fs_metadata().is_some());
+    assert_eq!(src.parent, Some(tree.root_id()));
+    assert_eq!(src.depth, 1);
+
+    let lib = tree.get(None, "src/lib.rs").unwrap();
+    assert_eq!(lib.parent, Some(src.id));
+    assert_eq!(src.children, [lib.id]);
+    assert_eq!(lib.relative_path(), Path::new("src/lib.rs"));
+    assert!(std::fs::read_to_string(&lib.path).unwrap().contains("pub mod fstree"));
+
+    let conflict = FsTree::from_entries(root, [
+        ("Cargo.toml", metadata("Cargo.toml")),
+        ("Cargo.toml", metadata("Cargo.toml")),
+    ]);
+
+    assert!(conflict.is_err());
+}
+
+#[test]
+fn build_distinguishes_missing_and_empty_roots() {
+    let root = std::env::temp_dir().join(format!("harper-test-{}-empty-tree", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+
+    let error = FsTree::build(&root).unwrap_err().to_string();
+    assert!(error.contains("does not exist"), "{error}");
+
+    std::fs::create_dir_all(&root).unwrap();
+    let tree = FsTree::build(&root).unwrap();
+    assert_eq!(tree.len(), 1);
+    assert!(tree.root().file_type.is_dir() && tree.root().children.is_empty());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn traversals_are_nameable_and_depth_limited() {
+    struct Walk<'a> {
+        files: Entries<'a, Bfs<'a>>,
+        dirs: Entries<'a, Dfs<'a>>,
+    }
+
+    let tree = FsTree::from_memory("/site", [
+        ("content/index.md", ""),
+        ("content/guide/intro.md", ""),
+        ("content/guide/deep/more.md", ""),
+    ]).unwrap();
+
+    let content = tree.get_id(None, "content").unwrap();
+    let walk = Walk {
+        files: tree.iter_breadth_first(content).max_depth(2).files(),
+        dirs: tree.iter_depth_first(content).dirs(),
+    };
+
+    let mut files: Vec<_> = walk.files.map(|e| e.file_name.as_str()).collect();
+    files.sort();
+    assert_eq!(files, ["index.md", "intro.md"]);
+
+    let mut dirs: Vec<_> = walk.dirs.map(|e| e.file_name.as_str()).collect();
+    dirs.sort();
+    assert_eq!(dirs, ["content", "deep", "guide"]);
+
+    let shallow: Vec<_> = tree.iter_depth_first(content).max_depth(1).entries()
+        .map(|e| e.file_name.as_str())
+        .collect();
+    assert_eq!(shallow.len(), 3);
+    assert!(!shallow.contains(&"intro.md"));
+    assert_eq!(tree.iter_breadth_first(content).max_depth(0).count(), 1);
+}
+
+#[test]
+fn build_skips_ignored_entries() {
+    let root = std::env::temp_dir().join(format!("harper-test-{}-ignored-tree", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    for path in ["a.md", "a.md.swp", "b.tmp", "keep.tmp", "web/node_modules/x.js", "web/app.js"] {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    std::fs::write(root.join(".harperignore"), "# editor files\n*.swp\n*.tmp\n!keep.tmp\n\nnode_modules/\n").unwrap();
+    let tree = FsTree::build(&root).unwrap();
+    assert!(tree.get(None, "a.md").is_some());
+    assert!(tree.get(None, "keep.tmp").is_some());
+    assert!(tree.get(None, "web/app.js").is_some());
+    assert!(tree.get(None, "a.md.swp").is_none());
+    assert!(tree.get(None, "b.tmp").is_none());
+    assert!(tree.get(None, "web/node_modules").is_none());
+    assert!(tree.get(None, "web/node_modules/x.js").is_none());
+
+    let error = FsTree::build_with_ignore(&root, Ignore::new(["*"]), |_, _| Ok(())).unwrap_err();
+    assert!(error.to_string().contains("zero files"), "{error}");
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn content_hashes_compare_contents() {
+    let tree = FsTree::from_memory("/site", [
+        ("a.md", "same"),
+        ("b/c.md", "same"),
+        ("d.md", "different"),
+    ]).unwrap();
+
+    let hash = |path: &str| tree.get(None, path).unwrap().content_hash().unwrap();
+    assert_eq!(hash("a.md"), hash("b/c.md"));
+    assert_ne!(hash("a.md"), hash("d.md"));
+    assert_eq!(hash("d.md"), hash("d.md"));
+}
+
+#[test]
+fn case_insensitive_lookups_prefer_exact_names() {
+    let tree = FsTree::from_memory("/site", [
+        ("templates/Page.html", "upper"),
+        ("templates/page.html", "lower"),
+        ("templates/Blog/post.html", ""),
+    ]).unwrap();
+
+    let templates = tree.get_id(None, "templates");
+    let name = |id: EntryId| tree[id].file_name.clone();
+    assert_eq!(tree.get_id_ci(templates, "page.html").map(name).as_deref(), Some("page.html"));
+    assert_eq!(tree.get_id_ci(templates, "Page.html").map(name).as_deref(), Some("Page.html"));
+    assert_eq!(tree.get_id_ci(templates, "blog/POST.HTML"), tree.get_id(templates, "Blog/post.html"));
+    assert_eq!(tree.get_id_ci(templates, "./blog/../PAGE.html").map(name).as_deref(), Some("Page.html"));
+    assert_eq!(tree.get_id_ci(templates, "blog"), tree.get_id(templates, "Blog"));
+    assert_eq!(tree.get_file_id_ci(templates, "blog"), None);
+    assert_eq!(tree.get_id_ci(templates, "blog/missing.html"), None);
+}
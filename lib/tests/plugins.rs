@@ -0,0 +1,46 @@
+#![cfg(feature = "plugins")]
+
+use std::sync::Arc;
+
+use harper::fstree::FsTree;
+use harper::markdown::{Markdown, Renderer};
+use harper::taxonomy::Metadata;
+use harper::templating::plugins::{self, LuaMarkdown};
+
+fn render(init: &str, markdown: &str) -> harper::error::Result<String> {
+    let tree = Arc::new(FsTree::from_memory("/site", [("plugins/init.lua", init)])?);
+    let plugins = plugins::init(tree)?.expect("init.lua exists");
+
+    let metadata = Metadata::new();
+    Markdown::from(markdown.to_string())
+        .plugin(LuaMarkdown::new(Arc::new(plugins)))
+        .plugin(Renderer::new(metadata.key("content")))
+        .run()?;
+
+    let content = metadata.get_raw("content").unwrap();
+    Ok(content.as_str().unwrap().to_string())
+}
+
+#[test]
+fn markdown_transforms_run_in_order() {
+    let init = r#"
+        harper.register_markdown("shout", function(md)
+            return (md:gsub("hello", "HELLO"))
+        end)
+
+        harper.register_markdown("noop", function(md) return nil end)
+
+        harper.register_markdown("emphasize", function(md)
+            return (md:gsub("HELLO", "*HELLO*"))
+        end)
+    "#;
+
+    assert_eq!(render(init, "hello world").unwrap(), "<p><em>HELLO</em> world</p>\n");
+}
+
+#[test]
+fn markdown_transform_errors_name_the_transform() {
+    let init = r#"harper.register_markdown("broken", function(md) error("oops") end)"#;
+    let error = render(init, "hello").unwrap_err().to_string();
+    assert!(error.contains("broken"));
+}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use harper::{Kind, Site};
+use harper::fstree::FsTree;
+use harper::markdown::{FrontMatter, Markdown, Renderer};
+use harper::value::Toml;
+
+#[test]
+fn site_from_memory() {
+    let tree = Arc::new(FsTree::from_memory("/site", [
+        ("content/guide/index.md", "+++\ntitle = \"Guide\"\n+++\n# Welcome"),
+        ("content/guide/intro.md", "Introduction"),
+    ]).unwrap());
+
+    let mut site = Site::new(tree.clone());
+    let guide = tree.get_id(None, "content/guide").unwrap();
+    let collection = site.get_or_insert_collection(|| "guide".into(), guide);
+    let index = collection.set_index_item(tree.get_id(None, "content/guide/index.md").unwrap());
+    let intro = collection.new_item(tree.get_id(None, "content/guide/intro.md").unwrap());
+    intro.metadata.insert_raw("title", "Intro");
+
+    Markdown::from(&*index.entry)
+        .plugin(FrontMatter::new(Toml, &index.metadata))
+        .plugin(Renderer::new(index.metadata.key("content")))
+        .run()
+        .unwrap();
+
+    let title = index.metadata.get_raw("title").unwrap();
+    let content = index.metadata.get_raw("content").unwrap();
+    assert_eq!(title.as_str(), Some("Guide"));
+    assert_eq!(content.as_str().map(|s| s.trim()), Some("<h1>Welcome</h1>"));
+    assert_eq!(intro.entry.relative_path().to_str(), Some("content/guide/intro.md"));
+    assert_eq!(site.collections[&guide].items.len(), 1);
+}
+
+#[test]
+fn memory_tree_rejects_conflicts() {
+    assert!(FsTree::from_memory("/site", [("a", "file"), ("a/b", "nested")]).is_err());
+    assert!(FsTree::from_memory("/site", [("a", "one"), ("a", "two")]).is_err());
+    assert!(FsTree::from_memory("/site", [("../a", "escape")]).is_err());
+    assert!(FsTree::from_memory("/site", [("/a", "absolute")]).is_err());
+}
+
+#[test]
+fn map_items_ordered() {
+    let tree = Arc::new(FsTree::from_memory("/site", [
+        ("guide/index.md", ""),
+        ("guide/b.md", ""),
+        ("guide/a.md", ""),
+        ("guide/z/data.toml", ""),
+        ("guide/y/data.toml", ""),
+    ]).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    let collection = site.get_or_insert_collection(|| "guide".into(), id("guide"));
+    collection.new_item(id("guide/b.md"));
+    collection.new_item(id("guide/a.md"));
+    collection.new_datum(id("guide/z"), id("guide/z/data.toml"));
+    collection.new_datum(id("guide/y"), id("guide/y/data.toml"));
+    collection.set_index_item(id("guide/index.md"));
+
+    let paths = collection.map_items_ordered(|kind, item| {
+        let path = item.entry.relative_path().to_string_lossy().into_owned();
+        (kind, path)
+    });
+
+    assert_eq!(paths, vec![
+        (Kind::Index, "guide/index.md".to_string()),
+        (Kind::Item(0), "guide/b.md".to_string()),
+        (Kind::Item(1), "guide/a.md".to_string()),
+        (Kind::Datum(id("guide/y")), "guide/y/data.toml".to_string()),
+        (Kind::Datum(id("guide/z")), "guide/z/data.toml".to_string()),
+    ]);
+}
+
+#[test]
+fn items_by_content_path() {
+    let tree = Arc::new(FsTree::from_memory("/site", [
+        ("content/guide/index.md", ""),
+        ("content/guide/intro.md", ""),
+        ("content/about.md", ""),
+        ("docs/api.md", ""),
+    ]).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    site.content_roots = vec![id("content"), id("docs")];
+    let collection = site.get_or_insert_collection(|| "guide".into(), id("content/guide"));
+    collection.set_index_item(id("content/guide/index.md"));
+    collection.new_item(id("content/guide/intro.md"));
+    site.new_resource(id("content/about.md"));
+    site.new_resource(id("docs/api.md"));
+
+    let at = |path: &str| site.item_at(path).map(|item| item.entry.id);
+    assert_eq!(at("guide/intro.md"), Some(id("content/guide/intro.md")));
+    assert_eq!(at("guide/index.md"), Some(id("content/guide/index.md")));
+    assert_eq!(at("about.md"), Some(id("content/about.md")));
+    assert_eq!(at("api.md"), Some(id("docs/api.md")));
+    assert_eq!(at("guide"), None);
+    assert_eq!(at("missing.md"), None);
+}
+
+#[test]
+fn metadata_inject_respects_existing_keys() {
+    use harper::taxonomy::Metadata;
+    use harper::value::{Dict, Value};
+
+    let injected: Dict = [("title", "Injected"), ("commit", "abc123")].into_iter()
+        .map(|(k, v)| (k.into(), Value::from(v)))
+        .collect();
+
+    let metadata = Metadata::new();
+    metadata.insert_raw("title", "Mine");
+    metadata.inject(&injected, false);
+    assert_eq!(metadata.get_raw("title"), Some("Mine".into()));
+    assert_eq!(metadata.get_raw("commit"), Some("abc123".into()));
+
+    metadata.inject(&injected, true);
+    assert_eq!(metadata.get_raw("title"), Some("Injected".into()));
+}
+
+#[test]
+fn metadata_extend_overwrites_and_is_concurrent() {
+    use harper::taxonomy::Metadata;
+    use harper::value::Value;
+
+    let metadata = Metadata::new();
+    metadata.insert_raw("title", "Old");
+    metadata.extend([("title", Value::from("New")), ("tags", Value::from(3usize))]);
+    assert_eq!(metadata.get_raw("title"), Some("New".into()));
+    assert_eq!(metadata.get_raw("tags"), Some(Value::from(3usize)));
+
+    let fields: Vec<(Arc<str>, Value)> = vec![("computed".into(), "yes".into())];
+    metadata.extend(fields);
+    assert_eq!(metadata.get_raw("computed"), Some("yes".into()));
+
+    std::thread::scope(|scope| {
+        for t in 0..8usize {
+            let metadata = &metadata;
+            scope.spawn(move || {
+                metadata.extend((0..100usize).map(|i| (format!("key-{i}"), t)));
+                metadata.extend((0..10usize).map(|i| (format!("thread-{t}-{i}"), i)));
+            });
+        }
+    });
+
+    for i in 0..100usize {
+        let Some(Value::Num(_)) = metadata.get_raw(&format!("key-{i}")) else {
+            panic!("missing key-{i}");
+        };
+    }
+
+    for t in 0..8usize {
+        assert_eq!(metadata.get_raw(&format!("thread-{t}-9")), Some(Value::from(9usize)));
+    }
+}
+
+#[test]
+fn item_content_reads_rendered_html() {
+    use harper::Content;
+
+    let tree = Arc::new(FsTree::from_memory("/site", [("post.md", "Hello *there*.")]).unwrap());
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "post.md").unwrap());
+    assert_eq!(item.content().unwrap(), None);
+
+    Markdown::from(&*item.entry)
+        .plugin(Renderer::new(item.metadata.metakey(Content)))
+        .run()
+        .unwrap();
+
+    assert_eq!(item.content().unwrap().as_deref(), Some("<p>Hello <em>there</em>.</p>\n"));
+
+    item.metadata.insert_raw("content", 42);
+    assert!(item.content().is_err());
+}
@@ -0,0 +1,610 @@
+use std::fs;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
+
+use harper::{Site, Item, Collection, Metadata};
+use harper::fstree::FsTree;
+use harper::value::{Dict, Value};
+use harper::templating::{Engine, EngineInit};
+use harper::templating::minijinja::MiniJinjaEngine;
+
+/// A site in the temporary directory, removed when dropped.
+struct SiteDir(PathBuf);
+
+impl Deref for SiteDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for SiteDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for SiteDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn site_dir(name: &str, files: &[(&str, &str)]) -> SiteDir {
+    let root = std::env::temp_dir()
+        .join(format!("harper-test-{}-{name}", std::process::id()));
+
+    let _ = fs::remove_dir_all(&root);
+    for (path, contents) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    SiteDir(root)
+}
+
+fn render(root: &Path, template: &str) -> harper::error::Result<String> {
+    let tree = Arc::new(FsTree::build(root).unwrap());
+    let templates = tree.get_id(None, "templates");
+    let engine = MiniJinjaEngine::init(tree.clone(), templates, ());
+
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "content/page.md").unwrap());
+    engine.render(template, &Arc::new(site), None, &item)
+}
+
+#[test]
+fn include_resolves_from_includes_dir() {
+    let root = site_dir("include", &[
+        ("content/page.md", "hello"),
+        ("templates/page.html", "<main>{% include \"nav.html\" %}</main>"),
+        ("templates/includes/nav.html", "<nav></nav>"),
+    ]);
+
+    assert_eq!(render(&root, "page.html").unwrap(), "<main><nav></nav></main>");
+}
+
+#[test]
+fn missing_include_names_templates() {
+    let root = site_dir("missing-include", &[
+        ("content/page.md", "hello"),
+        ("templates/page.html", "<main>{% include \"missing.html\" %}</main>"),
+    ]);
+
+    let error = render(&root, "page.html").unwrap_err().to_string();
+    assert!(error.contains("page.html"), "{error}");
+    assert!(error.contains("missing.html"), "{error}");
+}
+
+/// An engine that implements only the required methods, by way of minijinja.
+#[derive(Debug)]
+struct MinimalEngine(MiniJinjaEngine);
+
+impl Engine for MinimalEngine {
+    fn render(
+        &self,
+        name: &str,
+        site: &Arc<Site>,
+        collection: Option<&Arc<Collection>>,
+        item: &Arc<Item>,
+    ) -> harper::error::Result<String> {
+        self.0.render(name, site, collection, item)
+    }
+
+    fn render_raw(
+        &self,
+        name: Option<&str>,
+        template_str: &str,
+        site: &Arc<Site>,
+        collection: Option<&Arc<Collection>>,
+        item: &Arc<Item>,
+    ) -> harper::error::Result<String> {
+        self.0.render_raw(name, template_str, site, collection, item)
+    }
+
+    fn render_str(
+        &self,
+        name: Option<&str>,
+        template_str: &str,
+        meta: Metadata,
+    ) -> harper::error::Result<String> {
+        self.0.render_str(name, template_str, meta)
+    }
+}
+
+#[test]
+fn render_value_takes_any_dictionary() {
+    let root = site_dir("render-value", &[("page.md", "")]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let minimal = MinimalEngine(MiniJinjaEngine::init(tree, None, ()));
+    let context = || Value::from(Dict::<&str, Value>::from([
+        ("name", Value::from("docs")),
+        ("tags", Value::from(vec!["a", "b"])),
+    ]));
+
+    let template = "{{ name }}: {{ tags | join(', ') }}";
+    assert_eq!(engine.render_value(None, template, context()).unwrap(), "docs: a, b");
+    assert_eq!(minimal.render_value(None, template, context()).unwrap(), "docs: a, b");
+    assert_eq!(minimal.render_value(None, "empty", Value::Null).unwrap(), "empty");
+
+    let error = minimal.render_value(None, "{{ x }}", Value::from(1)).unwrap_err();
+    assert!(error.to_string().contains("must be a dictionary"), "{error}");
+    assert!(minimal.compile("missing.html").is_ok());
+}
+
+#[test]
+fn date_converts_to_timezone() {
+    let root = site_dir("date-tz", &[("page.md", "")]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "page.md").unwrap());
+    item.metadata.insert_raw("at", "2024-01-15T12:00:00Z");
+    item.metadata.insert_raw("day", "2024-01-15T12:00:00");
+
+    let site = Arc::new(site);
+    let utc = MiniJinjaEngine::init(tree.clone(), None, ());
+    let tokyo = MiniJinjaEngine::init(tree.clone(), None, Dict::from([("timezone", "Asia/Tokyo")]));
+    let render = |engine: &MiniJinjaEngine, template: &str| {
+        engine.render_raw(None, template, &site, None, &item)
+    };
+
+    let fmt = "'%Y-%m-%d %H:%M %Z'";
+    let at = |tz: &str| format!("{{{{ at | date({fmt}{tz}) }}}}");
+    assert_eq!(render(&utc, &at("")).unwrap(), "2024-01-15 12:00 UTC");
+    assert_eq!(render(&utc, &at(", tz='America/New_York'")).unwrap(), "2024-01-15 07:00 EST");
+    assert_eq!(render(&tokyo, &at("")).unwrap(), "2024-01-15 21:00 JST");
+    assert_eq!(render(&tokyo, &at(", tz='UTC'")).unwrap(), "2024-01-15 12:00 UTC");
+
+    // Naive datetimes aren't instants and are formatted as they are.
+    let day = "{{ day | date('%Y-%m-%d %H:%M', tz='Asia/Tokyo') }}";
+    assert_eq!(render(&utc, day).unwrap(), "2024-01-15 12:00");
+
+    let error = render(&utc, &at(", tz='Mars/Olympus_Mons'")).unwrap_err().to_string();
+    assert!(error.contains("invalid timezone Mars/Olympus_Mons"), "{error}");
+
+    let mars = MiniJinjaEngine::init(tree.clone(), None, Dict::from([("timezone", "Mars/Olympus_Mons")]));
+    let error = render(&mars, &at("")).unwrap_err().to_string();
+    assert!(error.contains("invalid timezone Mars/Olympus_Mons"), "{error}");
+}
+
+#[test]
+fn load_reads_data_files() {
+    let root = site_dir("load-reads-data-files", &[
+        ("page.md", ""),
+        ("data/team.toml", "lead = \"Ana\"\n[[members]]\nname = \"Bo\""),
+        ("data/links.json", r#"{"home": "/", "count": 2}"#),
+        ("data/menu.yaml", "items:\n  - Home\n  - Blog"),
+        ("data/notes.txt", "hi"),
+        ("data/broken.json", "{"),
+    ]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "page.md").unwrap());
+    let site = Arc::new(site);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let render = |template: &str| engine.render_raw(None, template, &site, None, &item);
+
+    let toml = "{% set t = load('data/team.toml') %}{{ t.lead }} {{ t.members[0].name }}";
+    assert_eq!(render(toml).unwrap(), "Ana Bo");
+    let json = "{% set l = load('./data/links.json') %}{{ l.home }} {{ l.count }}";
+    assert_eq!(render(json).unwrap(), "/ 2");
+    let yaml = "{% set m = load('data/menu.yaml') %}{{ m['items'] | join(', ') }}";
+    assert_eq!(render(yaml).unwrap(), "Home, Blog");
+
+    let error = |template: &str| render(template).unwrap_err().to_string();
+    let missing = error("{{ load('data/missing.toml') }}");
+    assert!(missing.contains("data file data/missing.toml does not exist"), "{missing}");
+    let unsupported = error("{{ load('data/notes.txt') }}");
+    assert!(unsupported.contains("unsupported data file format `txt`"), "{unsupported}");
+    let broken = error("{{ load('data/broken.json') }}");
+    assert!(broken.contains("failed to parse data/broken.json"), "{broken}");
+
+    for path in ["../site/data/team.toml", "data/../data/team.toml", "/site/data/team.toml"] {
+        let escape = error(&format!("{{{{ load('{path}') }}}}"));
+        assert!(escape.contains("must be relative to the site root"), "{escape}");
+    }
+}
+
+#[test]
+fn timeago_is_relative_to_now() {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let root = site_dir("timeago-is-relative-to-now", &[("page.md", "")]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "page.md").unwrap());
+    item.metadata.insert_raw("past", now - 3 * 3600);
+    item.metadata.insert_raw("future", now + 2 * 86400 + 600);
+    item.metadata.insert_raw("recent", now - 10);
+
+    let site = Arc::new(site);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let render = |template: &str| engine.render_raw(None, template, &site, None, &item);
+
+    assert_eq!(render("{{ past | timeago }}").unwrap(), "3 hours ago");
+    assert_eq!(render("{{ past | timeago('days') }}").unwrap(), "just now");
+    assert_eq!(render("{{ future | timeago }}").unwrap(), "in 2 days");
+    assert_eq!(render("{{ recent | timeago('minute') }}").unwrap(), "just now");
+    assert!(render("{{ past | timeago('fortnight') }}").is_err());
+}
+
+#[test]
+fn now_exposes_current_time() {
+    let root = site_dir("now-exposes-current-time", &[("page.md", "")]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "page.md").unwrap());
+
+    let site = Arc::new(site);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let render = |template: &str| engine.render_raw(None, template, &site, None, &item).unwrap();
+
+    let before = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let timestamp: u64 = render("{{ now().timestamp }}").parse().unwrap();
+    assert!(timestamp >= before && timestamp <= before + 60, "{timestamp} vs {before}");
+
+    let year: i32 = render("{{ now().year }}").parse().unwrap();
+    assert!(year >= 2024, "{year}");
+    assert!((1..=12).contains(&render("{{ now().month }}").parse::<u32>().unwrap()));
+    assert_eq!(render("{{ now() | date('%s') }}"), render("{{ now().timestamp }}"));
+    assert!(render("{{ now() }}").ends_with("+00:00"));
+}
+#[test]
+fn undefined_variable_reports_location() {
+    let root = site_dir("undefined", &[
+        ("content/page.md", "hello"),
+        ("templates/page.html", "<main>\n  {{ nonexistent }}\n</main>"),
+    ]);
+
+    let error = render(&root, "page.html").unwrap_err().to_string();
+    assert!(error.contains("page.html"), "{error}");
+    assert!(error.contains("line: 2"), "{error}");
+    assert!(error.contains("column: 6"), "{error}");
+    assert!(error.contains("undefined: nonexistent"), "{error}");
+}
+
+#[test]
+fn collection_count_first_last() {
+    let root = site_dir("collection-count-first-last", &[
+        ("guide/index.md", ""),
+        ("guide/a.md", ""),
+        ("guide/b.md", ""),
+        ("guide/c.md", ""),
+    ]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    let collection = site.get_or_insert_collection(|| "guide".into(), id("guide"));
+    let index = collection.set_index_item(id("guide/index.md"));
+    for (path, title) in [("guide/a.md", "A"), ("guide/b.md", "B"), ("guide/c.md", "C")] {
+        collection.new_item(id(path)).metadata.insert_raw("title", title);
+    }
+
+    collection.items.get(2).unwrap().metadata.insert_raw("draft", true);
+    let collection = site.collections[&id("guide")].clone();
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let template = "{{ collection.count }} {{ collection.first.title }} \
+        {{ collection.last.title }}";
+    let mut site = Arc::new(site);
+    let output = engine.render_raw(None, template, &site, Some(&collection), &index);
+    assert_eq!(output.unwrap(), "2 A B");
+
+    // Drafts are only left out of production builds.
+    Arc::get_mut(&mut site).unwrap().drafts = true;
+    let output = engine.render_raw(None, template, &site, Some(&collection), &index);
+    assert_eq!(output.unwrap(), "3 A C");
+}
+
+#[test]
+fn collection_parent_and_children() {
+    let root = site_dir("collection-parent-and-children", &[
+        ("guide/index.md", ""),
+        ("guide/advanced/index.md", ""),
+        ("guide/advanced/deep/nested/index.md", ""),
+        ("guide/basics/index.md", ""),
+    ]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    let mut index = None;
+    for name in ["guide", "guide/advanced", "guide/advanced/deep/nested", "guide/basics"] {
+        let collection = site.get_or_insert_collection(|| name.into(), id(name));
+        let item = collection.set_index_item(id(&format!("{name}/index.md")));
+        item.metadata.insert_raw("title", name);
+        index.get_or_insert(item);
+    }
+
+    let site = Arc::new(site);
+    let guide = &site.collections[&id("guide")];
+    let nested = &site.collections[&id("guide/advanced/deep/nested")];
+    assert!(guide.parent(&site).is_none());
+    assert_eq!(nested.parent(&site).unwrap().entry.id, id("guide/advanced"));
+    assert_eq!(guide.children(&site).len(), 2);
+
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let template = "{% for c in collection.children %}{{ c.index.title }} \
+        {% for n in c.children %}[{{ n.parent.index.title }}]{% endfor %}{% endfor %}\
+        {{ collection.parent is undefined }}";
+    let output = engine.render_raw(None, template, &site, Some(guide), &index.unwrap());
+    assert_eq!(output.unwrap(), "guide/advanced [guide/advanced]guide/basics true");
+}
+
+#[test]
+fn item_kind_and_group() {
+    let root = site_dir("item-kind-and-group", &[
+        ("guide/index.md", ""),
+        ("guide/intro.md", ""),
+        ("guide/authors/ann.toml", ""),
+        ("guide/authors/bob.toml", ""),
+    ]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    let collection = site.get_or_insert_collection(|| "guide".into(), id("guide"));
+    let index = collection.set_index_item(id("guide/index.md"));
+    let item = collection.new_item(id("guide/intro.md"));
+    let ann = collection.new_datum(id("guide/authors"), id("guide/authors/ann.toml"));
+    let bob = collection.new_datum(id("guide/authors"), id("guide/authors/bob.toml"));
+    bob.metadata.insert_raw("kind", "author");
+
+    let collection = site.collections[&id("guide")].clone();
+    let site = Arc::new(site);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let render = |item| {
+        let template = "{{ kind }} {{ group is defined and group }}";
+        engine.render_raw(None, template, &site, Some(&collection), item).unwrap()
+    };
+
+    assert_eq!(render(&index), "index false");
+    assert_eq!(render(&item), "item false");
+    assert_eq!(render(&ann), "datum authors");
+    assert_eq!(render(&bob), "author authors");
+}
+
+#[test]
+fn relref_is_relative_to_current_url() {
+    let root = site_dir("relref-is-relative-to-current-url", &[
+        ("guide/index.md", ""),
+        ("guide/intro.md", ""),
+        ("guide/basics.md", ""),
+        ("blog/index.md", ""),
+        ("blog/post.md", ""),
+    ]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    let mut items = vec![];
+    for (name, files) in [("guide", &["intro", "basics"][..]), ("blog", &["post"][..])] {
+        let collection = site.get_or_insert_collection(|| name.into(), id(name));
+        let index = collection.set_index_item(id(&format!("{name}/index.md")));
+        index.metadata.insert_raw("url", format!("https://example.com/{name}/"));
+        for file in files {
+            let item = collection.new_item(id(&format!("{name}/{file}.md")));
+            item.metadata.insert_raw("url", format!("https://example.com/{name}/{file}/"));
+            items.push(item);
+        }
+    }
+
+    let collection = site.collections[&id("guide")].clone();
+    let site = Arc::new(site);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let render = |template: &str| {
+        engine.render_raw(None, template, &site, Some(&collection), &items[0])
+    };
+
+    assert_eq!(render("{{ relref('basics.md') }}").unwrap(), "../basics/");
+    assert_eq!(render("{{ relref('basics') }}").unwrap(), "../basics/");
+    assert_eq!(render("{{ relref('intro') }}").unwrap(), "./");
+    assert_eq!(render("{{ relref('index') }}").unwrap(), "../");
+    assert_eq!(render("{{ relref('post', collection='blog') }}").unwrap(), "../../blog/post/");
+
+    let error = render("{{ relref('missing') }}").unwrap_err().to_string();
+    assert!(error.contains("no item \"missing\""), "{error}");
+    assert!(render("{{ relref('post', collection='nope') }}").is_err());
+}
+
+#[test]
+fn ref_resolves_content_path() {
+    let root = site_dir("ref-resolves-content-path", &[
+        ("content/posts/index.md", ""),
+        ("content/posts/hello.md", ""),
+        ("content/posts/draft.md", ""),
+    ]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    site.content_roots = vec![id("content")];
+    let collection = site.get_or_insert_collection(|| "posts".into(), id("content/posts"));
+    let index = collection.set_index_item(id("content/posts/index.md"));
+    let hello = collection.new_item(id("content/posts/hello.md"));
+    hello.metadata.insert_raw("url", "/posts/hello-world/");
+    collection.new_item(id("content/posts/draft.md"));
+
+    let collection = site.collections[&id("content/posts")].clone();
+    let site = Arc::new(site);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let render = |template| engine.render_raw(None, template, &site, Some(&collection), &index);
+
+    assert_eq!(render("{{ ref('posts/hello.md') }}").unwrap(), "/posts/hello-world/");
+    let missing = render("{{ ref('posts/nope.md') }}").unwrap_err().to_string();
+    assert!(missing.contains("no content item"), "{missing}");
+    let unrendered = render("{{ ref('posts/draft.md') }}").unwrap_err().to_string();
+    assert!(unrendered.contains("doesn't have a URL"), "{unrendered}");
+}
+
+#[test]
+fn render_cache_keys_on_context() {
+    let root = site_dir("render-cache-keys-on-context", &[("page.md", "")]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+    let engine = MiniJinjaEngine::init_cached(tree, None, ());
+    let render = |x: usize| {
+        let context = Value::from(Dict::<&str, usize>::from([("x", x)]));
+        engine.render_value(Some("x"), "{{ x }}", context).unwrap()
+    };
+
+    assert_eq!(render(1), "1");
+    assert_eq!(render(2), "2");
+    assert_eq!(render(1), "1");
+}
+
+#[test]
+fn default_treats_empty_as_missing() {
+    let root = site_dir("default-treats-empty-as-missing", &[("page.md", "")]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "page.md").unwrap());
+    item.metadata.insert_raw("null", Value::Null);
+    item.metadata.insert_raw("empty", "");
+    item.metadata.insert_raw("blank", " ");
+    item.metadata.insert_raw("tags", Value::Array(Arc::new(vec![])));
+    item.metadata.insert_raw("zero", 0);
+
+    let site = Arc::new(site);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let render = |template: &str| engine.render_raw(None, template, &site, None, &item).unwrap();
+
+    assert_eq!(render("{{ missing | default('a') }}{{ null | default('b') }}"), "ab");
+    assert_eq!(render("{{ empty | default('c') }}{{ tags | default('d') }}"), "cd");
+    assert_eq!(render("[{{ blank | default('e') }}][{{ zero | default('f') }}]"), "[ ][0]");
+    assert_eq!(render("{{ zero | default('g', true) }}{{ null | default }}"), "g");
+    assert_eq!(render("[{{ empty | default('h', strings=false) }}]"), "[]");
+    assert_eq!(render("{{ tags | default('i', sequences=false) | length }}"), "0");
+    assert_eq!(render("{{ empty | coalesce('j') }}{{ empty | d('k') }}"), "jk");
+}
+
+#[test]
+fn collection_merged_data() {
+    let root = site_dir("collection-merged-data", &[
+        ("docs/index.md", ""),
+        ("docs/config/a.toml", ""),
+        ("docs/config/b.toml", ""),
+        ("docs/extra/c.toml", ""),
+    ]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    let collection = site.get_or_insert_collection(|| "docs".into(), id("docs"));
+    let index = collection.set_index_item(id("docs/index.md"));
+    let a = collection.new_datum(id("docs/config"), id("docs/config/a.toml"));
+    a.metadata.insert_raw("name", "a");
+    let nav = Dict::<&str, &str>::from([("home", "/"), ("blog", "/blog")]);
+    a.metadata.insert_raw("nav", Value::from(nav));
+    let b = collection.new_datum(id("docs/config"), id("docs/config/b.toml"));
+    b.metadata.insert_raw("name", "b");
+    let c = collection.new_datum(id("docs/extra"), id("docs/extra/c.toml"));
+    c.metadata.insert_raw("nav", Value::from(Dict::<&str, &str>::from([("blog", "/news")])));
+
+    let collection = site.collections[&id("docs")].clone();
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let template = "{{ collection.merged_data.name }} {{ collection.merged_data.nav.home }} \
+        {{ collection.merged_data.nav.blog }} {{ collection.data.config[0].name }}";
+    let output = engine.render_raw(None, template, &Arc::new(site), Some(&collection), &index);
+    assert_eq!(output.unwrap(), "b / /news a");
+}
+
+#[test]
+fn join_rejects_invalid_urls() {
+    let root = site_dir("join-rejects-invalid-urls", &[("page.md", "")]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "page.md").unwrap());
+    let globals: Dict<&str, &str> = Dict::from([("root", "https://example.com")]);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, globals);
+    let site = Arc::new(site);
+    let render = |template: &str| engine.render_raw(None, template, &site, None, &item);
+
+    assert_eq!(render("{{ join('docs', 'a%20b') }}").unwrap(), "https://example.com/docs/a%20b");
+    for hostile in ["a b", "caf\u{e9}", "\"><script>", "a\nb"] {
+        let template = format!("{{{{ join('docs', {hostile:?}) }}}}");
+        let error = render(&template).unwrap_err().to_string();
+        assert!(error.contains("isn't a valid URL"), "{hostile:?}: {error}");
+    }
+}
+
+#[test]
+fn concat_and_merge_filters() {
+    let root = site_dir("concat-and-merge-filters", &[
+        ("guide/index.md", ""),
+        ("guide/a.md", ""),
+        ("guide/b.md", ""),
+        ("blog/index.md", ""),
+        ("blog/c.md", ""),
+    ]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    for (name, files) in [("guide", &["a", "b"][..]), ("blog", &["c"][..])] {
+        let collection = site.get_or_insert_collection(|| name.into(), id(name));
+        collection.set_index_item(id(&format!("{name}/index.md")));
+        for file in files {
+            let item = collection.new_item(id(&format!("{name}/{file}.md")));
+            item.metadata.insert_raw("title", file.to_uppercase());
+        }
+    }
+
+    let collection = site.collections[&id("guide")].clone();
+    let item = collection.items.get(0).unwrap().clone();
+    let nav: Dict<&str, &str> = Dict::from([("home", "/"), ("blog", "/blog")]);
+    let extra: Dict<&str, Value> = Dict::from([("nav", Value::from(nav)), ("theme", "dark".into())]);
+    item.metadata.insert_raw("extra", extra);
+
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let site = Arc::new(site);
+    let render = |template: &str| {
+        engine.render_raw(None, template, &site, Some(&collection), &item)
+    };
+
+    let titles = "| map(attribute='title') | join(',') }}";
+    let both = "collection.items | concat(site.collections.blog.items";
+    assert_eq!(render(&format!("{{{{ {both}) {titles}")).unwrap(), "A,B,C");
+    assert_eq!(render(&format!("{{{{ {both}, collection.items) {titles}")).unwrap(), "A,B,C,A,B");
+    assert_eq!(render(&format!("{{{{ {both}, collection.items, unique=true) {titles}")).unwrap(), "A,B,C");
+    assert_eq!(render("{{ [1] | concat(none, [2, 1], unique=true) | join(',') }}").unwrap(), "1,2");
+    assert!(render("{{ [1] | concat('ab') }}").is_err());
+
+    let deep = "{% set m = extra | merge({'nav': {'docs': '/docs'}, 'theme': 'light'}) %}\
+        {{ m.theme }} {{ m.nav.home }} {{ m.nav.docs }}";
+    assert_eq!(render(deep).unwrap(), "light / /docs");
+
+    let shallow = "{% set m = extra | merge({'nav': {'docs': '/docs'}}, deep=false) %}\
+        {{ m.nav.home is defined }} {{ m.nav.docs }} {{ m.theme }}";
+    assert_eq!(render(shallow).unwrap(), "false /docs dark");
+    assert!(render("{{ extra | merge([1]) }}").is_err());
+}
+
+
+#[test]
+fn floats_render_and_format_as_dates() {
+    let root = site_dir("floats-render-and-format-as-dates", &[("page.md", "")]);
+    let tree = Arc::new(FsTree::build(&root).unwrap());
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "page.md").unwrap());
+    item.metadata.insert_raw("mass", 1.25f64);
+    item.metadata.insert_raw("at", 86400.5f64);
+
+    let site = Arc::new(site);
+    let engine = MiniJinjaEngine::init(tree.clone(), None, ());
+    let render = |template: &str| engine.render_raw(None, template, &site, None, &item).unwrap();
+
+    assert_eq!(render("{{ mass }} {{ mass * 2 }} {{ mass > 1 }}"), "1.25 2.5 true");
+    assert_eq!(render("{{ at | date('%Y-%m-%d %H:%M:%S%.3f', tz='UTC') }}"), "1970-01-02 00:00:00.500");
+}
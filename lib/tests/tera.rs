@@ -0,0 +1,100 @@
+#![cfg(feature = "tera")]
+
+use std::sync::Arc;
+
+use harper::Site;
+use harper::fstree::FsTree;
+use harper::taxonomy::Metadata;
+use harper::value::Dict;
+use harper::templating::{Engine, EngineInit};
+use harper::templating::tera::TeraEngine;
+
+#[test]
+fn renders_templates_with_includes_and_inheritance() {
+    let tree = Arc::new(FsTree::from_memory("/site", [
+        ("content/page.md", ""),
+        ("templates/base.html", "<main>{% block main %}{% endblock %}</main>"),
+        ("templates/page.html", "{% extends \"base.html\" %}{% block main %}\
+            {% include \"nav.html\" %}{{ content | safe }}{% endblock %}"),
+        ("templates/includes/nav.html", "<nav>{{ title }}</nav>"),
+    ]).unwrap());
+
+    let engine = TeraEngine::init(tree.clone(), tree.get_id(None, "templates"), ());
+    engine.compile("page.html").unwrap();
+    assert!(engine.compile("missing.html").is_err());
+
+    let mut site = Site::new(tree.clone());
+    let item = site.new_resource(tree.get_id(None, "content/page.md").unwrap());
+    item.metadata.insert_raw("title", "Hi & Bye");
+    item.metadata.insert_raw("content", "<p>Body</p>");
+
+    let output = engine.render("page.html", &Arc::new(site), None, &item).unwrap();
+    assert_eq!(output, "<main><nav>Hi &amp; Bye</nav><p>Body</p></main>");
+}
+
+#[test]
+fn collection_navigation_and_listing() {
+    let tree = Arc::new(FsTree::from_memory("/site", [
+        ("guide/index.md", ""),
+        ("guide/a.md", ""),
+        ("guide/b.md", ""),
+        ("guide/c.md", ""),
+    ]).unwrap());
+
+    let id = |path: &str| tree.get_id(None, path).unwrap();
+    let mut site = Site::new(tree.clone());
+    let collection = site.get_or_insert_collection(|| "guide".into(), id("guide"));
+    let index = collection.set_index_item(id("guide/index.md"));
+    index.metadata.insert_raw("title", "Guide");
+    for (path, title) in [("guide/a.md", "A"), ("guide/b.md", "B"), ("guide/c.md", "C")] {
+        collection.new_item(id(path)).metadata.insert_raw("title", title);
+    }
+
+    collection.items.get(1).unwrap().metadata.insert_raw("draft", true);
+    let collection = site.collections[&id("guide")].clone();
+    let site = Arc::new(site);
+    let engine = TeraEngine::init(tree.clone(), None, ());
+    let render = |item| {
+        let template = "{{ kind }} {{ is_index }} {{ position | default(value='-') }} \
+            [{% if previous %}{{ previous.title }}{% endif %}] \
+            [{% if next %}{{ next.title }}{% endif %}] {{ collection.count }}:\
+            {% for item in collection.items %}{{ item.title }}{% endfor %}";
+
+        engine.render_raw(None, template, &site, Some(&collection), item).unwrap()
+    };
+
+    assert_eq!(render(&index), "index true - [] [A] 2:AC");
+    assert_eq!(render(collection.items.get(0).unwrap()), "item false 0 [Guide] [C] 2:AC");
+    assert_eq!(render(collection.items.get(2).unwrap()), "item false 2 [A] [] 2:AC");
+}
+
+#[test]
+fn ported_functions_and_filters() {
+    let tree = Arc::new(FsTree::from_memory("/site", [("page.md", "")]).unwrap());
+    let globals: Dict<&str, &str> = Dict::from([
+        ("root", "https://example.com/docs"),
+        ("timezone", "America/New_York"),
+    ]);
+
+    let engine = TeraEngine::init(tree.clone(), None, globals);
+    let render = |template: &str| {
+        let metadata = Metadata::new();
+        metadata.insert_raw("slug", "hello-big-world");
+        metadata.insert_raw("day", "2024-03-05");
+        metadata.insert_raw("stamp", 1_700_000_000);
+        engine.render_str(None, template, metadata)
+    };
+
+    assert_eq!(render("{{ join(path='a/') }}").unwrap(), "https://example.com/docs/a/");
+    assert_eq!(render("{{ join(paths=['a/', 'b']) }}").unwrap(), "https://example.com/docs/a/b");
+    assert!(render("{{ join(path='a b') }}").is_err());
+    assert_eq!(render("{{ slug | deslug }}").unwrap(), "hello big world");
+    assert_eq!(render("{{ day | date(format='%d/%m/%Y') }}").unwrap(), "05/03/2024");
+    assert_eq!(render("{{ stamp | date(format='%H:%M') }}").unwrap(), "17:13");
+    assert_eq!(render("{{ stamp | date(format='%H:%M', tz='UTC') }}").unwrap(), "22:13");
+    assert_eq!(render("{{ slug | split(pat='-') | length }}").unwrap(), "3");
+    assert_eq!(render("{{ slug | split(pat='-', n=1) }}").unwrap(), "big");
+    assert_eq!(render("{{ G | get(key='root') }}").unwrap(), "https://example.com/docs");
+    assert_eq!(render("{{ G | get(key='nope', default='x') }}").unwrap(), "x");
+    assert_eq!(render("{{ now() | date(format='%Y') }}").unwrap().len(), 4);
+}
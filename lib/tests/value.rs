@@ -0,0 +1,117 @@
+use harper::url::UrlBuf;
+use harper::value::{Dict, Format, Json, List, Mapper, Toml, Value};
+
+fn field(value: &Value, key: &str) -> Value {
+    match value {
+        Value::Dict(dict) => dict[key].clone(),
+        _ => panic!("expected a dictionary, found {value:?}"),
+    }
+}
+
+#[test]
+fn toml_datetimes_normalize_to_strings() {
+    let value = Toml.map(r#"
+        offset = 1979-05-27T07:32:00Z
+        local = 1979-05-27T07:32:00
+        date = 1979-05-27
+        nested = { dates = [1979-05-27, 2024-01-01] }
+    "#.to_string()).unwrap();
+
+    assert_eq!(field(&value, "offset").as_str(), Some("1979-05-27T07:32:00Z"));
+    assert_eq!(field(&value, "local").as_str(), Some("1979-05-27T07:32:00"));
+    assert_eq!(field(&value, "date").as_str(), Some("1979-05-27"));
+
+    let dates = field(&field(&value, "nested"), "dates");
+    let Value::Array(dates) = dates else { panic!("expected an array") };
+    assert_eq!(dates[0].as_str(), Some("1979-05-27"));
+    assert_eq!(dates[1].as_str(), Some("2024-01-01"));
+}
+
+#[test]
+fn json_and_toml_dates_agree() {
+    let toml = Toml.map("date = 2024-03-01T10:00:00Z".to_string()).unwrap();
+    let json = Json.map(r#"{ "date": "2024-03-01T10:00:00Z" }"#.to_string()).unwrap();
+    assert_eq!(field(&toml, "date"), field(&json, "date"));
+}
+
+#[test]
+fn merge_is_deep_and_right_biased() {
+    let base = Toml.map(r#"
+        title = "Base"
+        tags = ["a", "b"]
+        social = { github = "base", email = "base@example.com" }
+    "#.to_string()).unwrap();
+
+    let other = Toml.map(r#"
+        tags = ["c"]
+        social = { github = "other", mastodon = "@other" }
+        extra = true
+    "#.to_string()).unwrap();
+
+    let merged = base.merge(other);
+    assert_eq!(field(&merged, "title").as_str(), Some("Base"));
+    assert_eq!(field(&merged, "tags").as_slice().map(|s| s.len()), Some(1));
+    assert_eq!(field(&merged, "extra"), Value::Bool(true));
+
+    let social = field(&merged, "social");
+    assert_eq!(field(&social, "github").as_str(), Some("other"));
+    assert_eq!(field(&social, "email").as_str(), Some("base@example.com"));
+    assert_eq!(field(&social, "mastodon").as_str(), Some("@other"));
+    assert_eq!(Value::Bool(true).merge(merged.clone()), merged);
+}
+
+#[test]
+fn to_json_is_pretty_and_sorted() {
+    let value = Toml.map("b = 1\na = [true, \"x\"]\n".to_string()).unwrap();
+    assert_eq!(value.to_json(), "{\n  \"a\": [\n    true,\n    \"x\"\n  ],\n  \"b\": 1\n}");
+}
+
+#[test]
+fn urls_deserialize_only_if_valid() {
+    let urls: Dict<String, UrlBuf> = Toml::from_str("a = '/docs/a%20b'").unwrap();
+    assert_eq!(urls["a"].as_str(), "/docs/a%20b");
+
+    for hostile in [r"/a b", r"/caf\u00e9", r#"/\"<x>"#, r"\u0000"] {
+        let toml = format!("a = \"{hostile}\"");
+        let error = Toml::from_str::<Dict<String, UrlBuf>>(&toml).unwrap_err().to_string();
+        assert!(error.contains("invalid URL"), "{hostile:?}: {error}");
+    }
+
+    assert_eq!(UrlBuf::from("/a b\u{0}").as_str(), "/a%20b%00");
+    assert_eq!(UrlBuf::from("<x>".to_string()).as_str(), "%3Cx%3E");
+}
+
+
+#[test]
+fn floats_deserialize_compare_and_convert() {
+    let value = Toml.map("mass = 1.25\ncount = 3\nwhole = 2.0".to_string()).unwrap();
+    assert_eq!(field(&value, "mass"), Value::from(1.25f64));
+    assert_eq!(field(&value, "whole"), Value::from(2u8));
+    assert!(field(&value, "mass") < field(&value, "count"));
+    assert_eq!(value, Json.map(r#"{"mass": 1.25, "count": 3.0, "whole": 2}"#.to_string()).unwrap());
+
+    assert_eq!(f64::try_from(field(&value, "mass")), Ok(1.25));
+    assert_eq!(f64::try_from(field(&value, "count")), Ok(3.0));
+    assert_eq!(u8::try_from(field(&value, "whole")), Ok(2));
+    assert!(u8::try_from(field(&value, "mass")).is_err());
+    assert_eq!(i64::try_from(Value::from(-3.0f32)), Ok(-3));
+    assert_eq!(value.to_json(), "{\n  \"count\": 3,\n  \"mass\": 1.25,\n  \"whole\": 2.0\n}");
+}
+
+#[test]
+fn list_iterates_from_both_ends() {
+    let list = List::default();
+    for i in 1..=4 {
+        list.push(i);
+    }
+
+    assert_eq!(list.iter().next_back(), Some(&4));
+    assert_eq!(list.iter().rev().copied().collect::<Vec<_>>(), [4, 3, 2, 1]);
+    assert_eq!(list.iter().take(2).count(), 2);
+
+    let mut iter = list.iter();
+    assert_eq!((iter.next(), iter.next_back()), (Some(&1), Some(&4)));
+    assert_eq!(iter.len(), 2);
+    assert_eq!((iter.next_back(), iter.next()), (Some(&3), Some(&2)));
+    assert_eq!((iter.next(), iter.next_back()), (None, None));
+}
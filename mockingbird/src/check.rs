@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use harper::{error, Renderer, Site};
+use harper::error::{Error, Chainable};
+
+use crate::Template;
+use crate::discover::Mockingbird;
+use crate::util::ValueExt;
+
+impl Mockingbird {
+    /// Compiles every template in the templates root and dry renders the
+    /// first item of each collection. Returns every error encountered.
+    pub fn check(&self, site: &Arc<Site>) -> Vec<Error> {
+        let mut errors = vec![];
+        if let Some(root) = self.template_root {
+            let root = &self.tree[root];
+            for entry in self.tree.iter_depth_first(root.id).files() {
+                if entry.file_name.starts_with('.') {
+                    continue;
+                }
+
+                let name = entry.path_relative_to(root).unwrap().to_string_lossy();
+                if let Err(e) = self.config.engine.compile(&name) {
+                    errors.push(e.chain(error!("template failed to compile", "template" => name)));
+                }
+            }
+        }
+
+        for collection in site.collections.values() {
+            if let Err(e) = self.render_collection(site, collection) {
+                errors.push(e);
+                continue;
+            }
+
+            let Some(item) = collection.index.as_ref().or_else(|| collection.items.get(0)) else {
+                continue;
+            };
+
+            let template = match item.metadata.get(Template) {
+                Some(Ok(template)) => template,
+                Some(Err(e)) => {
+                    errors.push(e.type_err(Template, "invalid template value"));
+                    continue;
+                }
+                None => continue,
+            };
+
+            let result = self.config.engine
+                .render(template.as_str(), site, Some(collection), item)
+                .chain_with(|| error! {
+                    "failed to dry render item",
+                    "path" => item.entry.relative_path().display(),
+                    "template used" => template.as_str(),
+                });
+
+            if let Err(e) = result {
+                errors.push(e);
+            }
+        }
+
+        errors
+    }
+}
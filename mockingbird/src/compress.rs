@@ -0,0 +1,69 @@
+use std::io::Write;
+use std::path::Path;
+
+use harper::error;
+use harper::error::{Chainable, Result};
+use harper::fstree::FsTree;
+use harper::manifest::MANIFEST_FILE;
+use harper::rayon::prelude::*;
+
+use crate::config::{Build, Encoding};
+use crate::incremental::CACHE_FILE;
+use crate::discover::Mockingbird;
+
+/// Extensions of text outputs worth compressing. Other outputs, like images,
+/// fonts, and archives, are typically compressed already.
+const TEXT_EXTS: &[&str] = &[
+    "html", "htm", "css", "js", "mjs", "json", "xml", "svg", "txt", "csv", "map", "md",
+];
+
+impl Mockingbird {
+    /// Writes a compressed variant of every text file in the output directory
+    /// at least `min_size` bytes large for each configured encoding. Variants
+    /// are written next to the file with the encoding's extension appended.
+    pub fn precompress(&self) -> Result<()> {
+        let build = &self.config.settings.build;
+        if build.precompress.is_empty() || !self.output.exists() {
+            return Ok(());
+        }
+
+        let tree = FsTree::build(&self.output)?;
+        let files: Vec<_> = tree.iter()
+            .filter(|e| e.file_type.is_file() && e.file_name != MANIFEST_FILE && e.file_name != CACHE_FILE)
+            .filter(|e| e.file_ext().is_some_and(|ext| TEXT_EXTS.contains(&ext)))
+            .filter(|e| e.fs_metadata().is_some_and(|m| m.len() >= build.min_size))
+            .collect();
+
+        files.par_iter()
+            .flat_map(|entry| build.precompress.par_iter().map(move |&encoding| (entry, encoding)))
+            .try_for_each(|(entry, encoding)| compress(&entry.path, encoding, build))
+    }
+}
+
+fn compress(path: &Path, encoding: Encoding, build: &Build) -> Result<()> {
+    let data = std::fs::read(path)?;
+    let mut compressed = vec![];
+    match encoding {
+        Encoding::Gzip => {
+            let level = flate2::Compression::new(build.gzip_level.min(9));
+            let mut encoder = flate2::write::GzEncoder::new(&mut compressed, level);
+            encoder.write_all(&data)?;
+            encoder.finish()?;
+        }
+        Encoding::Brotli => {
+            let params = brotli::enc::BrotliEncoderParams {
+                quality: build.brotli_level.min(11) as i32,
+                ..Default::default()
+            };
+
+            brotli::BrotliCompress(&mut &data[..], &mut compressed, &params)?;
+        }
+    }
+
+    let mut output = path.as_os_str().to_owned();
+    output.push(encoding.extension());
+    std::fs::write(&output, compressed).chain_with(|| error! {
+        "failed to write compressed output",
+        "path" => Path::new(&output).display(),
+    })
+}
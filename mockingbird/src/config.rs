@@ -1,41 +1,626 @@
 use std::sync::Arc;
+use std::path::{Component, Path, PathBuf};
 
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 
-use harper::url::UrlBuf;
-use harper::value::{Toml, Format, Value};
-use harper::fstree::FsTree;
+use harper::url::{Url, UrlBuf};
+use harper::value::{Format, Json, Toml, Value, Yaml};
+use harper::fstree::{Entry, EntryId, FsTree};
+use harper::{err, error};
 use harper::error::Result;
 use harper::templating::{Engine, EngineInit};
+use harper::markdown::{AdmonitionConfig, CodeBlockStyle, DiagramConfig, MathConfig};
+use harper::util::SlugStyle;
+use harper::feed::FeedFormat;
 
 #[derive(Debug)]
 pub struct Config {
     pub engine: Arc<dyn Engine>,
     pub settings: Settings,
+    /// The config file the settings were read from, if any.
+    pub file: Option<EntryId>,
 }
 
 #[derive(Default, Debug, Deserialize, Serialize)]
 pub struct Settings {
+    /// The URL the site is served from, either absolute, with a scheme and
+    /// host, e.g. `https://example.com/docs`, or a path, e.g. `/docs`. It is
+    /// normalized to have no trailing `/`, and a path to have a leading one.
     #[serde(default)]
     pub root: UrlBuf,
     #[serde(default)]
     pub aliases: FxHashMap<String, String>,
+    /// The default timezone used by the `date` filter, e.g. `America/New_York`.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub content: ContentSettings,
+    /// The markup of highlighted code blocks.
+    #[serde(default)]
+    pub code: CodeBlockStyle,
+    /// Settings shared by the syntax highlighters.
+    #[serde(default)]
+    pub highlight: Highlight,
+    /// The classes and default titles of admonitions by name.
+    #[serde(default)]
+    pub admonitions: AdmonitionConfig,
+    /// Whether and how TeX math is wrapped for a client-side renderer.
+    #[serde(default)]
+    pub math: MathConfig,
+    /// Which fenced code blocks are diagrams for a client-side renderer.
+    #[serde(default)]
+    pub diagrams: DiagramConfig,
+    /// Which files and directories discovery ignores.
+    #[serde(default)]
+    pub hidden: Hidden,
+    /// Navigation menus by name, e.g. `main` for `[[menu.main]]` entries.
+    /// Exposed to templates as the `menu` global.
+    #[serde(default)]
+    pub menu: FxHashMap<String, Menu>,
+    /// Post-processing of the written output.
+    #[serde(default)]
+    pub build: Build,
+    /// The names of the site's source directories.
+    #[serde(default)]
+    pub dirs: Dirs,
+    /// Values injected into every item's metadata.
+    #[serde(default)]
+    pub inject: Inject,
+    /// RSS or Atom feeds of the site's content.
+    #[serde(default)]
+    pub feeds: Feeds,
+    /// How SCSS and Sass assets are compiled.
+    #[serde(default)]
+    pub sass: Sass,
     #[serde(flatten)]
     pub globals: FxHashMap<String, Value>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContentSettings {
+    /// Create a collection for every directory containing content files, even
+    /// if it doesn't contain an `index` file.
+    #[serde(default)]
+    pub auto_collections: bool,
+    /// Build an empty site, still copying assets, when the content root and
+    /// mounts contain no content files. Otherwise, that's an error. The
+    /// content root must exist either way.
+    #[serde(default)]
+    pub allow_empty: bool,
+    /// The metadata field, e.g. `title`, from which to derive an item's slug
+    /// when it doesn't set `slug`. Defaults to the file's stem.
+    #[serde(default)]
+    pub slug_from: Option<String>,
+    /// How heading anchors are derived from heading text: `harper`, the
+    /// default, or `github`, to match links to GitHub-rendered markdown.
+    #[serde(default)]
+    pub heading_slugs: SlugStyle,
+    /// How many levels to shift markdown headings down by, clamped at `h6`,
+    /// e.g. `1` for `#` to render as `<h2>` in templates with their own `<h1>`.
+    #[serde(default)]
+    pub heading_offset: u8,
+    /// Additional directories to treat as content roots.
+    #[serde(default)]
+    pub mounts: Vec<Mount>,
+    /// Write a `search-index.json` into each collection's output directory
+    /// indexing the collection's items.
+    #[serde(default)]
+    pub search_index: bool,
+    /// The file stems, e.g. `_index`, that identify a collection's index.
+    #[serde(default = "ContentSettings::default_index_stems")]
+    pub index_stems: Vec<String>,
+    /// A file stem prefix, e.g. `_data`, that forces a file into its
+    /// directory's data group instead of being an item.
+    #[serde(default)]
+    pub data_prefix: Option<String>,
+    /// How files nested more than one level below their collection are
+    /// grouped into data.
+    #[serde(default)]
+    pub data_grouping: DataGrouping,
+    /// Extensions, besides markdown's, of content files whose front matter is
+    /// extracted into metadata. The remaining body is stored as `content`.
+    #[serde(default = "ContentSettings::default_front_matter_exts")]
+    pub front_matter_exts: Vec<String>,
+    /// The minimum number of characters of text in an item's `snippet`.
+    #[serde(default = "ContentSettings::default_summary_length")]
+    pub summary_length: usize,
+    /// The text of a link to the item appended to truncated snippets, e.g.
+    /// `Read more`. No link is appended if unset.
+    #[serde(default)]
+    pub read_more: Option<String>,
+    /// The metadata field, e.g. `description`, of data items (TOML and JSON
+    /// files) from which to derive their `snippet`, `snippet_truncated`,
+    /// `word_count`, and `char_count`. The field's text is taken as plain text,
+    /// not markdown. Nothing is derived if unset.
+    #[serde(default)]
+    pub data_summary_from: Option<String>,
+    /// Names of files, e.g. `_redirects`, copied verbatim to the root of the
+    /// output, from the content root or else the site's root, as is, for hosts
+    /// that read them. They're never items and are never templatized, even
+    /// if hidden. Defaults to `_redirects`, `_headers`, and `CNAME`.
+    #[serde(default = "ContentSettings::default_passthrough")]
+    pub passthrough: Vec<String>,
+    /// Render items whose `date` is in the future or whose `expiry_date` has
+    /// passed. Otherwise, they're treated as drafts. Useful for previews.
+    #[serde(default)]
+    pub future: bool,
+    /// Render drafts and, as with `future`, embargoed items, for previews.
+    /// Drafts are then listed in collections like any other item. Otherwise,
+    /// as in production builds, they're neither rendered nor listed.
+    #[serde(default)]
+    pub drafts: bool,
+    /// Match template names, e.g. `Page.html` for `page.html`, without regard
+    /// to ASCII case when no template has exactly the name looked up.
+    #[serde(default)]
+    pub case_insensitive_templates: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataGrouping {
+    /// Group data files by their immediate parent directory.
+    #[default]
+    Parent,
+    /// Group data files by the top-level subdirectory of their collection.
+    TopLevel,
+}
+
+impl ContentSettings {
+    fn default_index_stems() -> Vec<String> {
+        vec!["index".into()]
+    }
+
+    fn default_front_matter_exts() -> Vec<String> {
+        vec!["html".into()]
+    }
+
+    fn default_summary_length() -> usize {
+        250
+    }
+
+    fn default_passthrough() -> Vec<String> {
+        vec!["_redirects".into(), "_headers".into(), "CNAME".into()]
+    }
+
+    /// Whether front matter is extracted from files with extension `ext`.
+    pub fn has_front_matter(&self, ext: Option<&str>) -> bool {
+        ext.is_some_and(|ext| self.front_matter_exts.iter().any(|e| e == ext))
+    }
+}
+
+impl Default for ContentSettings {
+    fn default() -> Self {
+        ContentSettings {
+            auto_collections: false,
+            allow_empty: false,
+            slug_from: None,
+            heading_slugs: SlugStyle::default(),
+            heading_offset: 0,
+            mounts: vec![],
+            search_index: false,
+            index_stems: Self::default_index_stems(),
+            data_prefix: None,
+            data_grouping: DataGrouping::Parent,
+            front_matter_exts: Self::default_front_matter_exts(),
+            summary_length: Self::default_summary_length(),
+            read_more: None,
+            data_summary_from: None,
+            passthrough: Self::default_passthrough(),
+            future: false,
+            drafts: false,
+            case_insensitive_templates: false,
+        }
+    }
+}
+
+/// Rules for files and directories, matched by name, ignored in discovery.
+/// Everything inside a hidden directory is hidden.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Hidden {
+    /// Hide names starting with `.`. Defaults to `true`.
+    pub dotfiles: bool,
+    /// Hide names starting with `_`, Jekyll-style, except for index files and
+    /// files with the data prefix. Defaults to `false`.
+    pub underscore: bool,
+    /// Hide names matching any of these globs, where `*` matches any run of
+    /// characters and `?` any single character.
+    pub patterns: Vec<String>,
+}
+
+impl Default for Hidden {
+    fn default() -> Self {
+        Hidden { dotfiles: true, underscore: false, patterns: vec![] }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Build {
+    /// Encodings to write precompressed variants of text outputs in, for
+    /// hosts that serve them directly.
+    pub precompress: Vec<Encoding>,
+    /// Outputs smaller than this many bytes aren't precompressed.
+    pub min_size: u64,
+    /// The gzip compression level, from 0 to 9. Defaults to 9.
+    pub gzip_level: u32,
+    /// The brotli compression level, from 0 to 11. Defaults to 11.
+    pub brotli_level: u32,
+    /// Memoize renders of identical template strings with identical contexts
+    /// within a build. Item pages are always rendered afresh.
+    pub render_cache: bool,
+    /// Skip rendering items and writing outputs whose sources and templates
+    /// are unchanged since the previous build into the same output directory,
+    /// as recorded in its `.harper-manifest.json` and `.harper-cache.json`.
+    /// Every item is still read and hashed. Pages showing content from
+    /// outside their own collection can go stale, so deploy from a full build.
+    pub incremental: bool,
+    /// Write a `sitemap.xml` listing every page at the output's root. On by
+    /// default, but only written when `root` has a host, e.g.
+    /// `https://example.com`, as sitemap URLs must be absolute. Otherwise, a
+    /// note says it wasn't.
+    pub sitemap: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Highlight {
+    /// Canonical language names by fence label, e.g. `js = "javascript"`,
+    /// looked up before each highlighter's built-in names. Merged into
+    /// `code.aliases`, taking precedence.
+    pub aliases: FxHashMap<String, String>,
+}
+
+/// Values injected into the metadata of every collection item once its front
+/// matter or data is read. Besides `values`, a `build` table with the build's
+/// `timestamp`, in seconds since the Unix epoch, and, if the site is in a git
+/// repository, `commit`, its `HEAD` commit hash, is injected unless `values`
+/// has a `build` key.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Inject {
+    /// Replace keys an item already has. By default, the item's keys win.
+    pub overwrite: bool,
+    /// Arbitrary values, e.g. `[inject.values]` then `edition = 2`.
+    pub values: FxHashMap<String, Value>,
+}
+
+/// Feeds of collection items, written as `feed.xml` for RSS or `atom.xml` for
+/// Atom, e.g. `/blog/feed.xml`, and listed in `site.feeds` for templates to
+/// link to. A collection's own feed's URL is `collection.feed`. Entries link
+/// to items by their URL, so `root` should include a host for readers to
+/// follow them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Feeds {
+    /// `rss`, the default, or `atom`.
+    pub format: FeedFormat,
+    /// Which feeds are written. None are by default.
+    pub scope: FeedScope,
+    /// The title of the site-wide feed. Collection feeds are titled by their
+    /// index's `title`, or else the collection's name. Defaults to the
+    /// config's `title`, if it's a string.
+    pub title: Option<String>,
+    /// The most entries, newest first, a feed includes. Defaults to all.
+    pub limit: Option<usize>,
+    /// The name of each feed's file, e.g. `rss.xml`, in place of the
+    /// format's default.
+    pub file_name: Option<String>,
+    /// Whether items without a `date` are left out. Otherwise, they're
+    /// listed after every dated item.
+    pub omit_undated: bool,
+    /// The author named in Atom feeds, which require one. Defaults to the
+    /// config's `author`, if it's a string, or else the feed's title.
+    pub author: Option<String>,
+}
+
+impl Feeds {
+    /// The name of each feed's file: `file_name` or the format's default.
+    pub fn file_name(&self) -> &str {
+        self.file_name.as_deref().unwrap_or(self.format.file_name())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeedScope {
+    /// No feeds are written.
+    #[default]
+    None,
+    /// A feed for each collection with items.
+    Collections,
+    /// A feed of every collection's items, at the site's root.
+    Site,
+    /// Both a feed for each collection and a site-wide feed. The root
+    /// collection, if there is one, has only the site-wide feed.
+    Both,
+}
+
+impl FeedScope {
+    pub fn collections(self) -> bool {
+        matches!(self, FeedScope::Collections | FeedScope::Both)
+    }
+
+    pub fn site(self) -> bool {
+        matches!(self, FeedScope::Site | FeedScope::Both)
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Sass {
+    /// Directories, relative to the site's root, that `@use` and `@import`
+    /// also resolve from, after the importing stylesheet's own directory, e.g.
+    /// `["sass"]` for partials shared by several stylesheets.
+    pub include_paths: Vec<PathBuf>,
+}
+
+/// The paths, relative to the site's root, of its source directories. The
+/// config file's own path, which can't be set here, is set on the command line.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Dirs {
+    /// Defaults to `content`.
+    pub content: PathBuf,
+    /// Defaults to `templates`.
+    pub templates: PathBuf,
+    /// Defaults to `assets`.
+    pub assets: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        Build {
+            precompress: vec![],
+            min_size: 1024,
+            gzip_level: 9,
+            brotli_level: 11,
+            render_cache: false,
+            incremental: false,
+            sitemap: true,
+        }
+    }
+}
+
+impl Default for Dirs {
+    fn default() -> Self {
+        Dirs {
+            content: crate::CONTENT_DIR.into(),
+            templates: crate::TEMPLATE_DIR.into(),
+            assets: crate::ASSETS_DIR.into(),
+        }
+    }
+}
+
+impl Encoding {
+    /// The extension appended to the name of a file compressed with `self`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Gzip => ".gz",
+            Encoding::Brotli => ".br",
+        }
+    }
+}
+
+/// A navigation menu: a list of entries sorted by weight.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Menu(pub Vec<MenuEntry>);
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MenuEntry {
+    /// The text of the entry.
+    pub name: String,
+    /// The URL the entry links to. Computed from `path` when unset.
+    #[serde(default)]
+    pub url: Option<UrlBuf>,
+    /// The path, relative to the content directory, of the content file
+    /// whose page the entry links to.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Entries are sorted by ascending weight, ties keeping config order.
+    #[serde(default)]
+    pub weight: i64,
+    /// The entry's submenu.
+    #[serde(default)]
+    pub children: Menu,
+}
+
+impl Menu {
+    /// Sorts the menu and its submenus by weight and resolves the URL of every
+    /// entry with a `path`. Errors if an entry has both or neither of `url`
+    /// and `path` or if a `path` doesn't name a file in the content directory.
+    ///
+    /// The URL is the one the file's page gets by default. Slugs and
+    /// permalinks set in the file's metadata aren't known until rendering and
+    /// are thus not taken into account.
+    fn resolve(&mut self, tree: &FsTree, settings: &Settings) -> Result<()> {
+        self.0.sort_by_key(|entry| entry.weight);
+        for entry in &mut self.0 {
+            entry.url = match (entry.url.take(), &entry.path) {
+                (Some(url), None) => Some(url),
+                (None, Some(path)) => Some(content_url(tree, settings, path)?),
+                (Some(_), Some(_)) => return err! {
+                    "menu entry must not have both a `url` and a `path`",
+                    "entry" => &entry.name,
+                },
+                (None, None) => return err! {
+                    "menu entry must have a `url` or a `path`",
+                    "entry" => &entry.name,
+                },
+            };
+
+            entry.children.resolve(tree, settings)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Validates the site's `root` URL and normalizes it: a path gets a leading
+/// `/`, and trailing `/`s are removed unless the root is just `/`. Errors if
+/// `root` has a query or fragment, has a scheme but no host, or looks like a
+/// host without a scheme, e.g. `example.com`.
+fn normalize_root(root: &UrlBuf) -> Result<UrlBuf> {
+    let invalid = |reason: &str| error! {
+        "invalid site root URL",
+        "root" => root.as_str(),
+        "reason" => reason,
+    };
+
+    let url: &Url = root;
+    if url.contains(['?', '#']) {
+        return Err(invalid("must not have a query or fragment"));
+    }
+
+    let mut normalized = match url.scheme() {
+        Some(scheme) => {
+            let host = url[scheme.len() + 1..].strip_prefix("//")
+                .and_then(|rest| rest.split('/').next())
+                .filter(|host| !host.is_empty());
+
+            if host.is_none() {
+                return Err(invalid("must have a host after the scheme"));
+            }
+
+            url.to_url_buf()
+        }
+        None if !url.starts_with('/') && url.split('/').next().is_some_and(|s| s.contains('.')) => {
+            return err! {
+                "site root looks like a host without a scheme",
+                "root" => root.as_str(),
+                "help" => format!("use `https://{url}` for a host or `/{url}` for a path", url = &**url),
+            };
+        }
+        None => {
+            let mut url = url.to_url_buf();
+            url.make_absolute();
+            url
+        }
+    };
+
+    let trimmed = normalized.trim_end_matches('/');
+    if trimmed.len() != normalized.len() {
+        normalized = UrlBuf::from(if trimmed.is_empty() { "/" } else { trimmed });
+    }
+
+    Ok(normalized)
+}
+
+/// Parses the config file `entry` in the format indicated by its extension.
+fn read_settings(entry: &Entry) -> Result<Settings> {
+    let path = &*entry.path;
+    match entry.file_ext() {
+        Some("toml") => Toml::read(path),
+        Some("yaml" | "yml") => Yaml::read(path),
+        Some("json") => Json::read(path),
+        _ => err! {
+            "config file format is not supported",
+            "path" => path.display(),
+            "supported extensions" => "toml, yaml, yml, json",
+        },
+    }
+}
+
+/// Computes the default URL of the page generated from the content file at
+/// `path`, relative to the content directory.
+fn content_url(tree: &FsTree, settings: &Settings, path: &Path) -> Result<UrlBuf> {
+    use crate::util::StringExt;
+
+    let full_path = settings.dirs.content.join(path);
+    let normal = path.components().all(|c| matches!(c, Component::Normal(_)));
+    let entry = tree.get_file_id(None, &full_path)
+        .filter(|_| normal)
+        .map(|id| &tree[id])
+        .ok_or_else(|| error! {
+            "menu entry path must name a file in the content directory",
+            "path" => path.display(),
+        })?;
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let markdown = matches!(entry.file_ext(), Some("md" | "mdown" | "markdown"));
+    let index = settings.content.index_stems.iter().any(|s| s == entry.file_stem());
+    let mut url = match (markdown, index) {
+        (true, true) => UrlBuf::from(dir),
+        (true, false) => UrlBuf::from(&*dir.join(entry.file_stem().slugify())),
+        (false, _) => UrlBuf::from(path),
+    };
+
+    if markdown {
+        url.append("/");
+    }
+
+    url.make_relative();
+    url.prepend(&settings.root);
+    Ok(url)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Mount {
+    /// The directory, relative to the site root, to mount.
+    pub path: PathBuf,
+    /// The path prefix to publish the mount under. Defaults to `path`.
+    #[serde(default)]
+    pub prefix: Option<PathBuf>,
+}
+
 impl Config {
-    pub fn discover<E: EngineInit>(tree: Arc<FsTree>) -> Result<Self> {
-        let mut settings = match tree.get(None, crate::CONFIG_FILE) {
-            Some(entry) => Toml::read(&*entry.path)?,
+    /// Reads the config file at `path`, relative to the root of `tree`, or,
+    /// if `path` is `None`, whichever of [`crate::CONFIG_FILES`] exists.
+    pub fn discover<E: EngineInit>(tree: Arc<FsTree>, path: Option<&Path>) -> Result<Self> {
+        let entry = match path {
+            Some(path) => Some(tree.get(None, path).ok_or_else(|| error! {
+                "config file does not exist",
+                "path" => path.display(),
+            })?),
+            None => {
+                let found: Vec<_> = crate::CONFIG_FILES.iter()
+                    .filter_map(|name| tree.get(None, name))
+                    .collect();
+
+                if found.len() > 1 {
+                    let names: Vec<_> = found.iter().map(|e| &*e.file_name).collect();
+                    return err! {
+                        "found multiple config files",
+                        "files" => names.join(", "),
+                        "help" => "remove all but one or choose one with `--config`",
+                    };
+                }
+
+                found.into_iter().next()
+            }
+        };
+
+        let mut settings = match entry {
+            Some(entry) => read_settings(entry)?,
             None => Settings::default(),
         };
 
-        settings.root.make_absolute();
+        settings.root = normalize_root(&settings.root)?;
         settings.aliases.insert("".into(), settings.root.to_string());
-        let templates_entry = crate::util::dircheck(&tree, None, crate::TEMPLATE_DIR, false)?;
-        let engine = Arc::new(E::init(tree.clone(), templates_entry, &settings));
-        Ok(Config { engine, settings })
+        let fence_aliases = settings.highlight.aliases.clone();
+        settings.code.aliases.extend(fence_aliases);
+
+        let mut menus = std::mem::take(&mut settings.menu);
+        for menu in menus.values_mut() {
+            menu.resolve(&tree, &settings)?;
+        }
+
+        settings.menu = menus;
+        let templates_entry = crate::util::dircheck(&tree, None, &settings.dirs.templates, false)?;
+        let engine = match settings.build.render_cache {
+            true => Arc::new(E::init_cached(tree.clone(), templates_entry, &settings)),
+            false => Arc::new(E::init(tree.clone(), templates_entry, &settings)),
+        };
+
+        Ok(Config { engine, settings, file: entry.map(|entry| entry.id) })
     }
 }
@@ -1,14 +1,24 @@
-use std::sync::Arc;
+use std::collections::hash_map;
+use std::sync::{Arc, Mutex};
 use std::path::{Path, PathBuf};
 
-use harper::{err, Collection, Site};
-use harper::fstree::{EntryId, FsTree};
+use rustc_hash::FxHashMap;
+
+use harper::{err, error, Collection, Generator, Item, Site};
+use harper::markdown::LunrDocument;
+use harper::feed::FeedLink;
+use harper::util::glob_match;
+use harper::diagnostics::{Diagnostics, Level};
+use harper::url::UrlBuf;
+use harper::fstree::{Entry, EntryId, FsTree};
 use harper::templating::EngineInit;
-use harper::error::Result;
+use harper::error::{Chainable, Error, Result};
+use harper::value::{Dict, Mapper, Sink, Toml, Value};
 use harper::templating::minijinja::MiniJinjaEngine;
 
-use crate::{ASSETS_DIR, CONTENT_DIR, TEMPLATE_DIR, PermaPath};
-use crate::config::Config;
+use crate::{DEFAULTS_FILE, PLUGIN_DIR, PermaPath};
+use crate::config::{self, Config, DataGrouping, FeedScope};
+use crate::incremental::Incremental;
 use crate::util::dircheck;
 
 #[derive(Debug)]
@@ -17,37 +27,294 @@ pub struct Mockingbird {
     pub config: Config,
     pub output: PathBuf,
     pub content_root: EntryId,
+    pub mounts: Vec<Mount>,
     pub template_root: Option<EntryId>,
     pub asset_root: Option<EntryId>,
+    /// Search documents for each collection, populated when rendering.
+    pub search_docs: Mutex<FxHashMap<EntryId, Vec<LunrDocument>>>,
+    /// Pages 2 and on of each paginated index, by the index's entry.
+    pub pages: Mutex<FxHashMap<EntryId, Vec<Arc<Item>>>>,
+    /// Non-fatal issues found while building.
+    pub diagnostics: Diagnostics,
+    /// Values injected into every item's metadata when it's rendered.
+    pub injected: Dict,
+    /// The state of an incremental build, if `build.incremental` is set.
+    pub incremental: Option<Incremental>,
+    /// Markdown transforms registered by `plugins/init.lua`, if any.
+    #[cfg(feature = "plugins")]
+    pub lua_markdown: Option<harper::templating::plugins::LuaMarkdown>,
+}
+
+/// An additional content root whose collections are published under `prefix`.
+#[derive(Debug)]
+pub struct Mount {
+    pub root: EntryId,
+    pub prefix: PathBuf,
 }
 
 impl Mockingbird {
+    #[cfg(test)]
     pub fn new<E, I, O>(input: I, output: O) -> Result<Self>
         where I: AsRef<Path>, O: AsRef<Path>, E: EngineInit
     {
-        let tree = Arc::new(FsTree::build(input)?);
+        Self::with_config::<E, I, O>(input, output, None)
+    }
+
+    /// Like [`Mockingbird::new()`], but reads the config file at `config`,
+    /// relative to `input`, when it is `Some`.
+    pub fn with_config<E, I, O>(input: I, output: O, config: Option<&Path>) -> Result<Self>
+        where I: AsRef<Path>, O: AsRef<Path>, E: EngineInit
+    {
+        let tree = Arc::new(FsTree::build(input.as_ref())?);
+        let config = Config::discover::<MiniJinjaEngine>(tree.clone(), config)?;
+        let dirs = &config.settings.dirs;
+        let content_root = dircheck(&tree, None, &dirs.content, true)?.unwrap();
+        let mounts = config.settings.content.mounts.iter()
+            .map(|mount| Self::resolve_mount(&tree, content_root, mount))
+            .collect::<Result<_>>()?;
+
         Ok(Mockingbird {
             output: output.as_ref().to_path_buf(),
-            content_root: dircheck(&tree, None, CONTENT_DIR, true)?.unwrap(),
-            template_root: dircheck(&tree, None, TEMPLATE_DIR, false)?,
-            asset_root: dircheck(&tree, None, ASSETS_DIR, false)?,
-            config: Config::discover::<MiniJinjaEngine>(tree.clone())?,
+            content_root,
+            mounts,
+            template_root: dircheck(&tree, None, &dirs.templates, false)?,
+            asset_root: dircheck(&tree, None, &dirs.assets, false)?,
+            search_docs: Mutex::default(),
+            pages: Mutex::default(),
+            diagnostics: Diagnostics::default(),
+            injected: Self::injected_values(input.as_ref(), &config.settings.inject),
+            incremental: match config.settings.build.incremental {
+                true => Some(Incremental::new(&tree, &config, output.as_ref())?),
+                false => None,
+            },
+            #[cfg(feature = "plugins")]
+            lua_markdown: harper::templating::plugins::init(tree.clone())?
+                .map(|plugins| harper::templating::plugins::LuaMarkdown::new(Arc::new(plugins))),
+            config,
             tree,
         })
     }
 
+    /// The configured injected values along with, unless they set `build`,
+    /// build information.
+    fn injected_values(input: &Path, inject: &config::Inject) -> Dict {
+        let mut values: Dict = inject.values.iter()
+            .map(|(key, value)| (key.as_str().into(), value.clone()))
+            .collect();
+
+        if !values.contains_key("build") {
+            let mut build = Dict::new();
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs());
+
+            build.insert("timestamp".into(), timestamp.into());
+            if let Some(commit) = crate::util::git_commit(input) {
+                build.insert("commit".into(), commit.into());
+            }
+
+            values.insert("build".into(), Value::Dict(Arc::new(build)));
+        }
+
+        values
+    }
+
+    // Out-of-tree mounts aren't supported as `FsTree` is single-rooted. Since
+    // `FsTree` follows links, an out-of-tree directory can instead be mounted
+    // by symlinking it into the site root.
+    fn resolve_mount(tree: &FsTree, content_root: EntryId, mount: &config::Mount) -> Result<Mount> {
+        use std::path::Component;
+
+        if !mount.path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return err! {
+                "content mounts must be subdirectories of the site root",
+                "mount path" => mount.path.display(),
+            };
+        }
+
+        let root = dircheck(tree, None, &mount.path, true)?.unwrap();
+        if tree.path_exists(content_root, root) || tree.path_exists(root, content_root) {
+            return err! {
+                "content mounts must not overlap the content root",
+                "mount path" => mount.path.display(),
+            };
+        }
+
+        let prefix = mount.prefix.clone().unwrap_or_else(|| mount.path.clone());
+        Ok(Mount { root, prefix })
+    }
+
+    /// Records a non-fatal issue. Warnings are reported once the build
+    /// finishes and fail it in strict mode.
+    pub fn warn(&self, warning: Error) {
+        self.diagnostics.push(Level::Warning, None, warning);
+    }
+
+    /// Records a warning about the file at `path`, relative to the site root.
+    pub fn warn_at(&self, path: &Path, warning: Error) {
+        self.diagnostics.push(Level::Warning, Some(path), warning);
+    }
+
+    /// Records a note, an expected issue worth reporting. Notes never fail
+    /// the build.
+    pub fn note(&self, note: Error) {
+        self.diagnostics.push(Level::Note, None, note);
+    }
+
+    /// Records a note, an expected issue worth reporting, about the file at
+    /// `path`. Notes never fail the build.
+    pub fn note_at(&self, path: &Path, note: Error) {
+        self.diagnostics.push(Level::Note, Some(path), note);
+    }
+
+    /// The content root and each mount along with its published path prefix.
+    pub fn content_roots(&self) -> impl Iterator<Item = (&Entry, &Path)> {
+        let main = (&self.tree[self.content_root], Path::new(""));
+        std::iter::once(main)
+            .chain(self.mounts.iter().map(|m| (&self.tree[m.root], &*m.prefix)))
+    }
+
+    /// The content root, and its prefix, that `id` was discovered in.
+    pub fn content_root_of(&self, id: EntryId) -> (&Entry, &Path) {
+        let main = (&self.tree[self.content_root], Path::new(""));
+        self.mounts.iter()
+            .find(|m| self.tree.path_exists(m.root, id))
+            .map_or(main, |m| (&self.tree[m.root], &*m.prefix))
+    }
+
+    /// The path of `entry` relative to its content root, prefixed with the
+    /// root's published path prefix.
+    pub fn content_path(&self, entry: &Entry) -> PathBuf {
+        let (content_root, prefix) = self.content_root_of(entry.id);
+        let relative = entry.path_relative_to(content_root).unwrap();
+        prefix.components().chain(relative.components()).collect()
+    }
+
+    /// Whether `entry` is a collection index file.
+    fn is_index(&self, entry: &Entry) -> bool {
+        let stem = entry.file_stem();
+        self.config.settings.content.index_stems.iter().any(|s| s == stem)
+    }
+
+    /// Whether `entry` is forced into its directory's data group.
+    fn is_forced_datum(&self, entry: &Entry) -> bool {
+        self.config.settings.content.data_prefix.as_deref()
+            .is_some_and(|prefix| entry.file_stem().starts_with(prefix))
+    }
+
+    /// Whether `entry` is a passthrough file in the content root, which is
+    /// copied verbatim rather than treated as content.
+    fn is_passthrough(&self, entry: &Entry) -> bool {
+        entry.file_type.is_file()
+            && entry.parent == Some(self.content_root)
+            && self.config.settings.content.passthrough.contains(&entry.file_name)
+    }
+
+    /// Whether `entry` is a defaults file rather than content.
+    fn is_defaults(&self, entry: &Entry) -> bool {
+        entry.file_type.is_file() && &*entry.file_name == DEFAULTS_FILE
+    }
+
+    /// Whether `entry`'s name is hidden by the configured rules.
+    fn is_hidden_name(&self, entry: &Entry) -> bool {
+        let hidden = &self.config.settings.hidden;
+        let name = &*entry.file_name;
+        (hidden.dotfiles && name.starts_with('.'))
+            || (hidden.underscore && name.starts_with('_')
+                && !self.is_index(entry) && !self.is_forced_datum(entry))
+            || hidden.patterns.iter().any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Whether `entry` or any of its ancestors up to, but excluding, `root`
+    /// is hidden. The templates and plugins directories are always hidden.
+    fn is_hidden(&self, root: EntryId, entry: &Entry) -> bool {
+        let special = [&*self.config.settings.dirs.templates, Path::new(PLUGIN_DIR)];
+        if special.iter().any(|dir| entry.relative_path().starts_with(dir)) {
+            return true;
+        }
+
+        std::iter::once(entry.id)
+            .chain(self.tree.ancestors_of(entry.id))
+            .take_while(|&id| id != root)
+            .any(|id| self.is_hidden_name(&self.tree[id]))
+    }
+
     pub fn discover(&self) -> Result<Site> {
         let mut site = Site::new(self.tree.clone());
+        site.content_roots = std::iter::once(self.content_root)
+            .chain(self.mounts.iter().map(|mount| mount.root))
+            .collect();
+        site.drafts = self.config.settings.content.drafts;
+
         self.build_site_items(&mut site);
         self.build_collections(&mut site)?;
         self.build_items(&mut site)?;
+        if site.collections.is_empty() && !self.config.settings.content.allow_empty {
+            return err! {
+                "found no content files",
+                "content root" => self.tree[self.content_root].path.display(),
+                "help" => "add content or set `content.allow_empty = true` to build an empty site",
+            };
+        }
+
+        self.apply_defaults(&site)?;
+        self.register_feeds(&mut site);
         Ok(site)
     }
 
+    /// Registers the feeds configured in `feeds.scope` in `site.feeds`, so
+    /// that they're known to templates before any are written.
+    fn register_feeds(&self, site: &mut Site) {
+        let feeds = &self.config.settings.feeds;
+        if feeds.scope != FeedScope::None && self.config.settings.root.scheme().is_none() {
+            self.warn(error! {
+                "feeds are enabled but `root` has no host: feed links are relative",
+                "root" => self.config.settings.root.as_str(),
+                "help" => "set `root` to the site's full URL, e.g. `https://example.com`",
+            });
+        }
+
+        let url = |dir: &Path| {
+            let mut url = UrlBuf::from(&*dir.join(feeds.file_name()));
+            url.make_relative();
+            url.prepend(&self.config.settings.root);
+            url
+        };
+
+        if feeds.scope.site() {
+            let url = url(Path::new(""));
+            site.feeds.push(FeedLink { collection: None, format: feeds.format, url });
+        }
+
+        if feeds.scope.collections() {
+            let mut collections: Vec<_> = site.collections.values()
+                .filter(|collection| !collection.items.is_empty())
+                .map(|collection| (collection.entry.id, self.content_path(&collection.entry)))
+                .filter(|(_, dir)| !(feeds.scope.site() && dir.as_os_str().is_empty()))
+                .collect();
+
+            collections.sort_by(|a, b| a.1.cmp(&b.1));
+            for (id, dir) in collections {
+                let url = url(&dir);
+                site.feeds.push(FeedLink { collection: Some(id), format: feeds.format, url });
+            }
+        }
+    }
+
     fn build_site_items(&self, site: &mut Site) {
-        let hidden = |filename: &str| filename.starts_with(".")
-            || filename.eq_ignore_ascii_case("include")
-            || filename.eq_ignore_ascii_case("includes");
+        for name in &self.config.settings.content.passthrough {
+            let id = self.tree.get_file_id(self.content_root, name)
+                .or_else(|| self.tree.get_file_id(None, name));
+
+            if let Some(id) = id {
+                let item = site.new_resource(id);
+                item.metadata.insert(PermaPath, Path::new(name));
+            }
+        }
+
+        let hidden = |entry: &Entry| self.is_hidden_name(entry)
+            || entry.file_name.eq_ignore_ascii_case("include")
+            || entry.file_name.eq_ignore_ascii_case("includes");
 
         let asset_root = match self.asset_root {
             Some(id) => &self.tree[id],
@@ -55,7 +322,7 @@ impl Mockingbird {
         };
 
         self.tree.depth_first_search(asset_root.id, |entry| {
-            if hidden(&entry.file_name) {
+            if entry.id != asset_root.id && hidden(entry) {
                 return false;
             }
 
@@ -70,21 +337,44 @@ impl Mockingbird {
     }
 
     fn build_collections(&self, site: &mut Site) -> Result<()> {
-        let content_root = &self.tree[self.content_root];
+        for (content_root, _) in self.content_roots() {
+            self.build_root_collections(site, content_root)?;
+        }
+
+        Ok(())
+    }
+
+    /// Errors if `dir`'s name isn't valid UTF-8. Collection and data group
+    /// names are looked up by string, so a lossy name could never be matched
+    /// exactly and might collide with another.
+    fn check_utf8_name(&self, dir: &Entry, kind: &str) -> Result<()> {
+        if !dir.has_utf8_name() {
+            return err! {
+                format!("{kind} directory names must be valid UTF-8"),
+                "directory" => dir.path.display(),
+            };
+        }
+
+        Ok(())
+    }
+
+    fn build_root_collections(&self, site: &mut Site, content_root: &Entry) -> Result<()> {
+        let name = |dir: &Entry| -> Result<Arc<str>> {
+            self.check_utf8_name(dir, "collection")?;
+            Ok(self.content_path(dir).to_string_lossy().into())
+        };
+
         // TODO: Provide a parallel iterator here?
         let index_files = self.tree.iter_breadth_first(content_root.id)
             .files()
-            .filter(|e| e.file_stem() == "index");
+            .filter(|e| self.is_index(e) && !self.is_passthrough(e))
+            .filter(|e| !self.is_hidden(content_root.id, e));
 
         // Find all collections, as identified by the presence of an index file.
         for index in index_files {
             let group_dir = &self.tree[index.parent.unwrap()];
-            let collection = site.get_or_insert_collection(|| {
-                group_dir.path_relative_to(content_root)
-                    .unwrap()
-                    .to_string_lossy()
-                    .into()
-            }, group_dir.id);
+            let name = name(group_dir)?;
+            let collection = site.get_or_insert_collection(|| name, group_dir.id);
 
             if let Some(ref existing) = collection.index {
                 return err!(
@@ -98,9 +388,46 @@ impl Mockingbird {
             collection.set_index_item(index.id);
         }
 
+        // Find all otherwise loose directories with content, if requested.
+        if self.config.settings.content.auto_collections {
+            let dirs = self.tree.iter_breadth_first(content_root.id)
+                .entries()
+                .filter(|e| e.id != content_root.id && e.file_type.is_dir())
+                .filter(|e| !self.is_hidden(content_root.id, e))
+                .filter(|e| e.children.iter()
+                    .map(|&c| &self.tree[c])
+                    .any(|c| c.file_type.is_file()
+                        && !self.is_hidden_name(c)
+                        && !self.is_defaults(c)
+                        && !self.is_passthrough(c)));
+
+            // Directories with an index already have their own collection.
+            for dir in dirs {
+                if site.collections.contains_key(&dir.id) {
+                    continue;
+                }
+
+                let name = name(dir)?;
+                site.get_or_insert_collection(|| name, dir.id);
+            }
+        }
+
         Ok(())
     }
 
+    /// The directory whose data group `entry`, in a collection at depth
+    /// `collection_depth`, belongs to.
+    fn data_group(&self, collection_depth: usize, entry: &Entry) -> EntryId {
+        let mut group = entry.parent.unwrap();
+        if self.config.settings.content.data_grouping == DataGrouping::TopLevel {
+            while self.tree[group].depth > collection_depth + 1 {
+                group = self.tree[group].parent.unwrap();
+            }
+        }
+
+        group
+    }
+
     fn parent<'a>(&self, site: &'a mut Site, mut entry: EntryId) -> Option<&'a mut Collection> {
         loop {
             let parent = self.tree[entry].parent?;
@@ -114,24 +441,90 @@ impl Mockingbird {
     }
 
     fn build_items(&self, site: &mut Site) -> Result<()> {
-        let tree = self.tree.clone();
-        let content_root = &tree[self.content_root];
-        let files = tree.iter_breadth_first(content_root.id).files()
-            .filter(|e| e.file_stem() != "index");
-
-        for entry in files {
-            let collection = match self.parent(site, entry.id) {
-                Some(collection) => collection,
-                None => site.get_or_insert_collection(|| "/".into(), content_root.id),
+        for (content_root, prefix) in self.content_roots() {
+            let root_name: Arc<str> = match prefix.as_os_str().is_empty() {
+                true => "/".into(),
+                false => prefix.to_string_lossy().into(),
             };
 
-            if entry.depth - collection.entry.depth <= 1 {
-                collection.new_item(entry.id);
-            } else {
-                collection.new_datum(entry.parent.unwrap(), entry.id);
-            };
+            let files = self.tree.iter_breadth_first(content_root.id).files()
+                .filter(|e| !self.is_index(e) && !self.is_defaults(e) && !self.is_passthrough(e))
+                .filter(|e| !self.is_hidden(content_root.id, e));
+
+            for entry in files {
+                let collection = match self.parent(site, entry.id) {
+                    Some(collection) => collection,
+                    None => site.get_or_insert_collection(|| root_name.clone(), content_root.id),
+                };
+
+                if entry.depth - collection.entry.depth <= 1 && !self.is_forced_datum(entry) {
+                    collection.new_item(entry.id);
+                } else {
+                    let group = self.data_group(collection.entry.depth, entry);
+                    self.check_utf8_name(&self.tree[group], "data")?;
+                    collection.new_datum(group, entry.id);
+                };
+            }
         }
 
         Ok(())
 	}
+
+    /// Applies the `_defaults.toml` files in the directories containing each
+    /// collection item, up to and including its content root, to the item's
+    /// metadata. Values are inserted from the farthest file to the nearest,
+    /// so nearer defaults take precedence. Since front matter and data are
+    /// read later, when rendering, an item's own values override all defaults.
+    fn apply_defaults(&self, site: &Site) -> Result<()> {
+        let mut cache: FxHashMap<EntryId, Option<Value>> = FxHashMap::default();
+        let items = site.collections.values().flat_map(|collection| {
+            collection.index.iter()
+                .chain(collection.items.iter())
+                .chain(collection.data.values().flat_map(|data| data.iter()))
+        });
+
+        for item in items {
+            let (content_root, _) = self.content_root_of(item.entry.id);
+            let mut dirs: Vec<_> = self.tree.ancestors_of(item.entry.id)
+                .take_while(|&id| id != content_root.id)
+                .collect();
+
+            dirs.push(content_root.id);
+            for dir in dirs.into_iter().rev() {
+                let defaults = match cache.entry(dir) {
+                    hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                    hash_map::Entry::Vacant(entry) => entry.insert(self.read_defaults(dir)?),
+                };
+
+                if let Some(defaults) = defaults {
+                    item.metadata.write_value(defaults.clone())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the defaults file in the directory `dir`, if there is one.
+    fn read_defaults(&self, dir: EntryId) -> Result<Option<Value>> {
+        let Some(id) = self.tree.get_file_id(dir, DEFAULTS_FILE) else {
+            return Ok(None);
+        };
+
+        let entry = &self.tree[id];
+        Toml.map(entry).map(Some).chain_with(|| error! {
+            "failed to read defaults file",
+            "path" => entry.relative_path().display(),
+        })
+    }
+}
+
+impl Generator for Mockingbird {
+    fn discover(&self) -> Result<Site> {
+        Mockingbird::discover(self)
+    }
+
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        Some(&self.diagnostics)
+    }
 }
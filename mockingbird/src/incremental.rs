@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use harper::{error, Collection, Item, Kind};
+use harper::error::{Chainable, Result};
+use harper::fstree::{Entry, FsTree};
+use harper::manifest::{Fingerprint, Manifest, StableHasher, MANIFEST_FILE};
+use harper::markdown::LunrDocument;
+use harper::path_str::IntoPathStr;
+use harper::value::{Dict, Num, Source, Value};
+
+use crate::{DEFAULTS_FILE, PLUGIN_DIR, PermaPath, Template};
+use crate::config::Config;
+use crate::discover::Mockingbird;
+use crate::render::alternate_outputs;
+
+/// The name of the file in the output directory caching what every item was
+/// rendered to, for the next incremental build.
+pub const CACHE_FILE: &str = ".harper-cache.json";
+
+/// The state of an incremental build: the manifest and render cache written
+/// by the previous build and those being recorded by this build.
+///
+/// An item whose source and the inputs every item depends on, the config file,
+/// every `_defaults.toml` and plugin file, and the set of templates, are
+/// unchanged isn't rendered again. Its metadata and search documents are
+/// restored from the previous build's render cache instead, without repeating
+/// any warnings its rendering emitted. Unpublished items are always rendered.
+///
+/// An item's outputs are rendered again only when its [`Fingerprint`]
+/// changes or an output is missing. The fingerprint covers the item's source,
+/// the templates it's rendered with and those they extend, include, or import
+/// by name, the config file, every `_defaults.toml` and plugin file, and which
+/// items are in its collection. An index's also covers the contents of every
+/// item in its collection. Pages that show other content, like another
+/// collection's items, can go stale and need a full build.
+#[derive(Debug)]
+pub struct Incremental {
+    last: Manifest,
+    manifest: Mutex<Manifest>,
+    /// What each item was rendered to by the previous build, by stable id.
+    last_rendered: FxHashMap<String, Rendered>,
+    /// What each item was rendered to by this build, by stable id.
+    rendered: Mutex<BTreeMap<String, Rendered>>,
+    /// The hash of the inputs every output depends on.
+    shared: u128,
+    /// The hash of each template and its dependencies by name.
+    templates: Mutex<FxHashMap<String, u128>>,
+}
+
+/// What an item was rendered to, and a hash of what it was rendered from.
+#[derive(Debug, Deserialize, Serialize)]
+struct Rendered {
+    hash: String,
+    metadata: BTreeMap<Arc<str>, Cached>,
+    search_docs: Option<Vec<LunrDocument>>,
+}
+
+/// A [`Value`] that deserializes to the variant it was serialized from. A
+/// `Value` path deserializes to a string.
+#[derive(Debug, Deserialize, Serialize)]
+enum Cached {
+    Null,
+    Bool(bool),
+    Num(Num),
+    String(Arc<str>),
+    Path(Arc<str>),
+    Array(Vec<Cached>),
+    Dict(BTreeMap<Arc<str>, Cached>),
+}
+
+impl Incremental {
+    /// Reads the previous build's manifest from `output`, if there is one.
+    pub fn new(tree: &FsTree, config: &Config, output: &Path) -> Result<Self> {
+        let plugins = tree.get_id(None, PLUGIN_DIR).map(|id| tree[id].path.clone());
+        let templates = tree.get_id(None, &config.settings.dirs.templates).map(|id| tree[id].path.clone());
+        let mut templates: Vec<&Entry> = tree.iter()
+            .filter(|e| e.file_type.is_file())
+            .filter(|e| templates.as_ref().is_some_and(|dir| e.path.starts_with(dir)))
+            .collect();
+
+        let mut shared: Vec<&Entry> = tree.iter()
+            .filter(|e| e.file_type.is_file())
+            .filter(|e| Some(e.id) == config.file
+                || e.file_name == DEFAULTS_FILE
+                || plugins.as_ref().is_some_and(|dir| e.path.starts_with(dir)))
+            .collect();
+
+        shared.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut hasher = StableHasher::new();
+        hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+        for entry in shared {
+            hasher.write(entry.relative_path().as_os_str().as_encoded_bytes());
+            hasher.write_hash(entry.content_hash()?);
+        }
+
+        // Which template an item is rendered with depends on which exist.
+        templates.sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in templates {
+            hasher.write(entry.relative_path().as_os_str().as_encoded_bytes());
+        }
+
+        // A missing or malformed cache, like a manifest, is empty.
+        let last_rendered = std::fs::read(output.join(CACHE_FILE)).ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+
+        Ok(Incremental {
+            last: Manifest::load(output.join(MANIFEST_FILE)),
+            manifest: Mutex::default(),
+            last_rendered,
+            rendered: Mutex::default(),
+            shared: hasher.finish(),
+            templates: Mutex::default(),
+        })
+    }
+
+    /// The hash of what the collection `item` of kind `kind` is rendered from.
+    pub fn render_hash(&self, kind: Kind, item: &Item) -> Result<u128> {
+        let mut hasher = StableHasher::new();
+        hasher.write_hash(self.shared);
+        hasher.write(match kind {
+            Kind::Index => b"index",
+            Kind::Item(_) => b"item",
+            Kind::Datum(_) => b"datum",
+        });
+        hasher.write(item.stable_id().as_bytes());
+        hasher.write_hash(item.entry.content_hash()?);
+        Ok(hasher.finish())
+    }
+
+    /// Restores the metadata `item` was rendered to by the previous build and
+    /// returns its search documents, if it was indexed, if it was rendered
+    /// from inputs with the same `hash`. Otherwise, returns `None`, and `item`
+    /// must be rendered.
+    pub fn restore(&self, mockingbird: &Mockingbird, item: &Item, hash: u128) -> Option<Option<Vec<LunrDocument>>> {
+        let rendered = self.last_rendered.get(&item.stable_id())?;
+        if rendered.hash != format!("{hash:032x}") {
+            return None;
+        }
+
+        for (key, value) in &rendered.metadata {
+            item.metadata.insert_raw(key.clone(), Value::from(value));
+        }
+
+        let inject = &mockingbird.config.settings.inject;
+        item.metadata.inject(&mockingbird.injected, inject.overwrite);
+        Some(rendered.search_docs.clone())
+    }
+
+    /// Caches what `item` was rendered to, from inputs with the given `hash`,
+    /// along with its `search_docs`, for the next build. Unpublished items
+    /// aren't cached: an embargoed item is published once its date passes,
+    /// without any change to its source.
+    pub fn cache(&self, mockingbird: &Mockingbird, item: &Item, hash: u128, search_docs: Option<Vec<LunrDocument>>) {
+        if item.is_draft() {
+            return;
+        }
+
+        // Injected values, like the build's timestamp, are injected afresh.
+        let metadata = item.metadata.to_value();
+        let metadata = metadata.as_dict().into_iter().flatten()
+            .filter(|(key, value)| mockingbird.injected.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), Cached::from(value)))
+            .collect();
+
+        let rendered = Rendered { hash: format!("{hash:032x}"), metadata, search_docs };
+        self.rendered.lock().unwrap().insert(item.stable_id(), rendered);
+    }
+
+    /// The fingerprint of the collection `item`'s outputs, or `None` if it has
+    /// none, as for data items and drafts.
+    pub fn fingerprint(&self,
+        mockingbird: &Mockingbird,
+        kind: Kind,
+        collection: &Arc<Collection>,
+        item: &Arc<Item>,
+    ) -> Result<Option<Fingerprint>> {
+        let alternates = alternate_outputs(item)?;
+        if !item.metadata.contains(PermaPath) && alternates.is_empty() {
+            return Ok(None);
+        }
+
+        let mut hasher = StableHasher::new();
+        hasher.write_hash(self.shared);
+        hasher.write_hash(item.entry.content_hash()?);
+
+        let template = match item.metadata.get(Template) {
+            Some(Ok(template)) => Some(template),
+            _ => None,
+        };
+
+        let templates = template.iter().chain(alternates.iter().map(|(_, _, template)| template));
+        for template in templates {
+            hasher.write(template.as_str().as_bytes());
+            hasher.write_hash(self.template_hash(mockingbird, template.as_str())?);
+        }
+
+        // Listings and navigation change when items are added, removed, or
+        // published. An index's listing also changes with any item's content.
+        for sibling in collection.items.iter() {
+            hasher.write(sibling.entry.relative_path().as_os_str().as_encoded_bytes());
+            hasher.write(&[sibling.is_draft() as u8]);
+            if matches!(kind, Kind::Index) {
+                hasher.write_hash(sibling.entry.content_hash()?);
+            }
+        }
+
+        let template = template.as_ref().map(|template| template.as_str());
+        Ok(Some(Fingerprint::new(hasher.finish(), template)))
+    }
+
+    /// The fingerprint of the output of the asset `entry`.
+    pub fn asset_fingerprint(&self, entry: &Entry) -> Result<Fingerprint> {
+        let mut hasher = StableHasher::new();
+        hasher.write_hash(self.shared);
+        hasher.write_hash(entry.content_hash()?);
+        Ok(Fingerprint::new(hasher.finish(), None))
+    }
+
+    /// Whether `item`'s outputs were written by the previous build with
+    /// `fingerprint` and are all still in place.
+    pub fn is_fresh(&self, mockingbird: &Mockingbird, item: &Item, fingerprint: &Fingerprint) -> Result<bool> {
+        if !self.last.is_fresh(item.entry.relative_path(), fingerprint) {
+            return Ok(false);
+        }
+
+        let alternates = alternate_outputs(item)?.into_iter().map(|(_, path, _)| path);
+        let pages = mockingbird.pages(item).into_iter()
+            .map(|page| page.path(PermaPath))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut outputs = item.path(PermaPath)?.into_iter()
+            .chain(alternates)
+            .chain(pages.into_iter().flatten());
+
+        Ok(outputs.all(|path| mockingbird.output.join(path).exists()))
+    }
+
+    /// Records that `item`'s outputs were written with `fingerprint`.
+    pub fn record(&self, item: &Item, fingerprint: Fingerprint) {
+        self.manifest.lock().unwrap().insert(item.entry.relative_path(), fingerprint);
+    }
+
+    /// Writes this build's manifest and render cache into `output`.
+    pub fn save(&self, output: &Path) -> Result<()> {
+        let path = output.join(CACHE_FILE);
+        let json = serde_json::to_vec(&*self.rendered.lock().unwrap())?;
+        std::fs::write(&path, json).chain_with(|| error! {
+            "failed to write render cache",
+            "path" => path.display(),
+        })?;
+
+        self.manifest.lock().unwrap().save(output.join(MANIFEST_FILE))
+    }
+
+    /// The hash of the template `name` and of every template it references,
+    /// transitively. Templates that don't exist hash only their name.
+    fn template_hash(&self, mockingbird: &Mockingbird, name: &str) -> Result<u128> {
+        if let Some(hash) = self.templates.lock().unwrap().get(name) {
+            return Ok(*hash);
+        }
+
+        let mut hasher = StableHasher::new();
+        let mut names = vec![name.to_string()];
+        let mut i = 0;
+        while let Some(name) = names.get(i).cloned() {
+            i += 1;
+            hasher.write(name.as_bytes());
+            let root = mockingbird.template_root;
+            let Some(id) = root.and_then(|root| mockingbird.tree.get_file_id(root, &name)) else {
+                continue;
+            };
+
+            let entry = &mockingbird.tree[id];
+            hasher.write_hash(entry.content_hash()?);
+            let source: Arc<str> = entry.try_read()?;
+            for dependency in template_refs(&source) {
+                if !names.iter().any(|name| name == dependency) {
+                    names.push(dependency.to_string());
+                }
+            }
+        }
+
+        let hash = hasher.finish();
+        self.templates.lock().unwrap().insert(name.to_string(), hash);
+        Ok(hash)
+    }
+}
+
+impl From<&Value> for Cached {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Cached::Null,
+            Value::Bool(v) => Cached::Bool(*v),
+            Value::Num(v) => Cached::Num(*v),
+            Value::String(v) => Cached::String(v.clone()),
+            Value::Path(v) => Cached::Path(v.as_str().into()),
+            Value::Array(v) => Cached::Array(v.iter().map(Cached::from).collect()),
+            Value::Dict(v) => Cached::Dict(v.iter().map(|(k, v)| (k.clone(), v.into())).collect()),
+        }
+    }
+}
+
+impl From<&Cached> for Value {
+    fn from(value: &Cached) -> Self {
+        match value {
+            Cached::Null => Value::Null,
+            Cached::Bool(v) => Value::Bool(*v),
+            Cached::Num(v) => Value::Num(*v),
+            Cached::String(v) => Value::String(v.clone()),
+            Cached::Path(v) => Value::Path(v.clone().into_path_str()),
+            Cached::Array(v) => Value::Array(Arc::new(v.iter().map(Value::from).collect())),
+            Cached::Dict(v) => {
+                let dict: Dict = v.iter().map(|(k, v)| (k.clone(), v.into())).collect();
+                Value::Dict(Arc::new(dict))
+            }
+        }
+    }
+}
+
+/// The names of the templates `source` extends, includes, or imports by a
+/// string literal, e.g. `base.html` in `{% extends "base.html" %}`.
+fn template_refs(source: &str) -> impl Iterator<Item = &str> {
+    source.split("{%").skip(1).filter_map(|tag| {
+        let tag = tag.trim_start_matches(['-', '+']).trim_start();
+        let rest = ["extends", "include", "import", "from"].iter()
+            .find_map(|keyword| tag.strip_prefix(keyword))?
+            .trim_start();
+
+        let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        rest[1..].split(quote).next()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::template_refs;
+
+    #[test]
+    fn finds_template_references() {
+        let source = "{% extends \"base.html\" %}{%- include 'nav.html' -%}\
+            {% import \"macros.html\" as m %}{% from 'forms.html' import input %}\
+            {% include includes %}{% if x %}{% endif %}{% includex \"no\" %}";
+
+        let refs: Vec<_> = template_refs(source).collect();
+        assert_eq!(refs, ["base.html", "nav.html", "macros.html", "forms.html"]);
+    }
+}
@@ -1,9 +1,11 @@
 use std::sync::Arc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use harper::{Renderer, Site};
-use harper::error::Result;
-use harper::value::Value;
+use harper::Site;
+use harper::diagnostics::Report;
+pub use harper::{Content, Draft, UrlRef};
+use harper::error::{Error, Result};
+use harper::value::{Dict, Value};
 use harper::path_str::PathStr;
 use harper::templating::minijinja::MiniJinjaEngine;
 use harper::url::Url;
@@ -13,38 +15,107 @@ mod util;
 mod config;
 mod discover;
 mod render;
+mod check;
+mod compress;
+mod incremental;
+mod watch;
+
+#[cfg(test)]
+mod tests;
 
 use crate::discover::Mockingbird;
 
 pub const CONTENT_DIR: &str = "content";
 pub const TEMPLATE_DIR: &str = "templates";
 pub const ASSETS_DIR: &str = "assets";
-pub const CONFIG_FILE: &str = "config.toml";
+pub const PLUGIN_DIR: &str = "plugins";
+/// The config files looked for, in the site's root, when none is specified.
+pub const CONFIG_FILES: &[&str] = &["config.toml", "config.yaml", "config.json"];
+pub const DEFAULTS_FILE: &str = "_defaults.toml";
 
 harper::define_meta_key! {
-    pub UrlRef : "url" => Arc<Url>,
+    pub Canonical : "canonical" => Arc<Url>,
     pub PermaPath : "permapath" => Arc<Path>,
     pub Template : "template" => Arc<PathStr>,
     pub Slug : "slug" => Arc<str>,
+    pub Permalink : "permalink" => Arc<str>,
 
     pub SourcePath : "source_path" => Arc<Path>,
     pub FileStem : "file_stem" => Arc<str>,
 
     pub Position : "position" => usize,
-    pub Draft : "draft" => bool,
+    pub Raw : "raw" => bool,
+    pub List : "list" => bool,
+    pub Outputs : "outputs" => Vec<Arc<str>>,
+    pub Alternates : "alternates" => Arc<Dict>,
+
+    pub Paginate : "paginate" => usize,
+    pub Page : "page" => usize,
+    pub TotalPages : "total_pages" => usize,
+    pub NextPageUrl : "next_page_url" => Arc<Url>,
+    pub PrevPageUrl : "prev_page_url" => Arc<Url>,
 
-    pub Content : "content" => Arc<str>,
     pub Data : "data" => Value,
 
-    pub Toc : "toc" => Arc<str>,
+    pub Toc : "toc" => Value,
+    pub TocHtml : "toc_html" => Arc<str>,
     pub Snip : "snippet" => Arc<str>,
+    pub SnipTruncated : "snippet_truncated" => bool,
+    pub WordCount : "word_count" => usize,
+    pub CharCount : "char_count" => usize,
+}
+
+/// Builds the site in `input` into `output` using the config file at `config`,
+/// or the default one if `None`. Returns the site and a report of the
+/// non-fatal issues encountered while building, sorted by path.
+pub fn run(input: &Path, output: &Path, config: Option<&Path>) -> Result<(Arc<Site>, Report)> {
+    build(Mockingbird::with_config::<MiniJinjaEngine, _, _>(input, output, config)?)
+}
+
+/// Like [`run()`], but also renders items dated in the future or past their
+/// expiry date if `future`.
+pub fn build_with(input: &Path, output: &Path, config: Option<&Path>, future: bool) -> Result<(Arc<Site>, Report)> {
+    let mut mockingbird = Mockingbird::with_config::<MiniJinjaEngine, _, _>(input, output, config)?;
+    mockingbird.config.settings.content.future |= future;
+    build(mockingbird)
+}
+
+/// Prints the warnings in `report` and, unless `quiet`, its notes.
+fn print_report(report: &Report, quiet: bool) {
+    if !quiet {
+        for note in &report.notes {
+            eprintln!("note: {note}");
+        }
+    }
+
+    for warning in &report.warnings {
+        eprintln!("warning: {warning}");
+    }
 }
 
-pub fn run(input: &Path, output: &Path) -> Result<Arc<Site>> {
-    let mockingbird = Mockingbird::new::<MiniJinjaEngine, _, _>(input, output)?;
+/// Builds the site `mockingbird` is configured for. See [`run()`].
+fn build(mockingbird: Mockingbird) -> Result<(Arc<Site>, Report)> {
+    let build = harper::build(&mockingbird)?;
+    Ok((build.site, build.report))
+}
+
+pub fn check(input: &Path, config: Option<&Path>) -> Result<Vec<Error>> {
+    let output = PathBuf::new();
+    let mockingbird = Mockingbird::with_config::<MiniJinjaEngine, _, _>(input, output, config)?;
     let site = Arc::new(mockingbird.discover()?);
-    mockingbird.render_site(&site)?;
-    Ok(site)
+    Ok(mockingbird.check(&site))
+}
+
+/// Returns the metadata of the item whose source is at `path`, relative to the
+/// content root, as sorted, pretty-printed JSON.
+pub fn dump_metadata(site: &Site, path: &Path) -> Result<String> {
+    let item = site.item_at(path).ok_or_else(|| harper::error! {
+        "no content item found to dump metadata for",
+        "path" => path.display(),
+        "help" => "paths are relative to the content directory, e.g. `blog/post.md`",
+    })?;
+
+    Ok(item.metadata.to_value().to_json())
 }
 
 mod flags {
@@ -61,6 +132,35 @@ mod flags {
                 required output: PathBuf
                 /// quiet: don't emit anything
                 optional -q,--quiet
+                /// fail the build if there are any warnings
+                optional -s,--strict
+                /// config file, relative to the input directory
+                optional -c,--config config: PathBuf
+                /// print the metadata of the item at this path, relative to
+                /// the content directory, as JSON
+                optional --dump-metadata path: PathBuf
+                /// render items dated in the future or past their expiry date
+                optional --future
+            }
+            /// Build a site, then rebuild it whenever its sources change.
+            cmd watch {
+                /// Directory containing the site sources
+                required input: PathBuf
+                /// Where to write the site to
+                required output: PathBuf
+                /// quiet: only emit warnings and errors
+                optional -q,--quiet
+                /// config file, relative to the input directory
+                optional -c,--config config: PathBuf
+                /// render items dated in the future or past their expiry date
+                optional --future
+            }
+            /// Check templates for errors without writing the site.
+            cmd check {
+                /// Directory containing the site sources
+                required input: PathBuf
+                /// config file, relative to the input directory
+                optional -c,--config config: PathBuf
             }
             /// Print the version and exit.
             cmd version { }
@@ -73,15 +173,69 @@ pub fn main() {
 
     match flags::Mockingbird::from_env_or_exit().subcommand {
         flags::MockingbirdCmd::Build(args) => {
-            let site = run(&args.input, &args.output).unwrap_or_else(|e| {
+            let config = args.config.as_deref();
+            let built = build_with(&args.input, &args.output, config, args.future);
+            let (site, report) = built.unwrap_or_else(|e| {
                 eprintln!("error: {e}");
                 std::process::exit(1)
             });
 
-            if !args.quiet {
+            print_report(&report, args.quiet);
+            let warnings = report.warnings;
+            if args.strict && !warnings.is_empty() {
+                eprintln!("strict build failed with {} warning(s)", warnings.len());
+                std::process::exit(1)
+            }
+
+            if let Some(path) = &args.dump_metadata {
+                match dump_metadata(&site, path) {
+                    Ok(json) => println!("{json}"),
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        std::process::exit(1)
+                    }
+                }
+            } else if !args.quiet {
                 site.visualize();
             }
         }
+        flags::MockingbirdCmd::Watch(args) => {
+            let (input, output) = (&args.input, &args.output);
+            let (config, future, quiet) = (args.config.as_deref(), args.future, args.quiet);
+            watch::rebuild(input, output, config, future, quiet);
+            if !quiet {
+                eprintln!("watching {} for changes", input.display());
+            }
+
+            let watched = watch::watch(input, output, |paths| {
+                if !quiet {
+                    eprintln!("{} path(s) changed: rebuilding", paths.len());
+                }
+
+                watch::rebuild(input, output, config, future, quiet);
+                std::ops::ControlFlow::Continue(())
+            });
+
+            if let Err(e) = watched {
+                eprintln!("error: {e}");
+                std::process::exit(1)
+            }
+        }
+        flags::MockingbirdCmd::Check(args) => {
+            let errors = check(&args.input, args.config.as_deref()).unwrap_or_else(|e| {
+                eprintln!("error: {e}");
+                std::process::exit(1)
+            });
+
+            for error in &errors {
+                eprintln!("error: {error}");
+            }
+
+            if !errors.is_empty() {
+                eprintln!("check failed with {} error(s)", errors.len());
+                std::process::exit(1)
+            }
+        }
         flags::MockingbirdCmd::Version(_) => {
             println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
         }
@@ -1,18 +1,26 @@
 use std::sync::Arc;
 use std::path::{PathBuf, Path};
 use std::borrow::Cow;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rustc_hash::FxHashMap;
 
 use harper::rayon::prelude::*;
-use harper::url::UrlBuf;
-use harper::error::{Result, Chainable};
-use harper::{error, render_site, Collection, Site};
-use harper::{Item, Kind, Renderer};
-use harper::value::{Grass, Json, Mapper, Sink, Source, Toml};
+use harper::url::{Url, UrlBuf};
+use harper::error::{Chainable, Error, Result};
+use harper::{err, error, render_site, Collection, Site};
+use harper::{Item, Kind, Metadata, Renderer};
+use harper::value::{Dict, Grass, Json, Mapper, Sink, Source, Toml, Value};
 use harper::markdown::{self, *};
-use harper::path_str::IntoPathStrLossy;
+use harper::feed::{Feed, FeedEntry};
+use harper::sitemap::Sitemap;
+use harper::path_str::{IntoPathStrLossy, PathStr};
 
 use crate::util::{StringExt, ValueExt};
-use crate::{Content, Draft, PermaPath, Slug, Snip, Template, Toc, UrlRef};
+use crate::{Canonical, Content, Draft, PermaPath, Permalink, Raw, Slug, Template, Toc, TocHtml, UrlRef};
+use crate::{Alternates, List, Outputs};
+use crate::{NextPageUrl, Page, Paginate, PrevPageUrl, TotalPages};
+use crate::{CharCount, Snip, SnipTruncated, WordCount};
 use crate::discover::Mockingbird;
 
 impl Renderer for Mockingbird {
@@ -22,81 +30,144 @@ impl Renderer for Mockingbird {
 
     fn render_site(&self, site: &Arc<Site>) -> Result<Self::Output> {
         render_site(self, site)?;
+        self.paginate(site)?;
+        self.check_output_paths(site)?;
+        self.check_unwritten(site);
+        self.write_search_indexes(site)?;
+        self.write_feeds(site)?;
 
-        site.collections.par_iter().map(|(_, collection)| collection.par_map_items(|_, item| {
-            // TODO: Validate template path? TODO: Validate permapath?
-            let Some(Ok(permapath)) = item.metadata.get(PermaPath) else {
-                return Ok(());
-            };
-
-            let output = self.output.join(permapath);
-            std::fs::create_dir_all(output.parent().unwrap())?;
-
-            match item.metadata.get(Template) {
-                Some(Err(e)) => return Err(e.type_err(Template, "invalid template value")),
-                Some(Ok(template)) => {
-                    output.write(self.config.engine
-                        .render(template.as_str(), site, Some(collection), item)
-                        .chain_with(|| error! {
-                            "failed to render item",
-                            "path" => item.entry.relative_path().display(),
-                            "template used" => template.as_str(),
-                        })?)
-                },
-                None => {
-                    let content: Arc<str> = item.entry.try_read()?;
-                    if !harper::util::is_template(&*content) {
-                        return output.write(content);
+        site.collections.par_iter().map(|(_, collection)| collection.par_map_items(|kind, item| {
+            // Skip writing outputs that are unchanged since the last build.
+            if let Some(incremental) = &self.incremental {
+                if let Some(fingerprint) = incremental.fingerprint(self, kind, collection, item)? {
+                    if !incremental.is_fresh(self, item, &fingerprint)? {
+                        self.write_item(site, collection, item)?;
                     }
 
-                    let name = item.entry.relative_path().to_string_lossy();
-                    output.write(self.config.engine
-                        .render_raw(Some(&*name), &content, site, Some(collection), item)
-                        .chain_with(|| error! {
-                            "failed to render direct item",
-                            "path" => name,
-                        })?)
+                    incremental.record(item, fingerprint);
+                    return Ok(());
                 }
             }
-        })).collect()
+
+            self.write_item(site, collection, item)
+        })).collect::<Result<()>>()?;
+
+        self.write_sitemap(site)?;
+        self.precompress()?;
+        if let Some(incremental) = &self.incremental {
+            incremental.save(&self.output)?;
+        }
+
+        Ok(())
     }
 
-    // TODO: We would like to be able to templatize JSON too.
     fn render_collection_item(&self,
         kind: Kind,
         _: &Arc<Site>,
         collection: &Arc<Collection>,
         item: &Arc<Item>
     ) -> Result<Self::Render> {
-        const KNOWN_EXTS: &[&str] = &["md", "mdown", "markdown", "toml", "json"];
+        // An unchanged item is restored from the previous build's render cache.
+        let Some(incremental) = &self.incremental else {
+            let search_docs = self.render_item(kind, collection, item)?;
+            return self.add_search_docs(collection, search_docs);
+        };
 
-        if let Some(Ok(true)) = item.metadata.get(Draft) {
-            return Ok(());
+        let hash = incremental.render_hash(kind, item)?;
+        let search_docs = match incremental.restore(self, item, hash) {
+            Some(search_docs) => search_docs,
+            None => self.render_item(kind, collection, item)?,
+        };
+
+        incremental.cache(self, item, hash, search_docs.clone());
+        self.add_search_docs(collection, search_docs)
+    }
+
+    fn render_site_item(&self, item: &Item) -> Result<()> {
+        // SCSS and Sass imports aren't tracked, so they're always compiled.
+        let sass = matches!(item.entry.file_ext(), Some("scss" | "sass"));
+        let Some(incremental) = self.incremental.as_ref().filter(|_| !sass) else {
+            return self.write_asset(item);
+        };
+
+        let fingerprint = incremental.asset_fingerprint(&item.entry)?;
+        if !incremental.is_fresh(self, item, &fingerprint)? {
+            self.write_asset(item)?;
         }
 
+        incremental.record(item, fingerprint);
+        Ok(())
+    }
+}
+
+impl Mockingbird {
+    /// Renders the collection `item`, returning its search documents, if it's
+    /// indexed.
+    // TODO: We would like to be able to templatize JSON too.
+    fn render_item(&self,
+        kind: Kind,
+        collection: &Arc<Collection>,
+        item: &Arc<Item>
+    ) -> Result<Option<Vec<LunrDocument>>> {
         let entry = &*item.entry;
+        if let Some(Ok(true)) = item.metadata.get(Draft) {
+            self.note_at(entry.relative_path(), error! {
+                "draft is not rendered",
+                "path" => entry.relative_path().display(),
+            });
+
+            return Ok(None);
+        }
+
+        let settings = &self.config.settings.content;
+        let mut search_docs = None;
         match entry.file_ext() {
             Some("md") | Some("mdown") | Some("markdown") => {
                 let engine = self.config.engine.clone();
+                let mut indexer = LunrIndexer::default();
                 Markdown::from(entry)
                     .plugin(FrontMatter::new(Toml, &item.metadata))
-                    .plugin(Templatize::with(entry.relative_path(), engine, &item.metadata))
+                    .plugin(Templatize::with(entry.relative_path(), engine, &item.metadata).skip_if(Raw))
+                    .plugin(LuaTransforms(self))
                     .plugin(Alias::new(&self.config.settings.aliases))
-                    .plugin(AutoHeading::default())
-                    .plugin(TableOfContents::new(item.metadata.metakey(Toc)))
-                    .plugin(Snippet::new(item.metadata.metakey(Snip), 250))
-                    .plugin(Admonition::default())
-                    .plugin(AutoHeading::default())
+                    .plugin(HeadingShift::by(settings.heading_offset))
+                    .plugin(AutoHeading::with_style(settings.heading_slugs))
+                    .plugin(TableOfContents::new(item.metadata.metakey(Toc))
+                        .render_html(item.metadata.metakey(TocHtml)))
+                    .plugin(Snippet::new(item.metadata.metakey(Snip), settings.summary_length)
+                        .report_truncation(item.metadata.metakey(SnipTruncated)))
+                    .plugin(Admonition::with_config(&self.config.settings.admonitions))
+                    .plugin(Math::with_config(&self.config.settings.math))
+                    .plugin(AutoHeading::with_style(settings.heading_slugs))
                     .plugin(HeadingAnchor::default())
+                    .plugin(&mut indexer)
+                    .plugin(DiagramPassthrough::with_config(&self.config.settings.diagrams))
                     .plugin(CodeTrim::trim(|l, _| l.trim().starts_with("# ") || l.trim() == "#"))
                     .plugin(CodeTrim::trim_start())
                     .plugin(Alias::new(&self.config.settings.aliases))
                     // .plugin(TsHighligher::default())
-                    .plugin(SyntaxHighlight::default())
+                    .plugin(SyntaxHighlight::with_style(&self.config.settings.code))
+                    .plugin(Footnotes)
                     .plugin(Parts::new(item.metadata.key("parts")))
                     .plugin(markdown::Renderer::new(item.metadata.metakey(Content)))
                     .run()
-                    .chain_with(|| "markdown rendering failed")?;
+                    .chain_with(|| error! {
+                        "markdown rendering failed",
+                        "path" => entry.relative_path().display(),
+                    })?;
+
+                search_docs = Some(indexer);
+            },
+            _ if self.has_front_matter(item) => {
+                let content: Arc<str> = entry.try_read()?;
+                let body = FrontMatter::new(Toml, &item.metadata)
+                    .preprocess(&content)
+                    .chain_with(|| error! {
+                        "front matter extraction failed",
+                        "path" => entry.relative_path().display(),
+                    })?;
+
+                item.metadata.insert(Content, &*body);
             },
             Some("toml") => Toml.map_copy(entry, &item.metadata).chain_with(|| error! {
                 "TOML deserialization failed",
@@ -109,17 +180,50 @@ impl Renderer for Mockingbird {
             _ => { }
         };
 
+        let inject = &self.config.settings.inject;
+        item.metadata.inject(&self.injected, inject.overwrite);
+
+        // Front matter may have made this a draft or embargoed it by date.
+        // Either way, it's treated as a draft from here on.
+        if matches!(kind, Kind::Index | Kind::Item(_)) {
+            let unpublished = match item.is_draft() {
+                true if !settings.drafts => Some("draft is not rendered"),
+                _ if settings.future || settings.drafts => None,
+                _ => self.embargo(item),
+            };
+
+            if let Some(reason) = unpublished {
+                item.metadata.insert(Draft, true);
+                self.note_at(entry.relative_path(), error! {
+                    reason,
+                    "path" => entry.relative_path().display(),
+                });
+
+                return Ok(None);
+            }
+        }
+
+        let is_data = matches!(entry.file_ext(), Some("toml" | "json"));
+        if let Some(field) = settings.data_summary_from.as_deref().filter(|_| is_data) {
+            summarize_data(item, field, settings.summary_length);
+        }
+
         // Computte the permapath and Url.
-        let content_root = &self.tree[self.content_root];
-        let group_perma = collection.entry.path_relative_to(content_root).unwrap();
-        let rendered = entry.file_ext().map_or(false, |e| KNOWN_EXTS.contains(&e));
+        let group_perma = self.content_path(&collection.entry);
+        let rendered = is_rendered_ext(entry.file_ext());
         let slug = item.metadata
-            .get_or_insert_with(Slug, || item.entry.file_stem().slugify())
+            .get_or_insert_with(Slug, || {
+                settings.slug_from.as_deref()
+                    .and_then(|key| item.metadata.get_raw(key))
+                    .and_then(|value| value.as_str().map(harper::util::slugify))
+                    .filter(|slug| !slug.is_empty())
+                    .unwrap_or_else(|| item.entry.file_stem().slugify().to_string())
+            })
             .map_err(|v| v.type_err(Slug, "invalid slug"))?;
 
-        let (permapath, mut url): (Cow<'_, Path>, _) = match (kind, rendered) {
+        let (permapath, url): (Cow<'_, Path>, _) = match (kind, rendered) {
             (Kind::Index, true) => {
-                let mut url = UrlBuf::from(group_perma);
+                let mut url = UrlBuf::from(&*group_perma);
                 url.append("/");
 
                 (group_perma.join("index.html").into(), url)
@@ -131,66 +235,154 @@ impl Renderer for Mockingbird {
 
                 (dir.join("index.html").into(), url)
             }
-            (Kind::Datum(_), true) => return Ok(()),
+            (Kind::Datum(_), true) => return Ok(None),
             (_, false) => {
-                let path = item.entry
-                    .path_relative_to(content_root)
-                    .unwrap();
-
-                (path.into(), UrlBuf::from(&*path))
+                let path = self.content_path(&item.entry);
+                let url = UrlBuf::from(&*path);
+                (path.into(), url)
             },
         };
 
-        url.make_relative().prepend(&self.config.settings.root);
-        item.metadata.insert(PermaPath, permapath);
-        item.metadata.insert(UrlRef, url);
-
-        let template_name = match kind {
-            Kind::Index => "index.html",
-            Kind::Item(_) => "page.html",
-            Kind::Datum(_) => "data.html",
+        let (permapath, mut url) = match item.metadata.get(Permalink) {
+            Some(Ok(permalink)) => {
+                let (path, url) = permalink_paths(&permalink)?;
+                (path.into(), url)
+            }
+            Some(Err(v)) => return Err(v.type_err(Permalink, "invalid permalink")),
+            None => (permapath, url),
         };
 
-        let template = self.template_root.and_then(|subtree| {
-            for parent in group_perma.ancestors() {
-                let template_path = parent.join(template_name);
-                if self.tree.get_file_id(subtree, &template_path).is_some() {
-                    return Some(template_path);
-                }
+        url.make_relative();
+        if let Some(canonical) = canonical_url(&self.config.settings.root, &url) {
+            item.metadata.insert(Canonical, canonical);
+        }
 
-                let template_path = parent.with_extension("html");
-                if self.tree.get_file_id(subtree, &template_path).is_some() {
-                    return Some(template_path);
-                }
+        url.prepend(&self.config.settings.root);
+        if let Some(text) = &settings.read_more {
+            let truncated = matches!(item.metadata.get(SnipTruncated), Some(Ok(true)));
+            if let (true, Some(Ok(snippet))) = (truncated, item.metadata.get(Snip)) {
+                item.metadata.insert(Snip, read_more(&snippet, &url, text));
             }
+        }
 
-            self.tree.get_file_id(subtree, "default.html")
-                .map(|_| PathBuf::from("default.html"))
+        let search_docs = search_docs.filter(|_| settings.search_index).map(|mut indexer| {
+            indexer.identify(&item.stable_id(), url.as_str());
+            indexer.docs
         });
 
-        if let Some(template_path) = template {
-            item.metadata.insert(Template, template_path.to_path_buf().into_path_str_lossy());
+        // An index with `list = false` is a standalone page, rendered like
+        // any other item, instead of as the collection's listing.
+        let template_stem = match kind {
+            Kind::Index if !item.metadata.try_get(List)?.unwrap_or(true) => "page",
+            Kind::Index => "index",
+            Kind::Item(_) => "page",
+            Kind::Datum(_) => "data",
+        };
+
+        // Other formats are written next to the HTML output, with the same
+        // stem, and rendered with their own templates: `page.json`, etc.
+        let formats = match rendered {
+            true => output_formats(item)?,
+            false => vec![OutputFormat::Html],
+        };
+
+        let mut alternates: Dict = Dict::new();
+        let mut primary_url = None;
+        for &format in formats.iter().filter(|&&f| f != OutputFormat::Html) {
+            let Some(template) = self.find_template(&group_perma, template_stem, format.ext()) else {
+                self.warn_at(entry.relative_path(), error! {
+                    "no template found for item output format: it is not written",
+                    "path" => entry.relative_path().display(),
+                    "format" => format.ext(),
+                });
+
+                continue;
+            };
+
+            let path = permapath.with_extension(format.ext());
+            let mut format_url = UrlBuf::from(&*path);
+            format_url.make_relative();
+            format_url.prepend(&self.config.settings.root);
+            primary_url.get_or_insert_with(|| format_url.clone());
+
+            let alternate: Dict<&str> = Dict::from([
+                ("permapath", Value::from(path)),
+                ("url", Value::from(format_url)),
+                ("template", Value::from(template)),
+            ]);
+
+            alternates.insert(format.ext().into(), Value::from(alternate));
+        }
+
+        if !alternates.is_empty() {
+            item.metadata.insert(Alternates, Arc::new(alternates));
+        }
+
+        if !formats.contains(&OutputFormat::Html) {
+            item.metadata.insert(UrlRef, primary_url.unwrap_or(url));
+            return Ok(search_docs);
+        }
+
+        item.metadata.insert(PermaPath, permapath);
+        item.metadata.insert(UrlRef, url);
+
+        // Other files, like stylesheets, are copied or templatized as-is.
+        if !rendered && !self.has_front_matter(item) {
+            return Ok(search_docs);
+        }
+
+        match self.find_template(&group_perma, template_stem, "html") {
+            Some(template_path) => {
+                item.metadata.insert(Template, template_path.into_path_str_lossy());
+            }
+            None if rendered && !is_data && !item.metadata.contains(Template) => {
+                self.warn_at(entry.relative_path(), error! {
+                    "no template found for item: its content is written as-is",
+                    "path" => entry.relative_path().display(),
+                });
+            }
+            None => {}
+        }
+
+        Ok(search_docs)
+    }
+
+    fn add_search_docs(&self, collection: &Collection, docs: Option<Vec<LunrDocument>>) -> Result<()> {
+        if let Some(docs) = docs {
+            self.search_docs.lock().unwrap()
+                .entry(collection.entry.id)
+                .or_default()
+                .extend(docs);
         }
 
         Ok(())
     }
 
-    fn render_site_item(&self, item: &Item) -> Result<()> {
+    /// Copies the asset `item` to its output path, compiling SCSS and Sass.
+    fn write_asset(&self, item: &Item) -> Result<()> {
         // TODO: Add cache key `?HASH`?
         let entry = &*item.entry;
-        let permapath = match item.metadata.get(PermaPath) {
-            Some(perma) => perma.map_err(|v| v.type_err(PermaPath, entry.path.display()))?,
-            None => return Ok(()),
+        let Some(permapath) = item.path(PermaPath)? else {
+            return Ok(());
         };
 
-        // TODO: Case-inensitive check.
+        // `case_insensitive_templates` only affects lookups in the input tree.
+        // Output paths mirror their source paths, so there's nothing to match.
         let output = self.output.join(&permapath);
         std::fs::create_dir_all(output.parent().unwrap())?;
         match entry.file_ext() {
             Some("scss") | Some("sass") => {
-                Grass::default().map_copy(&*entry.path, output.with_extension("css"))
+                let root = &self.tree[self.tree.root_id()].path;
+                self.config.settings.sass.include_paths.iter()
+                    .fold(Grass::default(), |grass, path| grass.load_path(root.join(path)))
+                    .map_copy(&*entry.path, output.with_extension("css"))
             },
-            _ => entry.path.read_to(&output).chain_with(|| error! {
+            // Copy on-disk assets without reading them into memory: they may
+            // be arbitrarily large. In-memory entries are written directly.
+            _ => match entry.contents {
+                None => std::fs::copy(&entry.path, &output).map(|_| ()).map_err(Error::from),
+                Some(_) => entry.read_to(&output),
+            }.chain_with(|| error! {
                 "failed to copy asset",
                 "source path" => entry.path.display(),
                 "destination path" => output.display(),
@@ -198,3 +390,656 @@ impl Renderer for Mockingbird {
         }
     }
 }
+
+/// Runs the markdown transforms registered by Lua plugins, if there are any.
+struct LuaTransforms<'a>(#[allow(unused)] &'a Mockingbird);
+
+impl Plugin for LuaTransforms<'_> {
+    fn preprocess<'a>(&self, input: &'a str) -> Result<Cow<'a, str>> {
+        #[cfg(feature = "plugins")]
+        if let Some(transforms) = &self.0.lua_markdown {
+            return transforms.preprocess(input);
+        }
+
+        Ok(Cow::Borrowed(input))
+    }
+}
+
+impl Mockingbird {
+    /// Whether `item` is a non-markdown file with extractable front matter.
+    fn has_front_matter(&self, item: &Item) -> bool {
+        self.config.settings.content.has_front_matter(item.entry.file_ext())
+    }
+
+    /// Finds the template, with extension `ext`, for an item in the collection
+    /// at `group_perma`: `{stem}.{ext}` or `{dir}.{ext}` in the nearest
+    /// directory, going up, that has one, or else `default.{ext}`. With
+    /// `content.case_insensitive_templates`, names are matched without regard
+    /// to ASCII case, and the template's actual path is returned.
+    fn find_template(&self, group_perma: &Path, stem: &str, ext: &str) -> Option<PathBuf> {
+        let subtree = self.template_root?;
+        let find = |path: PathBuf| match self.config.settings.content.case_insensitive_templates {
+            false => self.tree.get_file_id(subtree, &path).map(|_| path),
+            true => self.tree.get_file_id_ci(subtree, &path).and_then(|id| {
+                let path = self.tree[id].path.strip_prefix(&self.tree[subtree].path).ok()?;
+                Some(path.to_path_buf())
+            }),
+        };
+
+        for parent in group_perma.ancestors() {
+            if let Some(template_path) = find(parent.join(stem).with_extension(ext)) {
+                return Some(template_path);
+            }
+
+            if let Some(template_path) = find(parent.with_extension(ext)) {
+                return Some(template_path);
+            }
+        }
+
+        find(PathBuf::from("default").with_extension(ext))
+    }
+
+    /// Ensures that no two items are written to the same output path.
+    fn check_output_paths(&self, site: &Site) -> Result<()> {
+        let collection_items = site.collections.values().flat_map(|collection| {
+            collection.index.iter()
+                .chain(collection.items.iter())
+                .chain(collection.data.values().flat_map(|data| data.iter()))
+        });
+
+        let mut seen: FxHashMap<Arc<Path>, &Arc<Item>> = FxHashMap::default();
+        for item in collection_items.chain(site.items.iter()) {
+            let alternates = alternate_outputs(item)?.into_iter().map(|(_, path, _)| path);
+            let pages = self.pages(item).into_iter()
+                .map(|page| page.path(PermaPath))
+                .collect::<Result<Vec<_>>>()?;
+
+            let paths = item.path(PermaPath)?.into_iter().chain(alternates).chain(pages.into_iter().flatten());
+            for permapath in paths {
+                if let Some(existing) = seen.insert(permapath.clone(), item) {
+                    return err! {
+                        "multiple items share an output path",
+                        "output path" => permapath.display(),
+                        "first item" => existing.entry.relative_path().display(),
+                        "second item" => item.entry.relative_path().display(),
+                    };
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Mockingbird {
+    /// Returns why `item` isn't published, if it isn't, because its `date` is
+    /// in the future or its `expiry_date` has passed. Dates that can't be
+    /// parsed are warned about and otherwise ignored.
+    fn embargo(&self, item: &Item) -> Option<&'static str> {
+        let timestamp = |key: &str| {
+            let value = item.metadata.get_raw(key)?;
+            let timestamp = harper::util::timestamp(&value);
+            if timestamp.is_none() {
+                self.warn_at(item.entry.relative_path(), error! {
+                    "ignoring unrecognized date",
+                    "path" => item.entry.relative_path().display(),
+                    "field" => key,
+                    "value" => value.to_json(),
+                });
+            }
+
+            timestamp
+        };
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        match (timestamp("date"), timestamp("expiry_date")) {
+            (Some(date), _) if date > now => Some("future item is not rendered"),
+            (_, Some(expiry)) if expiry <= now => Some("expired item is not rendered"),
+            _ => None,
+        }
+    }
+
+    /// Warns about collection items that were neither rendered, templatized,
+    /// nor copied as intended, listing their paths by why:
+    ///
+    ///   * Items with no output at all, e.g. because none of their `outputs`
+    ///     has a template.
+    ///   * Items copied verbatim because of an unknown extension that's likely
+    ///     a misspelled markdown one, e.g. `.mdx`, and any index copied
+    ///     verbatim, which leaves its collection without an index page.
+    ///   * Data files, TOML or JSON, that aren't in a data group and have no
+    ///     template, so their source is written as their page.
+    ///
+    /// Drafts and data items in data groups are expected to have no output.
+    fn check_unwritten(&self, site: &Site) {
+        #[derive(PartialEq, Eq, PartialOrd, Ord)]
+        enum Unwritten { NoOutput, UnknownExt, NoDataTemplate }
+
+        let unwritten = |kind: Kind, item: &Arc<Item>| {
+            let ext = item.entry.file_ext();
+            let written = item.metadata.contains(PermaPath) || item.metadata.contains(Alternates);
+            let verbatim = !is_rendered_ext(ext) && !self.has_front_matter(item);
+            let is_data = matches!(ext, Some("toml" | "json"));
+            let markdown_like = ext.is_some_and(|ext| {
+                ext.starts_with("md") || ext.starts_with("markdown")
+                    || matches!(ext, "mkd" | "mkdn" | "mdwn" | "rmd")
+            });
+
+            match kind {
+                _ if item.is_draft() => None,
+                Kind::Datum(_) => None,
+                _ if !written => Some(Unwritten::NoOutput),
+                Kind::Index if verbatim => Some(Unwritten::UnknownExt),
+                Kind::Item(_) if verbatim && markdown_like => Some(Unwritten::UnknownExt),
+                _ if is_data && !item.metadata.contains(Template) => Some(Unwritten::NoDataTemplate),
+                _ => None,
+            }
+        };
+
+        let mut paths: Vec<_> = site.collections.values()
+            .flat_map(|collection| collection.map_items_ordered(|kind, item| {
+                let why = unwritten(kind, item)?;
+                Some((why, item.entry.relative_path().display().to_string()))
+            }))
+            .flatten()
+            .collect();
+
+        paths.sort();
+        for chunk in paths.chunk_by(|a, b| a.0 == b.0) {
+            let paths = chunk.iter().map(|(_, path)| &**path).collect::<Vec<_>>().join(", ");
+            self.warn(match chunk[0].0 {
+                Unwritten::NoOutput => error! {
+                    "content files were neither rendered, templatized, nor copied",
+                    "paths" => paths,
+                    "help" => "check for missing templates for their `outputs`",
+                },
+                Unwritten::UnknownExt => error! {
+                    "content files with unknown extensions were copied verbatim",
+                    "paths" => paths,
+                    "help" => "check for misspelled extensions, e.g. `.mdx` for `.md`",
+                },
+                Unwritten::NoDataTemplate => error! {
+                    "data files without a template were written as-is",
+                    "paths" => paths,
+                    "help" => "add a template for them or move them into a data group",
+                },
+            });
+        }
+    }
+}
+
+impl Mockingbird {
+    /// Splits the listing of every index with `paginate = N` into pages of `N`
+    /// published items. The index itself is page 1. Pages 2 and on are copies
+    /// of it written to `page/2/index.html`, etc., next to the index's output,
+    /// and are kept in `self.pages`. Every page gets its `page` number,
+    /// `total_pages`, and, where they exist, `next_page_url` and
+    /// `prev_page_url`.
+    fn paginate(&self, site: &Site) -> Result<()> {
+        let mut pages = FxHashMap::default();
+        for collection in site.collections.values() {
+            let Some(index) = collection.index.as_ref().filter(|index| site.is_listed(index)) else {
+                continue;
+            };
+
+            let per_page = match index.metadata.get(Paginate) {
+                Some(Ok(n)) if n > 0 => n,
+                Some(_) => return err! {
+                    "invalid `paginate`: expected a positive number of items per page",
+                    "path" => index.entry.relative_path().display(),
+                },
+                None => continue,
+            };
+
+            let Some(permapath) = index.path(PermaPath)? else {
+                continue;
+            };
+
+            let published = collection.items.iter().filter(|item| site.is_listed(item)).count();
+            let total = published.div_ceil(per_page).max(1);
+            let dir = permapath.parent().unwrap_or(Path::new(""));
+
+            // Page `n`'s output path, URL, and canonical URL.
+            let root = &self.config.settings.root;
+            let outputs: Vec<(Arc<Path>, Arc<Url>, Option<UrlBuf>)> = (2..=total).map(|n| {
+                let path = dir.join("page").join(n.to_string());
+                let mut url = UrlBuf::from(&*path);
+                url.append("/");
+                url.make_relative();
+                let canonical = canonical_url(root, &url);
+                url.prepend(root);
+                (path.join("index.html").into(), url.into(), canonical)
+            }).collect();
+
+            let urls: Vec<Arc<Url>> = index.url(UrlRef)?.into_iter()
+                .chain(outputs.iter().map(|(_, url, _)| url.clone()))
+                .collect();
+
+            let number = |item: &Item, n: usize| {
+                item.metadata.insert(Page, n);
+                item.metadata.insert(TotalPages, total);
+                item.metadata.remove(NextPageUrl);
+                item.metadata.remove(PrevPageUrl);
+                if let Some(next) = urls.get(n) {
+                    item.metadata.insert(NextPageUrl, next.clone());
+                }
+
+                if let Some(prev) = n.checked_sub(2).and_then(|i| urls.get(i)) {
+                    item.metadata.insert(PrevPageUrl, prev.clone());
+                }
+            };
+
+            number(index, 1);
+            let copies = outputs.into_iter().zip(2..).map(|((path, url, canonical), n)| {
+                let metadata = Metadata::new();
+                if let Some(dict) = index.metadata.to_value().as_dict() {
+                    metadata.append_all(dict);
+                }
+
+                // Only the index's page is paginated, not its other formats.
+                metadata.remove(Alternates);
+                metadata.remove(Canonical);
+                if let Some(canonical) = canonical {
+                    metadata.insert(Canonical, canonical);
+                }
+
+                metadata.insert(PermaPath, path);
+                metadata.insert(UrlRef, url);
+                let page = Item { entry: index.entry.clone(), metadata };
+                number(&page, n);
+                Arc::new(page)
+            }).collect();
+
+            pages.insert(index.entry.id, copies);
+        }
+
+        *self.pages.lock().unwrap() = pages;
+        Ok(())
+    }
+
+    /// Pages 2 and on of `item`, if it's a paginated index. See
+    /// [`Mockingbird::paginate()`].
+    pub fn pages(&self, item: &Item) -> Vec<Arc<Item>> {
+        self.pages.lock().unwrap().get(&item.entry.id).cloned().unwrap_or_default()
+    }
+
+    /// Renders and writes every output of the collection `item`: its page, if
+    /// it has one, and its alternate output formats.
+    fn write_item(&self, site: &Arc<Site>, collection: &Arc<Collection>, item: &Arc<Item>) -> Result<()> {
+        for (format, permapath, template) in alternate_outputs(item)? {
+            let output = self.output.join(permapath);
+            std::fs::create_dir_all(output.parent().unwrap())?;
+            output.write(self.config.engine
+                .render(template.as_str(), site, Some(collection), item)
+                .chain_with(|| error! {
+                    "failed to render item output format",
+                    "path" => item.entry.relative_path().display(),
+                    "format" => format,
+                    "template used" => template.as_str(),
+                })?)?;
+        }
+
+        self.write_page(site, collection, item)?;
+        for page in self.pages(item) {
+            self.write_page(site, collection, &page)?;
+        }
+
+        Ok(())
+    }
+
+    /// Renders and writes the page of the collection `item`, if it has one.
+    fn write_page(&self, site: &Arc<Site>, collection: &Arc<Collection>, item: &Arc<Item>) -> Result<()> {
+        // TODO: Validate template path?
+        let Some(permapath) = item.path(PermaPath)? else {
+            return Ok(());
+        };
+
+        let output = self.output.join(permapath);
+        std::fs::create_dir_all(output.parent().unwrap())?;
+
+        match item.metadata.get(Template) {
+            Some(Err(e)) => Err(e.type_err(Template, "invalid template value")),
+            Some(Ok(template)) => {
+                output.write(self.config.engine
+                    .render(template.as_str(), site, Some(collection), item)
+                    .chain_with(|| error! {
+                        "failed to render item",
+                        "path" => item.entry.relative_path().display(),
+                        "template used" => template.as_str(),
+                    })?)
+            },
+            None => {
+                let content: Arc<str> = match item.content() {
+                    Ok(Some(body)) if self.has_front_matter(item) => body,
+                    _ => item.entry.try_read()?,
+                };
+
+                let raw = matches!(item.metadata.get(Raw), Some(Ok(true)));
+                if raw || !harper::util::is_template(&content) {
+                    return output.write(content);
+                }
+
+                let name = item.entry.relative_path().to_string_lossy();
+                output.write(self.config.engine
+                    .render_raw(Some(&*name), &content, site, Some(collection), item)
+                    .chain_with(|| error! {
+                        "failed to render direct item",
+                        "path" => name,
+                    })?)
+            }
+        }
+    }
+
+    /// Writes a `search-index.json` into the output directory of every
+    /// collection with indexed items.
+    fn write_search_indexes(&self, site: &Site) -> Result<()> {
+        let search_docs = std::mem::take(&mut *self.search_docs.lock().unwrap());
+        for (id, docs) in search_docs {
+            let Some(collection) = site.collections.get(&id) else {
+                continue;
+            };
+
+            let dir = self.output.join(self.content_path(&collection.entry));
+            std::fs::create_dir_all(&dir)?;
+
+            let index = LunrIndexer::index(&docs);
+            dir.join("search-index.json").write(index.to_json())?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the feeds registered in `site.feeds`, each to the output path of
+    /// its URL: the directory of its collection or the output's root.
+    fn write_feeds(&self, site: &Site) -> Result<()> {
+        let config = &self.config.settings.feeds;
+        for feed in &site.feeds {
+            let collection = feed.collection.and_then(|id| site.collections.get(&id));
+            let dir = collection.map(|c| self.content_path(&c.entry)).unwrap_or_default();
+
+            let mut link = UrlBuf::from(&*dir);
+            link.append("/");
+            link.make_relative();
+            link.prepend(&self.config.settings.root);
+
+            let title: Arc<str> = match collection {
+                Some(collection) => collection.index.as_ref()
+                    .and_then(|index| index.metadata.get_raw("title")?.into_str().ok())
+                    .unwrap_or_else(|| collection.name.clone()),
+                None => config.title.as_deref()
+                    .or_else(|| self.config.settings.globals.get("title")?.as_str())
+                    .unwrap_or_default()
+                    .into(),
+            };
+
+            let mut builder = Feed::new(feed.format, &title, &link, &feed.url);
+            if let Some(limit) = config.limit {
+                builder = builder.limit(limit);
+            }
+
+            let author = config.author.as_deref()
+                .or_else(|| self.config.settings.globals.get("author")?.as_str());
+
+            if let Some(author) = author {
+                builder = builder.author(author);
+            }
+
+            builder = builder.omit_undated(config.omit_undated);
+
+            let mut collections: Vec<_> = match collection {
+                Some(collection) => vec![collection],
+                None => site.collections.values().collect(),
+            };
+
+            collections.sort_by(|a, b| a.entry.path.cmp(&b.entry.path));
+            for collection in collections {
+                builder = builder.entries(collection.items.iter().filter_map(|i| feed_entry(i)));
+            }
+
+            let output = self.output.join(&dir).join(config.file_name());
+            std::fs::create_dir_all(output.parent().unwrap())?;
+            output.write(builder.render())?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes `sitemap.xml` to the output's root, listing the URL of every
+    /// page written, dated by the modification time of its source. Sitemap
+    /// URLs must be absolute, so nothing is written unless `root` has a host,
+    /// which is noted.
+    fn write_sitemap(&self, site: &Site) -> Result<()> {
+        if !self.config.settings.build.sitemap {
+            return Ok(());
+        }
+
+        if self.config.settings.root.scheme().is_none() {
+            self.note(error! {
+                "sitemap not written: `root` has no host, and sitemap URLs must be absolute",
+                "root" => self.config.settings.root.as_str(),
+                "help" => "set `root` to the site's full URL, e.g. `https://example.com`, \
+                    or set `build.sitemap = false`",
+            });
+
+            return Ok(());
+        }
+
+        let mut sitemap = Sitemap::default();
+        // Only pages are listed, not assets like stylesheets.
+        let mut add = |item: &Item| {
+            let is_page = match item.metadata.get(PermaPath) {
+                Some(Ok(path)) => path.extension().is_some_and(|ext| ext == "html"),
+                _ => false,
+            };
+
+            if item.is_draft() || !is_page {
+                return;
+            }
+
+            if let Some(Ok(url)) = item.metadata.get(UrlRef) {
+                let lastmod = item.entry.fs_metadata().and_then(|m| m.modified().ok());
+                sitemap.url(&url, lastmod);
+            }
+        };
+
+        site.items.iter().for_each(|item| add(item));
+        for collection in site.collections.values() {
+            collection.index.iter().chain(collection.items.iter()).for_each(|item| add(item));
+            if let Some(index) = &collection.index {
+                self.pages(index).iter().for_each(|page| add(page));
+            }
+        }
+
+        std::fs::create_dir_all(&self.output)?;
+        self.output.join("sitemap.xml").write(sitemap.render())
+    }
+}
+
+/// Whether files with extension `ext` are rendered from markdown or data.
+fn is_rendered_ext(ext: Option<&str>) -> bool {
+    const KNOWN_EXTS: &[&str] = &["md", "mdown", "markdown", "toml", "json"];
+    ext.is_some_and(|e| KNOWN_EXTS.contains(&e))
+}
+
+/// A format an item can be written in, in addition to or instead of HTML,
+/// named in its `outputs`, e.g. `outputs = ["html", "json"]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Html,
+    Json,
+}
+
+impl OutputFormat {
+    /// The format's name in `outputs`, which is also its output and template
+    /// file extension.
+    fn ext(self) -> &'static str {
+        match self {
+            OutputFormat::Html => "html",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [OutputFormat::Html, OutputFormat::Json].into_iter().find(|f| f.ext() == name)
+    }
+}
+
+/// The formats `item` is written in: those in its `outputs`, or just HTML.
+fn output_formats(item: &Item) -> Result<Vec<OutputFormat>> {
+    let names = match item.metadata.get(Outputs) {
+        Some(Ok(names)) => names,
+        Some(Err(v)) => return Err(v.type_err(Outputs, "invalid outputs")),
+        None => return Ok(vec![OutputFormat::Html]),
+    };
+
+    names.iter()
+        .map(|name| OutputFormat::from_name(name).ok_or_else(|| error! {
+            "unknown output format",
+            "format" => name,
+            "path" => item.entry.relative_path().display(),
+            "known formats" => "html, json",
+        }))
+        .collect()
+}
+
+/// An alternate output's format name, output path, and template.
+pub type AlternateOutput = (Arc<str>, Arc<Path>, Arc<PathStr>);
+
+/// Every output of `item` in a format other than HTML, as resolved into its
+/// `alternates`.
+pub fn alternate_outputs(item: &Item) -> Result<Vec<AlternateOutput>> {
+    let alternates = match item.metadata.get(Alternates) {
+        Some(Ok(alternates)) => alternates,
+        Some(Err(v)) => return Err(v.type_err(Alternates, "invalid alternates")),
+        None => return Ok(vec![]),
+    };
+
+    alternates.iter()
+        .map(|(format, alternate)| {
+            let field = |key| alternate.as_dict().and_then(|dict| dict.get(key)).cloned();
+            let permapath = field("permapath").and_then(|v| Arc::<Path>::try_from(v).ok());
+            let template = field("template").and_then(|v| Arc::<PathStr>::try_from(v).ok());
+            match (permapath, template) {
+                (Some(permapath), Some(template)) => Ok((format.clone(), permapath, template)),
+                _ => err! {
+                    "invalid alternate output: expected a `permapath` and `template`",
+                    "format" => format,
+                    "path" => item.entry.relative_path().display(),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Computes the absolute canonical URL, including scheme and host, of the
+/// relative `url`. Returns `None` if `root` doesn't have a scheme.
+fn canonical_url(root: &Url, url: &Url) -> Option<UrlBuf> {
+    root.scheme()?;
+
+    let mut canonical = root.to_url_buf();
+    canonical.append(url);
+    Some(canonical)
+}
+
+/// Derives the snippet and word and character counts of a data item from the
+/// text in its metadata `field`, if there is any. The snippet is the text's
+/// first `length` characters, cut at a word boundary, as an HTML paragraph.
+fn summarize_data(item: &Item, field: &str, length: usize) {
+    let Some(text) = item.metadata.get_raw(field) else {
+        return;
+    };
+
+    let Some(text) = text.as_str().map(str::trim) else {
+        return;
+    };
+
+    let prefix = harper::util::truncate(text, length, "");
+    let truncated = matches!(prefix, Cow::Owned(_));
+    let snippet = match prefix.is_empty() {
+        true => String::new(),
+        false => format!("<p>{}</p>", harper::util::escape_html(&prefix)),
+    };
+
+    item.metadata.insert(Snip, &*snippet);
+    item.metadata.insert(SnipTruncated, truncated);
+    item.metadata.insert(WordCount, text.split_whitespace().count());
+    item.metadata.insert(CharCount, text.chars().count());
+}
+
+/// Appends a "read more" link to `url` with the text `text` to `snippet`.
+fn read_more(snippet: &str, url: &Url, text: &str) -> String {
+    format!("{snippet}<a class=\"read-more\" href=\"{}\">{text}</a>", url.as_str())
+}
+
+/// Computes the output path and URL for a `permalink` value. The permalink
+/// must be a URL-safe path without a query or fragment that doesn't escape the
+/// output directory. Permalinks ending in `/` are written to `index.html`.
+fn permalink_paths(permalink: &str) -> Result<(PathBuf, UrlBuf)> {
+    let url = Url::try_new(permalink)
+        .filter(|url| url.scheme().is_none() && !url.contains(['?', '#']))
+        .ok_or_else(|| error! {
+            "permalink must be a URL-safe path",
+            "permalink" => permalink,
+        })?;
+
+    let mut path = PathBuf::new();
+    for segment in url.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return err! {
+                "permalink must not escape the output directory",
+                "permalink" => permalink,
+            },
+            segment => path.push(segment),
+        }
+    }
+
+    if url.ends_with('/') || path.as_os_str().is_empty() {
+        path.push("index.html");
+    }
+
+    Ok((path, url.to_url_buf()))
+}
+
+/// The feed entry for `item`: its `title`, or else its file stem, linking to
+/// its canonical URL or else its URL, with its `date` and `snippet`. Items
+/// without a URL, like data items, and drafts have no entry.
+fn feed_entry(item: &Item) -> Option<FeedEntry> {
+    if item.is_draft() {
+        return None;
+    }
+
+    let title = item.metadata.get_raw("title").and_then(|v| v.into_str().ok());
+    Some(FeedEntry {
+        link: item.metadata.get(Canonical).or_else(|| item.metadata.get(UrlRef))?.ok()?,
+        title: title.unwrap_or_else(|| item.entry.file_stem().into()),
+        date: item.metadata.get_raw("date").and_then(|v| harper::util::timestamp(&v)),
+        summary: item.metadata.get(Snip).and_then(Result::ok),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use harper::url::Url;
+
+    use super::canonical_url;
+
+    fn canonical(root: &str, url: &str) -> Option<String> {
+        canonical_url(Url::new(root), Url::new(url)).map(|url| url.as_str().to_string())
+    }
+
+    #[test]
+    fn canonical_absolute_root() {
+        let url = |root, url| canonical(root, url).unwrap();
+        assert_eq!(url("https://rocket.rs", "guide/"), "https://rocket.rs/guide/");
+        assert_eq!(url("https://rocket.rs/", "guide/"), "https://rocket.rs/guide/");
+        assert_eq!(url("https://rocket.rs/v0.5", "guide/intro/"), "https://rocket.rs/v0.5/guide/intro/");
+        assert_eq!(url("https://rocket.rs/v0.5/", "a.css"), "https://rocket.rs/v0.5/a.css");
+    }
+
+    #[test]
+    fn canonical_relative_root() {
+        assert_eq!(canonical("/", "guide/"), None);
+        assert_eq!(canonical("/v0.5", "guide/"), None);
+    }
+}
@@ -0,0 +1,917 @@
+//! End-to-end tests: each builds a small site in a temporary directory.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A site in the temporary directory, removed when dropped.
+struct SiteDir(PathBuf);
+
+impl Deref for SiteDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for SiteDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for SiteDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Creates a fresh directory named after `name` in the temporary directory
+/// containing `files`, given as `(path, contents)` pairs.
+fn site_dir(name: &str, files: &[(&str, &str)]) -> SiteDir {
+    let root = std::env::temp_dir()
+        .join(format!("mockingbird-test-{}-{name}", std::process::id()));
+
+    let _ = std::fs::remove_dir_all(&root);
+    for (path, contents) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    SiteDir(root)
+}
+
+mod discover {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use harper::markdown::{FrontMatter, Plugin};
+    use harper::templating::minijinja::MiniJinjaEngine;
+    use harper::value::Toml;
+
+    use crate::discover::Mockingbird;
+    use super::site_dir;
+
+    /// The data group, relative to the content root, of the datum at `path`.
+    fn data_group_of(root: &Path, path: &str) -> PathBuf {
+        let mockingbird = Mockingbird::new::<MiniJinjaEngine, _, _>(root, root.join("out")).unwrap();
+        let site = mockingbird.discover().unwrap();
+        let content = &mockingbird.tree[mockingbird.content_root];
+        let datum = mockingbird.tree.get_id(None, path).unwrap();
+
+        site.collections.values()
+            .flat_map(|c| c.data.iter())
+            .find(|(_, data)| data.iter().any(|item| item.entry.id == datum))
+            .map(|(&group, _)| mockingbird.tree[group].path_relative_to(content).unwrap().to_path_buf())
+            .unwrap()
+    }
+
+    #[test]
+    fn deep_datum_groups_by_parent() {
+        let root = site_dir("deep-datum-parent", &[
+            ("content/guide/index.md", "guide"),
+            ("content/guide/a/b/c/data.toml", "x = 1"),
+        ]);
+
+        assert_eq!(data_group_of(&root, "content/guide/a/b/c/data.toml"), Path::new("guide/a/b/c"));
+    }
+
+    #[test]
+    fn deep_datum_groups_by_top_level() {
+        let root = site_dir("deep-datum-top-level", &[
+            ("config.toml", "[content]\ndata_grouping = \"top-level\""),
+            ("content/guide/index.md", "guide"),
+            ("content/guide/a/b/c/data.toml", "x = 1"),
+        ]);
+
+        assert_eq!(data_group_of(&root, "content/guide/a/b/c/data.toml"), Path::new("guide/a"));
+    }
+
+    #[test]
+    fn defaults_cascade() {
+        let root = site_dir("defaults-cascade", &[
+            ("content/_defaults.toml", "author = \"site\"\nlayout = \"wide\""),
+            ("content/guide/index.md", "guide"),
+            ("content/guide/_defaults.toml", "author = \"guide\""),
+            ("content/guide/intro.md", "+++\nlayout = \"narrow\"\n+++\nintro"),
+            ("content/about.md", "about"),
+        ]);
+
+        let mockingbird = Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out")).unwrap();
+        let site = mockingbird.discover().unwrap();
+        let items: Vec<_> = site.collections.values()
+            .flat_map(|c| c.index.iter().chain(c.items.iter()))
+            .collect();
+
+        let item = |path: &str| items.iter()
+            .find(|item| item.entry.relative_path() == Path::new(path))
+            .unwrap_or_else(|| panic!("missing item {path}"));
+
+        let get = |path: &str, key: &str| item(path).metadata.get_raw(key)
+            .and_then(|v| v.as_str().map(String::from));
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(get("content/about.md", "author").as_deref(), Some("site"));
+        assert_eq!(get("content/guide/index.md", "author").as_deref(), Some("guide"));
+        assert_eq!(get("content/guide/intro.md", "author").as_deref(), Some("guide"));
+        assert_eq!(get("content/guide/intro.md", "layout").as_deref(), Some("wide"));
+
+        let intro = item("content/guide/intro.md");
+        let front_matter = "+++\nlayout = \"narrow\"\n+++\n";
+        FrontMatter::new(Toml, &intro.metadata).preprocess(front_matter).unwrap();
+        assert_eq!(get("content/guide/intro.md", "layout").as_deref(), Some("narrow"));
+        assert_eq!(get("content/guide/intro.md", "author").as_deref(), Some("guide"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_utf8_data_group_errors() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = site_dir("non-utf8", &[("content/guide/index.md", "guide")]);
+        let dir = root.join("content/guide").join(OsStr::from_bytes(b"caf\xe9"));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("data.toml"), "x = 1").unwrap();
+
+        let mockingbird = Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out")).unwrap();
+        let error = mockingbird.discover().unwrap_err().to_string();
+        assert!(error.contains("data directory names must be valid UTF-8"), "{error}");
+    }
+
+    #[test]
+    fn menu_resolves_and_sorts() {
+        let root = site_dir("menu", &[
+            ("config.toml", r#"
+                root = "https://example.com"
+
+                [[menu.main]]
+                name = "Guide"
+                path = "guide/index.md"
+                weight = 2
+
+                [[menu.main.children]]
+                name = "Intro"
+                path = "guide/intro.md"
+
+                [[menu.main]]
+                name = "Home"
+                url = "/"
+                weight = 1
+            "#),
+            ("content/guide/index.md", "guide"),
+            ("content/guide/intro.md", "intro"),
+        ]);
+
+        let mockingbird = Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out")).unwrap();
+        let main = &mockingbird.config.settings.menu["main"].0;
+        let names: Vec<_> = main.iter().map(|entry| &*entry.name).collect();
+        assert_eq!(names, ["Home", "Guide"]);
+        assert_eq!(main[1].url.as_ref().unwrap().as_str(), "https://example.com/guide/");
+
+        let intro = &main[1].children.0[0];
+        assert_eq!(intro.url.as_ref().unwrap().as_str(), "https://example.com/guide/intro/");
+
+        let missing = "[[menu.main]]\nname = \"X\"\npath = \"nope.md\"";
+        fs::write(root.join("config.toml"), missing).unwrap();
+        assert!(Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out")).is_err());
+    }
+
+    #[test]
+    fn configurable_dirs_and_config_path() {
+        let root = site_dir("custom-dirs", &[
+            ("site.toml", "[dirs]\ncontent = \"src\"\ntemplates = \"theme/layouts\""),
+            ("src/guide/index.md", "guide"),
+            ("theme/layouts/page.html", "{{ content }}"),
+        ]);
+
+        let build = |config: &str| {
+            let config = Some(Path::new(config));
+            Mockingbird::with_config::<MiniJinjaEngine, _, _>(&root, root.join("out"), config)
+        };
+
+        let mockingbird = build("site.toml").unwrap();
+        assert_eq!(mockingbird.tree[mockingbird.content_root].file_name, "src");
+        let templates = mockingbird.template_root.unwrap();
+        assert!(mockingbird.tree[templates].path.ends_with("theme/layouts"));
+        assert!(mockingbird.asset_root.is_none());
+
+        let site = mockingbird.discover().unwrap();
+        assert_eq!(site.collections.len(), 1);
+        assert!(site.index.contains_key("guide"));
+
+        assert!(build("missing.toml").is_err());
+    }
+
+    #[test]
+    fn config_format_detection() {
+        let root = site_dir("config-formats", &[
+            ("config.yaml", "code:\n  line_numbers: false\nsearch_title: Docs"),
+            ("content/index.md", "home"),
+        ]);
+
+        let mockingbird = Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out")).unwrap();
+        assert!(!mockingbird.config.settings.code.line_numbers);
+        assert_eq!(mockingbird.config.settings.globals["search_title"], "Docs".into());
+
+        fs::write(root.join("config.json"), r#"{"search_title": "API"}"#).unwrap();
+        let error = Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out")).unwrap_err();
+        assert!(error.to_string().contains("config.yaml, config.json"), "{error}");
+
+        fs::remove_file(root.join("config.yaml")).unwrap();
+        let mockingbird = Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out")).unwrap();
+        assert_eq!(mockingbird.config.settings.globals["search_title"], "API".into());
+    }
+
+    #[test]
+    fn passthrough_files_copied_to_output_root() {
+        let redirects = "/old  /new  301\n/{{ x }}  /y\n";
+        let root = site_dir("passthrough", &[
+            ("CNAME", "example.com"),
+            ("content/index.md", "home"),
+            ("content/_redirects", redirects),
+            ("content/_headers", "/*\n  X-Frame-Options: DENY"),
+            ("content/notes.txt", "notes"),
+        ]);
+
+        let (site, _) = crate::run(&root, &root.join("out"), None).unwrap();
+        let out = |path: &str| fs::read_to_string(root.join("out").join(path)).unwrap();
+        assert_eq!(out("_redirects"), redirects);
+        assert_eq!(out("_headers"), "/*\n  X-Frame-Options: DENY");
+        assert_eq!(out("CNAME"), "example.com");
+
+        let items = site.collections.values().flat_map(|c| c.items.iter());
+        let names: Vec<_> = items.map(|item| &*item.entry.file_name).collect();
+        assert_eq!(names, ["notes.txt"]);
+    }
+
+    #[test]
+    fn empty_content_is_configurable() {
+        let root = site_dir("empty-content", &[("assets/style.css", "body {}")]);
+        fs::create_dir_all(root.join("content")).unwrap();
+
+        let error = crate::run(&root, &root.join("out"), None).unwrap_err().to_string();
+        assert!(error.contains("found no content files"), "{error}");
+
+        fs::write(root.join("config.toml"), "[content]\nallow_empty = true").unwrap();
+        let (site, _) = crate::run(&root, &root.join("out"), None).unwrap();
+        assert!(site.collections.is_empty());
+        assert_eq!(fs::read_to_string(root.join("out/style.css")).unwrap(), "body {}");
+
+        fs::remove_dir_all(root.join("content")).unwrap();
+        assert!(crate::run(&root, &root.join("out"), None).is_err());
+    }
+
+    #[test]
+    fn site_root_is_validated_and_normalized() {
+        let root = site_dir("site-root", &[("content/index.md", "hi")]);
+        let load = |url: &str| {
+            fs::write(root.join("config.toml"), format!("root = {url:?}")).unwrap();
+            Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out"))
+                .map(|mockingbird| mockingbird.config.settings.root.to_string())
+                .map_err(|e| e.to_string())
+        };
+
+        assert_eq!(load("https://x/").unwrap(), "https://x");
+        assert_eq!(load("/base/").unwrap(), "/base");
+        assert_eq!(load("base").unwrap(), "/base");
+        assert_eq!(load("/").unwrap(), "/");
+        assert_eq!(load("").unwrap(), "/");
+
+        let error = load("example.com").unwrap_err();
+        assert!(error.contains("https://example.com"), "{error}");
+        assert!(load("https:/x").is_err());
+        assert!(load("/base?q").is_err());
+        assert!(load("/a b").is_err());
+    }
+
+    #[test]
+    fn auto_collections_under_root_index() {
+        let root = site_dir("auto-collections", &[
+            ("config.toml", "[content]\nauto_collections = true"),
+            ("content/index.md", "Home"),
+            ("content/projects/a.md", "A"),
+            ("content/projects/b.md", "B"),
+            ("content/guide/index.md", "Guide"),
+            ("content/guide/intro.md", "Intro"),
+            ("templates/default.html", "{{ content | safe }}"),
+        ]);
+
+        let mockingbird = Mockingbird::new::<MiniJinjaEngine, _, _>(&root, root.join("out")).unwrap();
+        let site = mockingbird.discover().unwrap();
+        let collection = |name: &str| &site.collections[&site.index[name]];
+        assert!(collection("projects").index.is_none());
+        assert_eq!(collection("projects").items.len(), 2);
+        assert!(collection("guide").index.is_some());
+        assert_eq!(collection("guide").items.len(), 1);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let a = fs::read_to_string(root.join("out/projects/a/index.html")).unwrap();
+        assert!(a.contains("<p>A</p>"), "{a}");
+    }
+}
+
+mod render {
+    use std::path::Path;
+
+    use crate::{CharCount, Content, Snip, SnipTruncated, WordCount};
+    use super::site_dir;
+
+    #[test]
+    fn read_more_only_when_truncated() {
+        let root = site_dir("read-more", &[
+            ("config.toml", "[content]\nsummary_length = 10\nread_more = \"More\""),
+            ("content/blog/index.md", "Blog."),
+            ("content/blog/short.md", "Short."),
+            ("content/blog/long.md", "A first paragraph.\n\nA second paragraph."),
+        ]);
+
+        let (site, _) = crate::run(&root, &root.join("out"), None).unwrap();
+        let snippet = |path: &str| site.collections.values()
+            .flat_map(|c| c.items.iter())
+            .find(|item| item.entry.relative_path() == Path::new(path))
+            .and_then(|item| item.metadata.get(Snip))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(&*snippet("content/blog/short.md"), "<p>Short.</p>");
+        assert_eq!(&*snippet("content/blog/long.md"),
+            r#"<p>A first paragraph.</p><a class="read-more" href="/blog/long/">More</a>"#);
+
+    }
+
+    #[test]
+    fn missing_template_warns() {
+        let root = site_dir("missing-template", &[
+            ("content/index.md", "Home"),
+            ("content/style.css", "body {}"),
+        ]);
+
+        let warnings = crate::run(&root, &root.join("out"), None).unwrap().1.warnings;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].to_string().contains("no template found"), "{}", warnings[0]);
+
+        std::fs::create_dir_all(root.join("templates")).unwrap();
+        std::fs::write(root.join("templates/default.html"), "{{ content }}").unwrap();
+        let warnings = crate::run(&root, &root.join("out"), None).unwrap().1.warnings;
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn case_insensitive_templates() {
+        let root = site_dir("case-insensitive-templates", &[
+            ("content/index.md", "Home"),
+            ("content/guide/index.md", "Guide"),
+            ("templates/Index.HTML", "<main>{{ content }}</main>"),
+            ("templates/Guide/Index.html", "<section>{{ content }}</section>"),
+        ]);
+
+        let warnings = crate::run(&root, &root.join("out"), None).unwrap().1.warnings;
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert!(warnings[0].to_string().contains("no template found"), "{}", warnings[0]);
+
+        std::fs::write(root.join("config.toml"), "[content]\ncase_insensitive_templates = true").unwrap();
+        let warnings = crate::run(&root, &root.join("out"), None).unwrap().1.warnings;
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let index = std::fs::read_to_string(root.join("out/index.html")).unwrap();
+        assert!(index.starts_with("<main>"), "{index}");
+        let guide = std::fs::read_to_string(root.join("out/guide/index.html")).unwrap();
+        assert!(guide.starts_with("<section>"), "{guide}");
+    }
+
+    #[test]
+    fn diagnostics_sorted_by_path_and_drafts_noted() {
+        let root = site_dir("diagnostics-sorted", &[
+            ("config.toml", "[build]\nsitemap = false"),
+            ("content/b/index.md", "B"),
+            ("content/a/index.md", "A"),
+            ("content/a/draft.md", "+++\ndraft = true\n+++\nD"),
+        ]);
+
+        let (_, report) = crate::run(&root, &root.join("out"), None).unwrap();
+        let warnings: Vec<_> = report.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert!(warnings[0].contains("content/a/index.md"), "{warnings:?}");
+        assert!(warnings[1].contains("content/b/index.md"), "{warnings:?}");
+
+        assert_eq!(report.notes.len(), 1);
+        let note = report.notes[0].to_string();
+        assert!(note.contains("draft") && note.contains("content/a/draft.md"), "{note}");
+    }
+
+    #[test]
+    fn future_and_expired_items_are_drafts() {
+        let root = site_dir("future-expiry", &[
+            ("config.toml", "[build]\nsitemap = false"),
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/a.md", "+++\ndate = 2000-01-01\n+++\nA"),
+            ("content/notes/b.md", "+++\ndate = \"2999-01-01\"\n+++\nB"),
+            ("content/notes/c.md", "+++\nexpiry_date = 2000-01-01T00:00:00Z\n+++\nC"),
+            ("content/notes/d.md", "+++\ndate = \"someday\"\nexpiry_date = 2999-01-01\n+++\nD"),
+            ("templates/index.html", "{% for i in collection.items %}{{ i.slug }} {% endfor %}"),
+            ("templates/page.html",
+                "{% if previous is defined %}{{ previous.slug }}{% endif %}<\
+                 {{ slug }}>{% if next is defined %}{{ next.slug }}{% endif %}"),
+        ]);
+
+        let (_, report) = crate::run(&root, &root.join("out"), None).unwrap();
+        let out = root.join("out");
+        let read = |path: &str| std::fs::read_to_string(out.join(path)).unwrap();
+        assert_eq!(read("notes/index.html"), "a d ");
+        assert_eq!(read("notes/a/index.html"), "index<a>d");
+        assert_eq!(read("notes/d/index.html"), "a<d>");
+        assert!(!out.join("notes/b").exists() && !out.join("notes/c").exists());
+
+        let notes: Vec<_> = report.notes.iter().map(|n| n.to_string()).collect();
+        assert_eq!(notes.len(), 2, "{notes:?}");
+        assert!(notes[0].contains("future") && notes[0].contains("b.md"), "{notes:?}");
+        assert!(notes[1].contains("expired") && notes[1].contains("c.md"), "{notes:?}");
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].to_string().contains("someday"));
+
+        std::fs::write(root.join("config.toml"), "[build]\nsitemap = false\n[content]\nfuture = true").unwrap();
+        std::fs::remove_dir_all(&out).unwrap();
+        let (_, report) = crate::run(&root, &out, None).unwrap();
+        assert!(report.notes.is_empty(), "{:?}", report.notes);
+        assert_eq!(read("notes/index.html"), "a b c d ");
+        assert_eq!(read("notes/b/index.html"), "a<b>c");
+
+        // Previews with drafts render and list them, and embargoed items too.
+        std::fs::write(root.join("content/notes/e.md"), "+++\ndraft = true\n+++\nE").unwrap();
+        std::fs::write(root.join("config.toml"), "[build]\nsitemap = false\n[content]\ndrafts = true").unwrap();
+        std::fs::remove_dir_all(&out).unwrap();
+        let (_, report) = crate::run(&root, &out, None).unwrap();
+        assert!(report.notes.is_empty(), "{:?}", report.notes);
+        assert_eq!(read("notes/index.html"), "a b c d e ");
+        assert_eq!(read("notes/e/index.html"), "d<e>");
+    }
+
+    #[test]
+    fn feeds_written_and_linked() {
+        let root = site_dir("feeds", &[
+            ("config.toml", "root = \"https://example.com\"\ntitle = \"Site\"\n\
+                [feeds]\nscope = \"both\""),
+            ("content/blog/index.md", "+++\ntitle = \"Blog\"\n+++\n"),
+            ("content/blog/a.md", "+++\ntitle = \"A & B\"\ndate = 2020-01-01\n+++\nA"),
+            ("content/blog/b.md", "+++\ndate = 2021-01-01\n+++\nB"),
+            ("content/blog/c.md", "+++\ndraft = true\n+++\nC"),
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/n.md", "N"),
+            ("templates/index.html",
+                "{{ site.feeds | length }} {{ site.feeds[0].type | safe }} {{ collection.feed | safe }}"),
+            ("templates/page.html", "{{ content }}"),
+        ]);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let read = |path: &str| std::fs::read_to_string(root.join("out").join(path)).unwrap();
+        assert_eq!(read("blog/index.html"),
+            "3 application/rss+xml https://example.com/blog/feed.xml");
+
+        let blog = read("blog/feed.xml");
+        assert!(blog.contains("<title>Blog</title>"), "{blog}");
+        assert!(blog.contains("<atom:link href=\"https://example.com/blog/feed.xml\""), "{blog}");
+        assert!(blog.contains("<title>A &amp; B</title>"), "{blog}");
+        assert!(!blog.contains("blog/c/"), "{blog}");
+        let (a, b) = (blog.find("blog/a/").unwrap(), blog.find("blog/b/").unwrap());
+        assert!(b < a, "newest first: {blog}");
+
+        let site = read("feed.xml");
+        assert!(site.contains("<title>Site</title>"), "{site}");
+        assert!(site.contains("https://example.com/notes/n/") && site.contains("blog/a/"));
+        assert!(read("notes/feed.xml").contains("notes/n/"));
+
+        std::fs::write(root.join("config.toml"), "[feeds]\nformat = \"atom\"\nscope = \"collections\"").unwrap();
+        std::fs::remove_dir_all(root.join("out")).unwrap();
+        let (_, report) = crate::run(&root, &root.join("out"), None).unwrap();
+        assert!(report.warnings.iter().any(|w| w.to_string().contains("no host")));
+        let atom = read("blog/atom.xml");
+        assert!(atom.contains("<link href=\"/blog/atom.xml\" rel=\"self\""), "{atom}");
+        assert!(atom.contains("<updated>2021-01-01T00:00:00+00:00</updated>"), "{atom}");
+        assert!(atom.contains("<author>\n<name>Blog</name>\n</author>"), "{atom}");
+        assert!(!root.join("out/feed.xml").exists() && !root.join("out/atom.xml").exists());
+
+        std::fs::write(root.join("config.toml"), "root = \"https://example.com\"\n\
+            [feeds]\nscope = \"collections\"\nfile_name = \"rss.xml\"\nomit_undated = true").unwrap();
+        std::fs::remove_dir_all(root.join("out")).unwrap();
+        let (_, report) = crate::run(&root, &root.join("out"), None).unwrap();
+        assert!(report.warnings.is_empty());
+        let rss = read("blog/rss.xml");
+        assert!(rss.contains("<atom:link href=\"https://example.com/blog/rss.xml\""), "{rss}");
+        assert!(rss.contains("blog/a/") && rss.contains("blog/b/"), "{rss}");
+        assert!(!read("notes/rss.xml").contains("<item>"));
+        assert!(!root.join("out/blog/feed.xml").exists());
+    }
+
+    #[test]
+    fn sitemap_lists_written_pages() {
+        let root = site_dir("sitemap", &[
+            ("config.toml", "root = \"https://example.com/docs\""),
+            ("content/index.md", "Home"),
+            ("content/guide/index.md", "Guide"),
+            ("content/guide/a.md", "A"),
+            ("content/guide/b.md", "+++\ndraft = true\n+++\nB"),
+            ("content/style.css", "body {}"),
+            ("templates/default.html", "{{ content }}"),
+        ]);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let sitemap = std::fs::read_to_string(root.join("out/sitemap.xml")).unwrap();
+        let locs: Vec<_> = sitemap.lines()
+            .filter_map(|line| line.strip_prefix("<loc>")?.strip_suffix("</loc>"))
+            .collect();
+
+        assert_eq!(locs, [
+            "https://example.com/docs/",
+            "https://example.com/docs/guide/",
+            "https://example.com/docs/guide/a/",
+        ]);
+
+        assert_eq!(sitemap.matches("<lastmod>").count(), 3, "{sitemap}");
+
+        std::fs::write(root.join("config.toml"), "").unwrap();
+        std::fs::remove_dir_all(root.join("out")).unwrap();
+        let (_, report) = crate::run(&root, &root.join("out"), None).unwrap();
+        assert!(!root.join("out/sitemap.xml").exists());
+        let notes: Vec<_> = report.notes.iter().map(|n| n.to_string()).collect();
+        assert!(notes.iter().any(|n| n.contains("sitemap not written")), "{notes:?}");
+
+        std::fs::write(root.join("config.toml"), "[build]\nsitemap = false").unwrap();
+        std::fs::remove_dir_all(root.join("out")).unwrap();
+        let (_, report) = crate::run(&root, &root.join("out"), None).unwrap();
+        assert!(!root.join("out/sitemap.xml").exists());
+        assert!(report.notes.iter().all(|n| !n.to_string().contains("sitemap")));
+    }
+
+    #[test]
+    fn paginated_index_pages() {
+        let root = site_dir("paginate", &[
+            ("config.toml", "root = \"https://example.com/docs\""),
+            ("content/blog/index.md", "+++\npaginate = 2\n+++\nBlog"),
+            ("content/blog/a.md", "A"),
+            ("content/blog/b.md", "B"),
+            ("content/blog/c.md", "+++\ndraft = true\n+++\nC"),
+            ("content/blog/d.md", "D"),
+            ("content/blog/e.md", "E"),
+            ("content/blog/f.md", "F"),
+            ("templates/index.html", concat!(
+                "{% autoescape false %}{{ page }}/{{ total_pages }}: ",
+                "{% for item in page_items %}{{ item.url }} {% endfor %}",
+                "[{{ prev_page_url | default }}] [{{ next_page_url | default }}] {{ url }}{% endautoescape %}",
+            )),
+            ("templates/page.html", "{{ content }}"),
+        ]);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let read = |path: &str| std::fs::read_to_string(root.join("out").join(path)).unwrap();
+        let url = |path: &str| format!("https://example.com/docs/blog/{path}");
+
+        assert_eq!(read("blog/index.html"), format!(
+            "1/3: {} {} [] [{}] {}", url("a/"), url("b/"), url("page/2/"), url(""),
+        ));
+
+        assert_eq!(read("blog/page/2/index.html"), format!(
+            "2/3: {} {} [{}] [{}] {}", url("d/"), url("e/"), url(""), url("page/3/"), url("page/2/"),
+        ));
+
+        assert_eq!(read("blog/page/3/index.html"), format!(
+            "3/3: {} [{}] [] {}", url("f/"), url("page/2/"), url("page/3/"),
+        ));
+
+        assert!(!root.join("out/blog/page/4").exists());
+        let sitemap = read("sitemap.xml");
+        assert!(sitemap.contains(&format!("<loc>{}</loc>", url("page/3/"))), "{sitemap}");
+    }
+
+    #[test]
+    fn data_items_summarized_from_field() {
+        let root = site_dir("data-summary", &[
+            ("config.toml", "[content]\nsummary_length = 12\ndata_summary_from = \"bio\""),
+            ("content/team/index.md", "Team."),
+            ("content/team/ann.toml", "bio = \"Fish & chips are served daily.\""),
+            ("content/team/bob.json", r#"{"bio": "Bob tests."}"#),
+            ("content/team/cat.toml", "name = \"Cat\""),
+        ]);
+
+        let (site, _) = crate::run(&root, &root.join("out"), None).unwrap();
+        let item = |path: &str| site.collections.values()
+            .flat_map(|c| c.items.iter())
+            .find(|item| item.entry.relative_path() == Path::new(path))
+            .unwrap()
+            .clone();
+
+        let ann = item("content/team/ann.toml");
+        assert_eq!(&*ann.metadata.get(Snip).unwrap().unwrap(), "<p>Fish &amp; chips</p>");
+        assert_eq!(ann.metadata.get(SnipTruncated), Some(Ok(true)));
+        assert_eq!(ann.metadata.get(WordCount), Some(Ok(6)));
+        assert_eq!(ann.metadata.get(CharCount), Some(Ok(30)));
+
+        let bob = item("content/team/bob.json");
+        assert_eq!(&*bob.metadata.get(Snip).unwrap().unwrap(), "<p>Bob tests.</p>");
+        assert_eq!(bob.metadata.get(SnipTruncated), Some(Ok(false)));
+
+        assert!(item("content/team/cat.toml").metadata.get(Snip).is_none());
+    }
+
+    #[test]
+    fn binary_assets_copied_verbatim() {
+        let root = site_dir("binary-assets", &[("content/index.md", "Home")]);
+        let bytes: Vec<u8> = (0..=255).cycle().take(64 * 1024).collect();
+        std::fs::create_dir_all(root.join("assets/media")).unwrap();
+        std::fs::write(root.join("assets/media/blob.bin"), &bytes).unwrap();
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        assert_eq!(std::fs::read(root.join("out/media/blob.bin")).unwrap(), bytes);
+    }
+
+    #[test]
+    fn sass_imports_from_include_paths() {
+        let root = site_dir("sass-include", &[
+            ("config.toml", "[sass]\ninclude_paths = [\"sass\"]"),
+            ("content/index.md", "Home"),
+            ("sass/_colors.scss", "$primary: red;"),
+            ("assets/a.scss", "@use \"colors\";\nh1 { color: colors.$primary; }"),
+            ("assets/css/b.scss", "@import \"colors\";\np { color: $primary; }"),
+        ]);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let read = |path: &str| std::fs::read_to_string(root.join("out").join(path)).unwrap();
+        assert!(read("a.css").contains("color: red"));
+        assert!(read("css/b.css").contains("color: red"));
+
+        std::fs::write(root.join("assets/a.scss"), "@use \"missing\";").unwrap();
+        let error = crate::run(&root, &root.join("out"), None).unwrap_err().to_string();
+        assert!(error.contains("import: missing"), "{error}");
+        assert!(error.contains(&root.join("sass").display().to_string()), "{error}");
+    }
+
+    #[test]
+    fn fence_label_aliases() {
+        let config = "[code]\nmode = \"fallback\"\n[highlight.aliases]\nferris = \"rust\"";
+        let root = site_dir("fence-aliases", &[
+            ("config.toml", config),
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/code.md", "```ferris\nfn main() {}\n```\n\n```mystery\nx\n```"),
+        ]);
+
+        let (site, _) = crate::run(&root, &root.join("out"), None).unwrap();
+        let item = site.collections.values()
+            .flat_map(|c| c.items.iter())
+            .find(|item| item.entry.file_name == "code.md")
+            .unwrap();
+
+        let content = item.metadata.get(Content).unwrap().unwrap();
+        assert!(content.starts_with("<div class=\"code\""), "{content}");
+        assert!(!content.contains("language-ferris"), "{content}");
+        assert!(content.contains("<pre><code class=\"language-mystery\">x\n</code></pre>"), "{content}");
+    }
+
+    #[test]
+    fn items_render_in_multiple_output_formats() {
+        let root = site_dir("output-formats", &[
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/both.md", "+++\ntitle = \"Both\"\noutputs = [\"html\", \"json\"]\n+++\nHi"),
+            ("content/notes/only.md", "+++\ntitle = \"Only\"\noutputs = [\"json\"]\n+++\nHi"),
+            ("templates/page.html", "{{ title }} {{ alternates.json.url | split('/') | last }}"),
+            ("templates/page.json", "{\"title\": \"{{ title }}\", \"url\": \"{{ url }}\"}"),
+        ]);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let read = |path: &str| std::fs::read_to_string(root.join("out").join(path)).unwrap();
+        assert_eq!(read("notes/both/index.html"), "Both index.json");
+        assert!(read("notes/both/index.json").ends_with("notes/both/\"}"));
+        assert!(read("notes/only/index.json").ends_with("notes/only/index.json\"}"));
+        assert!(!root.join("out/notes/only/index.html").exists());
+
+        std::fs::write(root.join("content/notes/bad.md"), "+++\noutputs = [\"amp\"]\n+++\n").unwrap();
+        let error = crate::run(&root, &root.join("out"), None).unwrap_err().to_string();
+        assert!(error.contains("unknown output format"), "{error}");
+    }
+
+    #[test]
+    fn metadata_dumps_as_sorted_json() {
+        let root = site_dir("dump-metadata", &[
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/a.md", "+++\ntitle = \"A\"\ntags = [\"x\"]\n+++\nHello"),
+            ("templates/page.html", "{{ content }}"),
+        ]);
+
+        let (site, _) = crate::run(&root, &root.join("out"), None).unwrap();
+        let json = crate::dump_metadata(&site, "notes/a.md".as_ref()).unwrap();
+        assert!(json.contains("\"title\": \"A\""), "{json}");
+        assert!(json.contains("\"content\": \"<p>Hello</p>\\n\""), "{json}");
+        assert!(json.find("\"content\"").unwrap() < json.find("\"title\"").unwrap());
+
+        let error = crate::dump_metadata(&site, "notes/missing.md".as_ref()).unwrap_err();
+        assert!(error.to_string().contains("no content item found"), "{error}");
+    }
+
+    #[test]
+    fn index_can_render_as_page() {
+        let root = site_dir("index-as-page", &[
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/a.md", "A"),
+            ("content/about/index.md", "+++\nlist = false\n+++\nAbout."),
+            ("content/about/team.md", "Team"),
+            ("templates/index.html", "listing: {{ collection.count }}"),
+            ("templates/page.html", "page: {{ content | safe }}"),
+        ]);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let read = |path: &str| std::fs::read_to_string(root.join("out").join(path)).unwrap();
+        assert_eq!(read("notes/index.html"), "listing: 1");
+        assert_eq!(read("about/index.html"), "page: <p>About.</p>\n");
+        assert_eq!(read("about/team/index.html"), "page: <p>Team</p>\n");
+
+        std::fs::write(root.join("content/about/index.md"), "+++\nlist = \"no\"\n+++\n").unwrap();
+        assert!(crate::run(&root, &root.join("out"), None).is_err());
+    }
+
+    #[test]
+    fn unwritten_content_is_reported() {
+        let root = site_dir("unwritten", &[
+            ("content/guide/index.mdx", "# Guide"),
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/a.md", "A"),
+            ("content/notes/b.md", "+++\noutputs = [\"json\"]\n+++\nB"),
+            ("content/notes/c.md", "+++\ndraft = true\noutputs = [\"json\"]\n+++\nC"),
+            ("content/notes/d.mdx", "# D"),
+            ("content/notes/logo.svg", "<svg></svg>"),
+            ("content/people/index.md", "People."),
+            ("content/people/ann.toml", "name = \"Ann\""),
+            ("templates/notes/page.html", "{{ content }}"),
+        ]);
+
+        let warnings = crate::run(&root, &root.join("out"), None).unwrap().1.warnings;
+        let warning = |message: &str| warnings.iter()
+            .map(|w| w.to_string())
+            .find(|w| w.contains(message))
+            .unwrap_or_else(|| panic!("no {message:?} warning in {warnings:?}"));
+
+        let none = warning("neither rendered");
+        assert!(none.contains("paths: content/notes/b.md\n"), "{none}");
+        let unknown = warning("unknown extensions");
+        assert!(unknown.contains("paths: content/guide/index.mdx, content/notes/d.mdx\n"), "{unknown}");
+        let data = warning("data files without a template");
+        assert!(data.contains("paths: content/people/ann.toml\n"), "{data}");
+        assert!(warnings.iter().all(|w| !w.to_string().contains("svg")), "{warnings:?}");
+    }
+
+    #[test]
+    fn parts_and_content_in_templates() {
+        let root = site_dir("parts", &[
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/a.md", "One.\n\n===\n\nTwo.\n"),
+            ("templates/page.html", "{{ parts | length }}|{{ parts[1] | safe }}|{{ content | safe }}"),
+        ]);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let html = std::fs::read_to_string(root.join("out/notes/a/index.html")).unwrap();
+        assert_eq!(html, "2|<p>Two.</p>\n|<p>One.</p>\n<p>Two.</p>\n");
+    }
+
+    #[test]
+    fn injected_values_and_build_info() {
+        let config = "[inject.values]\nedition = 2\ntitle = \"Injected\"";
+        let root = site_dir("inject", &[
+            ("config.toml", config),
+            ("content/notes/index.md", "Notes."),
+            ("content/notes/a.md", "+++\ntitle = \"Mine\"\n+++\nA"),
+            ("templates/page.html", "{{ title }} {{ edition }} {{ build.timestamp > 0 }}"),
+        ]);
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let html = std::fs::read_to_string(root.join("out/notes/a/index.html")).unwrap();
+        assert_eq!(html, "Mine 2 true");
+
+        std::fs::write(root.join("config.toml"), format!("{config}\n[inject]\noverwrite = true")).unwrap();
+        crate::run(&root, &root.join("out"), None).unwrap();
+        let html = std::fs::read_to_string(root.join("out/notes/a/index.html")).unwrap();
+        assert_eq!(html, "Injected 2 true");
+    }
+}
+
+mod compress {
+    use std::io::Read;
+
+    use super::site_dir;
+
+    #[test]
+    fn precompress_large_text_outputs() {
+        let page = "Lorem ipsum dolor sit amet. ".repeat(100);
+        let root = site_dir("precompress", &[
+            ("config.toml", "[build]\nprecompress = [\"gzip\", \"brotli\"]\nmin_size = 1024"),
+            ("content/index.md", "Home"),
+            ("content/page.md", &page),
+            ("assets/image.png", &page),
+        ]);
+
+        let output = root.join("out");
+        crate::run(&root, &output, None).unwrap();
+
+        let gzip = std::fs::read(output.join("page/index.html.gz")).unwrap();
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(&gzip[..]).read_to_string(&mut decompressed).unwrap();
+        assert_eq!(decompressed, std::fs::read_to_string(output.join("page/index.html")).unwrap());
+
+        assert!(output.join("page/index.html.br").exists());
+        assert!(!output.join("index.html.gz").exists());
+        assert!(!output.join("image.png.gz").exists());
+    }
+}
+
+mod incremental {
+    use std::fs;
+
+    use crate::incremental::CACHE_FILE;
+    use super::site_dir;
+
+    #[test]
+    fn unchanged_outputs_are_skipped() {
+        let root = site_dir("incremental", &[
+            ("config.toml", "[build]\nincremental = true"),
+            ("content/index.md", "Home"),
+            ("content/a.md", "A"),
+            ("content/b.md", "B"),
+            ("assets/style.css", "body {}"),
+            ("templates/base.html", "<main>{% block main %}{% endblock %}</main>"),
+            ("templates/index.html", "{% for item in collection.items %}{{ item.url }}={{ item.content | safe }}{% endfor %}"),
+            ("templates/page.html", "{% extends \"base.html\" %}{% block main %}{{ content | safe }}{% endblock %}"),
+        ]);
+
+        let out = |path: &str| root.join("out").join(path);
+        let mark = |path: &str| fs::write(out(path), "stale").unwrap();
+        let read = |path: &str| fs::read_to_string(out(path)).unwrap();
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        assert!(out(harper::manifest::MANIFEST_FILE).exists());
+        assert_eq!(read("a/index.html"), "<main><p>A</p>\n</main>");
+
+        // Nothing changed: nothing is written again.
+        for path in ["index.html", "a/index.html", "b/index.html", "style.css"] {
+            mark(path);
+        }
+
+        crate::run(&root, &root.join("out"), None).unwrap();
+        for path in ["index.html", "a/index.html", "b/index.html", "style.css"] {
+            assert_eq!(read(path), "stale", "{path}");
+        }
+
+        // A changed item is rendered and written again along with its
+        // collection's index. Unchanged items are restored from the cache.
+        let cache = fs::read_to_string(out(CACHE_FILE)).unwrap();
+        fs::write(out(CACHE_FILE), cache.replace("<p>B</p>", "<p>cached B</p>")).unwrap();
+        fs::write(root.join("content/a.md"), "A2").unwrap();
+        crate::run(&root, &root.join("out"), None).unwrap();
+        assert_eq!(read("a/index.html"), "<main><p>A2</p>\n</main>");
+        assert!(read("index.html").contains("=<p>cached B</p>"), "{}", read("index.html"));
+        assert_eq!(read("b/index.html"), "stale");
+
+        // A changed base template invalidates every page extending it, but
+        // the pages' content needn't be rendered again.
+        fs::write(root.join("templates/base.html"), "<div>{% block main %}{% endblock %}</div>").unwrap();
+        crate::run(&root, &root.join("out"), None).unwrap();
+        assert_eq!(read("b/index.html"), "<div><p>cached B</p>\n</div>");
+
+        // A missing output is written again.
+        fs::remove_file(out("style.css")).unwrap();
+        crate::run(&root, &root.join("out"), None).unwrap();
+        assert_eq!(read("style.css"), "body {}");
+    }
+}
+
+mod watch {
+    use std::ops::ControlFlow;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::site_dir;
+
+    #[test]
+    fn changes_are_debounced_and_output_ignored() {
+        let root = site_dir("watch", &[("content/index.md", "Home")]);
+        std::fs::create_dir_all(root.join("out")).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let thread_root = root.to_path_buf();
+        std::thread::spawn(move || {
+            crate::watch::watch(&thread_root, &thread_root.join("out"), |paths| {
+                tx.send(paths.to_vec()).unwrap();
+                ControlFlow::Break(())
+            })
+        });
+
+        // The watcher may not be running yet: keep writing until it notices.
+        let paths = loop {
+            std::fs::write(root.join("out/index.html"), "stale").unwrap();
+            std::fs::write(root.join("content/a.md"), "A").unwrap();
+            std::fs::write(root.join("content/index.md"), "Home!").unwrap();
+            if let Ok(paths) = rx.recv_timeout(Duration::from_millis(500)) {
+                break paths;
+            }
+        };
+
+        assert!(paths.iter().any(|p| p.ends_with("content/a.md")), "{paths:?}");
+        assert!(paths.iter().all(|p| !p.ends_with("out/index.html")), "{paths:?}");
+    }
+}
@@ -54,6 +54,19 @@ pub fn dircheck<P: AsRef<Path>>(
     }
 }
 
+/// The hash of the `HEAD` commit of the git repository containing `dir`, if
+/// there is one and `git` is installed.
+pub fn git_commit(dir: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !commit.is_empty()).then_some(commit)
+}
+
 #[macro_export]
 macro_rules! time {
     ($e:expr) => {{
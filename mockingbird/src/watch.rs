@@ -0,0 +1,94 @@
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use harper::Site;
+use harper::error;
+use harper::error::{Chainable, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// How long the input must go without changes before a rebuild starts, so
+/// that an editor's burst of writes on save triggers one rebuild.
+pub const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Builds the site in `input` into `output`, as `mockingbird build` does,
+/// printing the build's diagnostics, or the error that failed it, instead of
+/// returning them so that a long-running process can carry on. Returns the
+/// site if the build succeeded.
+pub fn rebuild(
+    input: &Path,
+    output: &Path,
+    config: Option<&Path>,
+    future: bool,
+    quiet: bool,
+) -> Option<Arc<Site>> {
+    let start = Instant::now();
+    match crate::build_with(input, output, config, future) {
+        Ok((site, report)) => {
+            crate::print_report(&report, quiet);
+            if !quiet {
+                eprintln!("built in {:?}", start.elapsed());
+            }
+
+            Some(site)
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            None
+        }
+    }
+}
+
+/// Watches `input` recursively, calling `on_change` with the paths changed
+/// since its last call once no change has been seen for [`DEBOUNCE`]. Changes
+/// within `output`, which may be inside `input`, are ignored. Returns when
+/// `on_change` breaks or if the watcher stops.
+pub fn watch<F>(input: &Path, output: &Path, mut on_change: F) -> Result<()>
+    where F: FnMut(&[PathBuf]) -> ControlFlow<()>
+{
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let (input, output) = (canonical(input), canonical(output));
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(Box::new)
+        .chain_with(|| error!("failed to start file watcher"))?;
+
+    watcher.watch(&input, RecursiveMode::Recursive)
+        .map_err(Box::new)
+        .chain_with(|| error! {
+            "failed to watch input directory",
+            "path" => input.display(),
+        })?;
+
+    let mut changed: Vec<PathBuf> = vec![];
+    loop {
+        let event = match changed.is_empty() {
+            true => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            false => rx.recv_timeout(DEBOUNCE),
+        };
+
+        match event {
+            Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => {
+                let paths = event.paths.into_iter().filter(|path| !path.starts_with(&output));
+                for path in paths {
+                    if !changed.contains(&path) {
+                        changed.push(path);
+                    }
+                }
+            }
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => eprintln!("warning: file watcher error: {e}"),
+            Err(RecvTimeoutError::Timeout) => {
+                if on_change(&changed).is_break() {
+                    return Ok(());
+                }
+
+                changed.clear();
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}